@@ -1,22 +1,69 @@
-use anyhow::{anyhow, Context, Result};
+use crate::key_log::KEY_LOG;
+use anyhow::{anyhow, bail, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine};
 use http::uri::Authority;
 use moka::future::Cache;
+use p12_keystore::{KeyStore, KeyStoreEntry, PrivateKeyChain};
 use rand::{rngs::OsRng, thread_rng, Rng};
 use rcgen::{
     BasicConstraints, Certificate, CertificateParams, DnType, ExtendedKeyUsagePurpose, Ia5String,
-    IsCa, KeyPair, KeyUsagePurpose, SanType,
+    IsCa, KeyPair, KeyUsagePurpose, SanType, PKCS_ECDSA_P256_SHA256,
 };
-use rsa::{pkcs8::EncodePrivateKey, RsaPrivateKey};
-use std::{fs, io::Cursor, path::Path, sync::Arc};
+use rsa::{
+    pkcs1::DecodeRsaPrivateKey,
+    pkcs8::{
+        der::{asn1::AnyRef, Decode, Encode},
+        AlgorithmIdentifierRef, EncodePrivateKey, ObjectIdentifier, PrivateKeyInfo,
+    },
+    RsaPrivateKey,
+};
+use serde::Deserialize;
+use std::{fs, future::Future, net::IpAddr, path::Path, sync::Arc};
 use time::{Duration, OffsetDateTime};
 use tokio_rustls::rustls::{
     pki_types::{CertificateDer, PrivateKeyDer},
     ServerConfig,
 };
+use x509_parser::parse_x509_certificate;
+
+pub const CA_CERT_FILENAME: &str = "ez-shark-ca-cert.cer";
+pub const PRIVATE_KEY_FILENAME: &str = "ez-shark-key.pem";
 
 const TTL_SECS: i64 = 365 * 24 * 60 * 60;
 const CACHE_TTL: u64 = TTL_SECS as u64 / 2;
+const CACHE_SIZE: u64 = 1_000;
 const NOT_BEFORE_OFFSET: i64 = 60;
+const CA_VALIDITY_DAYS: u32 = 3650;
+/// CA 生效时间前推的天数，避免设备时钟偏差导致根证书尚未生效
+const CA_BACKDATE_DAYS: i64 = 3650;
+/// id-ecPublicKey
+const EC_PUBLIC_KEY_OID: ObjectIdentifier = ObjectIdentifier::new_unwrap("1.2.840.10045.2.1");
+
+/// CA 私钥类型
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CaKeyType {
+    #[default]
+    Rsa2048,
+    EcdsaP256,
+}
+
+/// CA 导出格式，PKCS#12 包含私钥，必须设置密码
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CaExportFormat {
+    Pem,
+    Der,
+    Pkcs12,
+}
+
+/// 导入的 CA 来源
+pub enum CaSource<'a> {
+    /// PEM 或 DER 编码的证书，以及 PEM 编码的私钥（PKCS#8、PKCS#1 或 SEC1）
+    Pem { cert: &'a [u8], key: &'a [u8] },
+    /// 同时包含证书和私钥的 PKCS#12 文件，例如 Charles 导出的根证书
+    Pkcs12 { data: &'a [u8], password: &'a str },
+}
 
 pub fn init_ca<T: AsRef<Path>>(
     ca_cert_file: T,
@@ -25,22 +72,12 @@ pub fn init_ca<T: AsRef<Path>>(
     let ca_cert_file = ca_cert_file.as_ref();
     let private_key_file = private_key_file.as_ref();
     let (private_key, ca_cert, ca_data) = if !ca_cert_file.exists() {
-        let private_key = gen_private_key().with_context(|| "Failed to generate private key")?;
-        let ca_cert =
-            gen_ca_cert(&private_key).with_context(|| "Failed to generate CA certificate")?;
-        fs::write(ca_cert_file, ca_cert.pem()).with_context(|| {
-            format!(
-                "Failed to save CA certificate to '{}'",
-                ca_cert_file.display()
-            )
-        })?;
-        fs::write(private_key_file, private_key.serialize_pem()).with_context(|| {
-            format!(
-                "Failed to save private key to '{}'",
-                private_key_file.display()
-            )
-        })?;
+        let private_key = gen_private_key(CaKeyType::default())
+            .with_context(|| "Failed to generate private key")?;
+        let ca_cert = gen_ca_cert(&private_key, CA_VALIDITY_DAYS)
+            .with_context(|| "Failed to generate CA certificate")?;
         let ca_data = ca_cert.pem();
+        save_ca(ca_cert_file, private_key_file, &ca_data, &private_key)?;
         (private_key, ca_cert, ca_data)
     } else {
         let private_key_err = || {
@@ -64,100 +101,361 @@ pub fn init_ca<T: AsRef<Path>>(
         (private_key, ca_cert, ca_data)
     };
 
-    let mut private_key_reader = Cursor::new(private_key.serialize_pem());
-    let private_key_der = rustls_pemfile::read_one(&mut private_key_reader)
-        .ok()
-        .flatten()
-        .and_then(|key| match key {
-            rustls_pemfile::Item::Pkcs1Key(key) => Some(PrivateKeyDer::Pkcs1(key)),
-            rustls_pemfile::Item::Pkcs8Key(key) => Some(PrivateKeyDer::Pkcs8(key)),
-            rustls_pemfile::Item::Sec1Key(key) => Some(PrivateKeyDer::Sec1(key)),
-            _ => None,
-        })
-        .ok_or_else(|| anyhow!("Invalid private key"))?;
+    CertificateAuthority::new(private_key, ca_cert, ca_data, CACHE_SIZE)
+}
+
+/// 以新的私钥类型和有效期重新生成 CA，覆盖原有文件
+pub fn rotate_ca<T: AsRef<Path>>(
+    ca_cert_file: T,
+    private_key_file: T,
+    key_type: CaKeyType,
+    validity_days: u32,
+) -> Result<CertificateAuthority> {
+    if validity_days == 0 {
+        bail!("CA validity must be at least one day");
+    }
+    let private_key =
+        gen_private_key(key_type).with_context(|| "Failed to generate private key")?;
+    let ca_cert = gen_ca_cert(&private_key, validity_days)
+        .with_context(|| "Failed to generate CA certificate")?;
+    let ca_data = ca_cert.pem();
+    save_ca(
+        ca_cert_file.as_ref(),
+        private_key_file.as_ref(),
+        &ca_data,
+        &private_key,
+    )?;
+
+    CertificateAuthority::new(private_key, ca_cert, ca_data, CACHE_SIZE)
+}
+
+/// 导入已有的 CA（如公司内部根证书或 Charles 根证书），校验通过后覆盖原有文件
+pub fn import_ca<T: AsRef<Path>>(
+    ca_cert_file: T,
+    private_key_file: T,
+    source: CaSource,
+) -> Result<CertificateAuthority> {
+    let (ca_der, private_key) = match source {
+        CaSource::Pem { cert, key } => (parse_cert(cert)?, parse_private_key(key)?),
+        CaSource::Pkcs12 { data, password } => parse_pkcs12(data, password)?,
+    };
+    validate_ca(&ca_der, &private_key)?;
+
+    let ca_params = CertificateParams::from_ca_cert_der(&ca_der)
+        .with_context(|| "Failed to parse CA certificate")?;
+    let ca_cert = ca_params
+        .self_signed(&private_key)
+        .with_context(|| "Failed to load CA certificate")?;
+    // 保存原始证书而不是重新签名后的证书，保证与已安装到设备上的根证书一致
+    let ca_data = der_to_pem(&ca_der);
+    save_ca(
+        ca_cert_file.as_ref(),
+        private_key_file.as_ref(),
+        &ca_data,
+        &private_key,
+    )?;
+
+    CertificateAuthority::new(private_key, ca_cert, ca_data, CACHE_SIZE)
+}
+
+fn save_ca(
+    ca_cert_file: &Path,
+    private_key_file: &Path,
+    ca_data: &str,
+    private_key: &KeyPair,
+) -> Result<()> {
+    fs::write(ca_cert_file, ca_data).with_context(|| {
+        format!(
+            "Failed to save CA certificate to '{}'",
+            ca_cert_file.display()
+        )
+    })?;
+    fs::write(private_key_file, private_key.serialize_pem()).with_context(|| {
+        format!(
+            "Failed to save private key to '{}'",
+            private_key_file.display()
+        )
+    })?;
+    Ok(())
+}
+
+/// 解析 PEM 或 DER 编码的证书，PEM 中有多个证书时取第一个
+fn parse_cert(data: &[u8]) -> Result<CertificateDer<'static>> {
+    if !data.trim_ascii_start().starts_with(b"-----BEGIN") {
+        return Ok(CertificateDer::from(data.to_vec()));
+    }
+    rustls_pemfile::certs(&mut &data[..])
+        .next()
+        .ok_or_else(|| anyhow!("No certificate found in PEM"))?
+        .with_context(|| "Failed to parse CA certificate")
+}
+
+fn parse_private_key(data: &[u8]) -> Result<KeyPair> {
+    let key = rustls_pemfile::private_key(&mut &data[..])
+        .with_context(|| "Failed to parse private key")?
+        .ok_or_else(|| anyhow!("No private key found in PEM"))?;
+    let key_pair = match key {
+        PrivateKeyDer::Pkcs1(key) => {
+            let key = RsaPrivateKey::from_pkcs1_der(key.secret_pkcs1_der())?;
+            KeyPair::try_from(key.to_pkcs8_der()?.as_bytes())?
+        }
+        PrivateKeyDer::Pkcs8(key) => KeyPair::try_from(key.secret_pkcs8_der())?,
+        PrivateKeyDer::Sec1(key) => KeyPair::try_from(sec1_to_pkcs8(key.secret_sec1_der())?)?,
+        _ => bail!("Unsupported private key format, please convert it to PKCS#8"),
+    };
+    Ok(key_pair)
+}
+
+/// 将 SEC1（"EC PRIVATE KEY"）私钥转换为 PKCS#8，ring 只能加载 PKCS#8
+fn sec1_to_pkcs8(der: &[u8]) -> Result<Vec<u8>> {
+    let ec_key = sec1::EcPrivateKey::from_der(der)
+        .map_err(|err| anyhow!("Failed to parse EC private key: {err}"))?;
+    let curve = ec_key
+        .parameters
+        .and_then(|parameters| parameters.named_curve())
+        .ok_or_else(|| anyhow!("EC private key does not specify a named curve"))?;
+    let key_info = PrivateKeyInfo {
+        algorithm: AlgorithmIdentifierRef {
+            oid: EC_PUBLIC_KEY_OID,
+            parameters: Some(AnyRef::from(&curve)),
+        },
+        private_key: der,
+        public_key: None,
+    };
+    key_info
+        .to_der()
+        .map_err(|err| anyhow!("Failed to convert EC private key: {err}"))
+}
+
+fn parse_pkcs12(data: &[u8], password: &str) -> Result<(CertificateDer<'static>, KeyPair)> {
+    let keystore = KeyStore::from_pkcs12(data, password)
+        .map_err(|err| anyhow!("Failed to read PKCS#12 file: {err}"))?;
+    let (_, chain) = keystore
+        .private_key_chain()
+        .ok_or_else(|| anyhow!("No private key found in PKCS#12 file"))?;
+    let private_key = KeyPair::try_from(chain.key())?;
+    let ca_der = chain
+        .chain()
+        .first()
+        .ok_or_else(|| anyhow!("No certificate found in PKCS#12 file"))?;
+    Ok((CertificateDer::from(ca_der.as_der().to_vec()), private_key))
+}
+
+/// 校验证书是有效期内的 CA 证书，且与私钥匹配
+fn validate_ca(ca_der: &CertificateDer<'_>, private_key: &KeyPair) -> Result<()> {
+    let (_, x509) =
+        parse_x509_certificate(ca_der).with_context(|| "Failed to parse CA certificate")?;
+    if !x509.is_ca() {
+        bail!("'{}' is not a CA certificate", x509.subject());
+    }
+    if !x509.validity().is_valid() {
+        bail!(
+            "CA certificate '{}' is expired or not yet valid",
+            x509.subject()
+        );
+    }
+    if x509.public_key().raw != private_key.public_key_der().as_slice() {
+        bail!("Private key does not match the CA certificate");
+    }
+    Ok(())
+}
 
-    let ca = CertificateAuthority::new(private_key, private_key_der, ca_cert, ca_data, 1_000);
-    Ok(ca)
+fn der_to_pem(der: &[u8]) -> String {
+    let encoded = STANDARD.encode(der);
+    let mut pem = String::from("-----BEGIN CERTIFICATE-----\n");
+    for line in encoded.as_bytes().chunks(64) {
+        pem.push_str(std::str::from_utf8(line).unwrap());
+        pem.push('\n');
+    }
+    pem.push_str("-----END CERTIFICATE-----\n");
+    pem
 }
 
 pub struct CertificateAuthority {
     private_key: KeyPair,
-    private_key_der: PrivateKeyDer<'static>,
     ca_cert: Certificate,
     ca_data: String,
-    cache: Cache<Authority, Arc<ServerConfig>>,
+    /// 所有叶子证书共用的 ECDSA P-256 密钥
+    leaf_key: KeyPair,
+    leaf_key_der: PrivateKeyDer<'static>,
+    /// 以证书名称（主机名或通配符域名）为 key，不区分端口
+    cache: Cache<String, Arc<ServerConfig>>,
 }
 
 impl CertificateAuthority {
     pub fn new(
         private_key: KeyPair,
-        private_key_der: PrivateKeyDer<'static>,
         ca_cert: Certificate,
         ca_data: String,
         cache_size: u64,
-    ) -> Self {
-        Self {
+    ) -> Result<Self> {
+        let leaf_key = KeyPair::generate_for(&PKCS_ECDSA_P256_SHA256)
+            .with_context(|| "Failed to generate leaf key")?;
+        let leaf_key_der = PrivateKeyDer::Pkcs8(leaf_key.serialize_der().into());
+
+        Ok(Self {
             private_key,
-            private_key_der,
             ca_cert,
             ca_data,
+            leaf_key,
+            leaf_key_der,
             cache: Cache::builder()
                 .max_capacity(cache_size)
                 .time_to_live(std::time::Duration::from_secs(CACHE_TTL))
                 .build(),
-        }
+        })
     }
 
     pub fn ca_cert_pem(&self) -> String {
         self.ca_data.clone()
     }
 
-    pub async fn gen_server_config(&self, authority: &Authority) -> Result<Arc<ServerConfig>> {
-        if let Some(server_cfg) = self.cache.get(authority).await {
+    pub fn ca_cert_der(&self) -> Result<CertificateDer<'static>> {
+        parse_cert(self.ca_data.as_bytes())
+    }
+
+    /// 按指定格式导出 CA，PKCS#12 使用 `password` 加密
+    pub fn export(&self, format: CaExportFormat, password: &str) -> Result<Vec<u8>> {
+        match format {
+            CaExportFormat::Pem => Ok(self.ca_data.clone().into_bytes()),
+            CaExportFormat::Der => Ok(self.ca_cert_der()?.to_vec()),
+            CaExportFormat::Pkcs12 => {
+                if password.is_empty() {
+                    bail!("A password is required to export PKCS#12");
+                }
+                let ca_cert = p12_keystore::Certificate::from_der(&self.ca_cert_der()?)
+                    .map_err(|err| anyhow!("Failed to encode CA certificate: {err}"))?;
+                let local_key_id: [u8; 20] = thread_rng().gen();
+                let chain =
+                    PrivateKeyChain::new(self.private_key.serialize_der(), local_key_id, [ca_cert]);
+                let mut keystore = KeyStore::new();
+                keystore.add_entry("ez-shark", KeyStoreEntry::PrivateKeyChain(chain));
+                keystore
+                    .writer(password)
+                    .write()
+                    .map_err(|err| anyhow!("Failed to write PKCS#12 file: {err}"))
+            }
+        }
+    }
+
+    /// `wildcard` 为 true 时按父域名签发通配符证书，提高缓存命中率
+    pub async fn gen_server_config(
+        &self,
+        authority: &Authority,
+        wildcard: bool,
+    ) -> Result<Arc<ServerConfig>> {
+        let name = cert_name(authority.host(), wildcard);
+        if let Some(server_cfg) = self.cache.get(&name).await {
             return Ok(server_cfg);
         }
 
-        let certs = vec![self.gen_cert(authority)?];
+        let server_cfg = self.build_server_config(self.gen_cert(&name)?)?;
+
+        self.cache.insert(name, Arc::clone(&server_cfg)).await;
 
+        Ok(server_cfg)
+    }
+
+    /// 复制上游证书的 subject、SAN 和有效期签发叶子证书，仅在缓存未命中时调用 `fetch` 获取上游证书
+    pub async fn gen_mirrored_server_config<F, Fut>(
+        &self,
+        authority: &Authority,
+        fetch: F,
+    ) -> Result<Arc<ServerConfig>>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<CertificateDer<'static>>>,
+    {
+        let key = format!("mirror:{}", cert_name(authority.host(), false));
+        if let Some(server_cfg) = self.cache.get(&key).await {
+            return Ok(server_cfg);
+        }
+
+        let upstream = fetch().await?;
+        let server_cfg = self.build_server_config(self.gen_mirrored_cert(&upstream)?)?;
+
+        self.cache.insert(key, Arc::clone(&server_cfg)).await;
+
+        Ok(server_cfg)
+    }
+
+    fn build_server_config(&self, cert: CertificateDer<'static>) -> Result<Arc<ServerConfig>> {
         let mut server_cfg = ServerConfig::builder()
             .with_no_client_auth()
-            .with_single_cert(certs, self.private_key_der.clone_key())?;
+            .with_single_cert(vec![cert], self.leaf_key_der.clone_key())?;
 
         server_cfg.alpn_protocols =
             vec![b"h2".to_vec(), b"http/1.1".to_vec(), b"http/1.0".to_vec()];
+        server_cfg.key_log = KEY_LOG.clone();
 
-        let server_cfg = Arc::new(server_cfg);
-
-        self.cache
-            .insert(authority.clone(), Arc::clone(&server_cfg))
-            .await;
-
-        Ok(server_cfg)
+        Ok(Arc::new(server_cfg))
     }
 
-    pub fn gen_cert(&self, authority: &Authority) -> Result<CertificateDer<'static>> {
+    /// 签发叶子证书，`name` 为 IP 时使用 IP SAN
+    pub fn gen_cert(&self, name: &str) -> Result<CertificateDer<'static>> {
         let mut params = CertificateParams::default();
         params.serial_number = Some(thread_rng().gen::<u64>().into());
 
         let not_before = OffsetDateTime::now_utc() - Duration::seconds(NOT_BEFORE_OFFSET);
         params.not_before = not_before;
         params.not_after = not_before + Duration::seconds(TTL_SECS);
-        params
-            .distinguished_name
-            .push(DnType::CommonName, authority.host());
-        params
-            .subject_alt_names
-            .push(SanType::DnsName(Ia5String::try_from(authority.host())?));
-
-        let cert = params.signed_by(&self.private_key, &self.ca_cert, &self.private_key)?;
+        params.distinguished_name.push(DnType::CommonName, name);
+        let san = match name.parse::<IpAddr>() {
+            Ok(ip) => SanType::IpAddress(ip),
+            Err(_) => SanType::DnsName(Ia5String::try_from(name)?),
+        };
+        params.subject_alt_names.push(san);
+
+        let cert = params.signed_by(&self.leaf_key, &self.ca_cert, &self.private_key)?;
         let cert_der = cert.der().clone();
         Ok(cert_der)
     }
+
+    /// 签发与上游证书 subject、SAN 列表和有效期一致的叶子证书
+    pub fn gen_mirrored_cert(
+        &self,
+        upstream: &CertificateDer<'_>,
+    ) -> Result<CertificateDer<'static>> {
+        let upstream_params = CertificateParams::from_ca_cert_der(upstream)
+            .with_context(|| "Failed to parse upstream certificate")?;
+
+        let mut params = CertificateParams::default();
+        params.serial_number = Some(thread_rng().gen::<u64>().into());
+        params.distinguished_name = upstream_params.distinguished_name;
+        params.subject_alt_names = upstream_params.subject_alt_names;
+        params.not_before = upstream_params.not_before;
+        params.not_after = upstream_params.not_after;
+        params.key_usages = upstream_params.key_usages;
+        params.extended_key_usages = upstream_params.extended_key_usages;
+
+        let cert = params.signed_by(&self.leaf_key, &self.ca_cert, &self.private_key)?;
+        Ok(cert.der().clone())
+    }
+}
+
+/// 证书名称，同时作为缓存 key
+fn cert_name(host: &str, wildcard: bool) -> String {
+    let host = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_lowercase();
+    if !wildcard || host.parse::<IpAddr>().is_ok() {
+        return host;
+    }
+    match host.split_once('.') {
+        Some((_, parent)) if is_wildcard_parent(parent) => format!("*.{parent}"),
+        _ => host,
+    }
+}
+
+// 父域名本身不能是公共后缀（com、co.uk、github.io 等），否则通配符证书会被客户端拒绝
+fn is_wildcard_parent(parent: &str) -> bool {
+    psl::domain_str(parent).is_some()
 }
 
-fn gen_ca_cert(key: &KeyPair) -> Result<Certificate> {
+fn gen_ca_cert(key: &KeyPair, validity_days: u32) -> Result<Certificate> {
     let mut params = CertificateParams::default();
-    let (yesterday, tomorrow) = validity_period();
+    let (not_before, not_after) = validity_period(validity_days);
     params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
     params
         .distinguished_name
@@ -165,8 +463,8 @@ fn gen_ca_cert(key: &KeyPair) -> Result<Certificate> {
     params
         .distinguished_name
         .push(DnType::OrganizationName, "ez-shark");
-    params.not_before = yesterday;
-    params.not_after = tomorrow;
+    params.not_before = not_before;
+    params.not_after = not_after;
     params
         .extended_key_usages
         .push(ExtendedKeyUsagePurpose::ServerAuth);
@@ -178,7 +476,10 @@ fn gen_ca_cert(key: &KeyPair) -> Result<Certificate> {
     Ok(ca_cert)
 }
 
-fn gen_private_key() -> Result<KeyPair> {
+fn gen_private_key(key_type: CaKeyType) -> Result<KeyPair> {
+    if let CaKeyType::EcdsaP256 = key_type {
+        return Ok(KeyPair::generate_for(&PKCS_ECDSA_P256_SHA256)?);
+    }
     let mut rng = OsRng;
     let bits = 2048;
     let private_key = RsaPrivateKey::new(&mut rng, bits)?;
@@ -187,9 +488,169 @@ fn gen_private_key() -> Result<KeyPair> {
     Ok(private_key)
 }
 
-fn validity_period() -> (OffsetDateTime, OffsetDateTime) {
-    let day = Duration::days(3650);
-    let yesterday = OffsetDateTime::now_utc().checked_sub(day).unwrap();
-    let tomorrow = OffsetDateTime::now_utc().checked_add(day).unwrap();
-    (yesterday, tomorrow)
+/// CA 有效期，生效时间与旧版本一样前推 `CA_BACKDATE_DAYS`，`days` 只决定到期时间
+fn validity_period(days: u32) -> (OffsetDateTime, OffsetDateTime) {
+    let now = OffsetDateTime::now_utc();
+    let not_before = now.checked_sub(Duration::days(CA_BACKDATE_DAYS)).unwrap();
+    let not_after = now.checked_add(Duration::days(days as i64)).unwrap();
+    (not_before, not_after)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use x509_parser::extensions::GeneralName;
+
+    fn test_ca() -> CertificateAuthority {
+        let private_key = gen_private_key(CaKeyType::EcdsaP256).unwrap();
+        let ca_cert = gen_ca_cert(&private_key, CA_VALIDITY_DAYS).unwrap();
+        let ca_data = ca_cert.pem();
+        CertificateAuthority::new(private_key, ca_cert, ca_data, CACHE_SIZE).unwrap()
+    }
+
+    fn temp_ca_files(name: &str) -> (std::path::PathBuf, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("ez-shark-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        (dir.join(CA_CERT_FILENAME), dir.join(PRIVATE_KEY_FILENAME))
+    }
+
+    fn pem(tag: &str, der: &[u8]) -> Vec<u8> {
+        der_to_pem(der).replace("CERTIFICATE", tag).into_bytes()
+    }
+
+    #[test]
+    fn test_ca_validity() {
+        let (cert_file, key_file) = temp_ca_files("validity");
+        let ca = rotate_ca(&cert_file, &key_file, CaKeyType::EcdsaP256, 30).unwrap();
+        let der = ca.ca_cert_der().unwrap();
+        let (_, cert) = parse_x509_certificate(&der).unwrap();
+        let now = OffsetDateTime::now_utc();
+        let not_before = cert.validity().not_before.to_datetime();
+        let not_after = cert.validity().not_after.to_datetime();
+        assert!(not_before < now - Duration::days(CA_BACKDATE_DAYS - 1));
+        assert!(not_after > now + Duration::days(29) && not_after < now + Duration::days(31));
+        assert!(rotate_ca(&cert_file, &key_file, CaKeyType::EcdsaP256, 0).is_err());
+    }
+
+    #[test]
+    fn test_pkcs12_round_trip() {
+        let (cert_file, key_file) = temp_ca_files("pkcs12");
+        let ca = rotate_ca(&cert_file, &key_file, CaKeyType::EcdsaP256, 30).unwrap();
+        assert!(ca.export(CaExportFormat::Pkcs12, "").is_err());
+        let data = ca.export(CaExportFormat::Pkcs12, "secret").unwrap();
+
+        let wrong_password = CaSource::Pkcs12 {
+            data: &data,
+            password: "wrong",
+        };
+        assert!(import_ca(&cert_file, &key_file, wrong_password).is_err());
+
+        let source = CaSource::Pkcs12 {
+            data: &data,
+            password: "secret",
+        };
+        let imported = import_ca(&cert_file, &key_file, source).unwrap();
+        assert_eq!(imported.ca_cert_pem(), ca.ca_cert_pem());
+        assert_eq!(fs::read_to_string(&cert_file).unwrap(), ca.ca_cert_pem());
+        imported.gen_cert("example.com").unwrap();
+    }
+
+    #[test]
+    fn test_pem_round_trip() {
+        let (cert_file, key_file) = temp_ca_files("pem");
+        let ca = rotate_ca(&cert_file, &key_file, CaKeyType::EcdsaP256, 30).unwrap();
+        let key = fs::read(&key_file).unwrap();
+        let der = ca.export(CaExportFormat::Der, "").unwrap();
+        let pem_data = ca.export(CaExportFormat::Pem, "").unwrap();
+
+        for cert in [&der, &pem_data] {
+            let source = CaSource::Pem { cert, key: &key };
+            let imported = import_ca(&cert_file, &key_file, source).unwrap();
+            assert_eq!(imported.ca_cert_pem(), ca.ca_cert_pem());
+        }
+
+        // 私钥与证书不匹配
+        let other = gen_private_key(CaKeyType::EcdsaP256).unwrap();
+        let other_key = other.serialize_pem().into_bytes();
+        let source = CaSource::Pem {
+            cert: &der,
+            key: &other_key,
+        };
+        assert!(import_ca(&cert_file, &key_file, source).is_err());
+    }
+
+    #[test]
+    fn test_import_sec1_key() {
+        let (cert_file, key_file) = temp_ca_files("sec1");
+        let ca = rotate_ca(&cert_file, &key_file, CaKeyType::EcdsaP256, 30).unwrap();
+        let der = ca.export(CaExportFormat::Der, "").unwrap();
+
+        // 把 PKCS#8 私钥拆成带曲线参数的 SEC1 "EC PRIVATE KEY"
+        let pkcs8 = fs::read_to_string(&key_file).unwrap();
+        let pkcs8 = KeyPair::from_pem(&pkcs8).unwrap().serialize_der();
+        let key_info = PrivateKeyInfo::from_der(&pkcs8).unwrap();
+        let curve = key_info.algorithm.parameters_oid().unwrap();
+        let mut ec_key = sec1::EcPrivateKey::from_der(key_info.private_key).unwrap();
+        ec_key.parameters = Some(sec1::EcParameters::NamedCurve(curve));
+        let sec1_key = pem("EC PRIVATE KEY", &ec_key.to_der().unwrap());
+
+        let source = CaSource::Pem {
+            cert: &der,
+            key: &sec1_key,
+        };
+        let imported = import_ca(&cert_file, &key_file, source).unwrap();
+        assert_eq!(imported.ca_cert_pem(), ca.ca_cert_pem());
+    }
+
+    #[test]
+    fn test_cert_name() {
+        assert_eq!(cert_name("www.example.com", true), "*.example.com");
+        assert_eq!(cert_name("API.v2.Example.com", true), "*.v2.example.com");
+        assert_eq!(cert_name("www.bbc.co.uk", true), "*.bbc.co.uk");
+        assert_eq!(cert_name("www.example.com", false), "www.example.com");
+        // 父域名是公共后缀时不使用通配符
+        assert_eq!(cert_name("example.com", true), "example.com");
+        assert_eq!(cert_name("bbc.co.uk", true), "bbc.co.uk");
+        assert_eq!(cert_name("abc.com.cn", true), "abc.com.cn");
+        assert_eq!(cert_name("user.github.io", true), "user.github.io");
+        assert_eq!(cert_name("localhost", true), "localhost");
+        // IP 不使用通配符
+        assert_eq!(cert_name("10.0.0.5", true), "10.0.0.5");
+        assert_eq!(cert_name("[::1]", true), "::1");
+    }
+
+    #[test]
+    fn test_ip_san() {
+        let ca = test_ca();
+        for (name, expected) in [
+            ("10.0.0.5", GeneralName::IPAddress(&[10, 0, 0, 5])),
+            ("*.example.com", GeneralName::DNSName("*.example.com")),
+        ] {
+            let der = ca.gen_cert(name).unwrap();
+            let (_, cert) = parse_x509_certificate(&der).unwrap();
+            let san = cert.subject_alternative_name().unwrap().unwrap();
+            assert_eq!(san.value.general_names, vec![expected]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_server_config_cache_key() {
+        let ca = test_ca();
+        let config = |host: &str, wildcard| {
+            let authority: Authority = host.parse().unwrap();
+            let ca = &ca;
+            async move { ca.gen_server_config(&authority, wildcard).await.unwrap() }
+        };
+
+        // 同一父域名下的主机和不同端口共用通配符证书
+        let a = config("a.example.com:443", true).await;
+        let b = config("b.example.com:8443", true).await;
+        assert!(Arc::ptr_eq(&a, &b));
+
+        let c = config("a.example.com:443", false).await;
+        let d = config("b.example.com:443", false).await;
+        assert!(!Arc::ptr_eq(&a, &c));
+        assert!(!Arc::ptr_eq(&c, &d));
+        assert!(Arc::ptr_eq(&c, &config("a.example.com:8443", false).await));
+    }
 }
@@ -0,0 +1,79 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    sync::{Arc, LazyLock, Mutex},
+};
+
+use log::error;
+use tauri::{AppHandle, Runtime};
+use tokio_rustls::rustls::KeyLog;
+
+use crate::models::ssl_key_log::get_ssl_key_log_config;
+
+/// 客户端侧和上游侧的 TLS 配置共享同一个 key log，输出文件可在运行时切换，
+/// 因此缓存的 ServerConfig 无需重建
+pub(crate) static KEY_LOG: LazyLock<Arc<KeyLogWriter>> = LazyLock::new(Default::default);
+
+#[derive(Debug, Default)]
+struct KeyLogState {
+    path: Option<PathBuf>,
+    file: Option<File>,
+}
+
+/// 以 NSS key log 格式追加写入文件，供 Wireshark 解密抓包
+#[derive(Debug, Default)]
+pub(crate) struct KeyLogWriter(Mutex<KeyLogState>);
+
+impl KeyLogWriter {
+    /// 切换输出文件，为 None 时停止输出
+    pub(crate) fn set_path(&self, path: Option<PathBuf>) {
+        let mut state = self.0.lock().unwrap();
+        if state.path != path {
+            state.path = path;
+            state.file = None;
+        }
+    }
+}
+
+impl KeyLog for KeyLogWriter {
+    fn log(&self, label: &str, client_random: &[u8], secret: &[u8]) {
+        let mut state = self.0.lock().unwrap();
+        let Some(path) = state.path.clone() else {
+            return;
+        };
+        if state.file.is_none() {
+            match OpenOptions::new().create(true).append(true).open(&path) {
+                Ok(file) => state.file = Some(file),
+                Err(err) => {
+                    error!("Failed to open key log file {}: {}", path.display(), err);
+                    return;
+                }
+            }
+        }
+
+        let line = format!("{} {} {}\n", label, to_hex(client_random), to_hex(secret));
+        if let Some(file) = state.file.as_mut() {
+            if let Err(err) = file.write_all(line.as_bytes()) {
+                error!("Failed to write key log file {}: {}", path.display(), err);
+                state.file = None;
+            }
+        }
+    }
+
+    fn will_log(&self, _label: &str) -> bool {
+        self.0.lock().unwrap().path.is_some()
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// 根据当前设置更新 key log 的输出文件
+pub(crate) fn configure_key_log<R: Runtime>(app: &AppHandle<R>) {
+    let path = get_ssl_key_log_config(app)
+        .ok()
+        .and_then(|config| config.output_path());
+    KEY_LOG.set_path(path);
+}
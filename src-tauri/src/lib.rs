@@ -1,19 +1,33 @@
 // #[macro_use]
 // extern crate log;
 pub mod frontend_message;
+pub mod key_log;
 pub mod models;
+pub mod onboarding;
+pub mod pac;
+pub mod proxy_health;
 pub mod rewind;
 pub mod server;
 pub mod state;
+pub mod throttled;
+pub mod timings;
+pub mod tls_info;
 pub mod traffic;
+pub mod upstream_proxy;
+pub mod upstream_tls;
 pub mod utils;
 
 use crate::models::{charles, charles::CharlesConverter};
+use crate::models::ssl_pinning::{get_ssl_pinning_config, save_ssl_pinning_config, PinnedHost};
+use crate::models::{
+    external_proxy::MutableExternalProxyConfiguration, upstream_trust::get_upstream_trust_config,
+};
 use crate::{
-    cert::CertificateAuthority,
+    cert::{CaExportFormat, CaKeyType, CaSource, CertificateAuthority, PRIVATE_KEY_FILENAME},
     server::{PrintMode, Server, ServerBuilder},
     state::{DebuggerCommand, State as TrafficState},
     traffic::{Body, SearchQuery, Traffic, TrafficHead},
+    upstream_proxy::UpstreamProxy,
     utils::serialize_option_datetime,
 };
 use anyhow::Result;
@@ -39,6 +53,8 @@ use tauri_plugin_store::StoreBuilder;
 use time::OffsetDateTime;
 use tokio::sync::{oneshot, Mutex};
 use tokio::{net::TcpListener, time::Duration};
+use timings::Timings;
+use tls_info::TlsSession;
 use traffic::{extract_mime, BodyHex, TransactionState};
 
 const APP_NAME: &str = "ez-shark";
@@ -46,6 +62,16 @@ const APP_NAME: &str = "ez-shark";
 #[derive(Debug, Clone, Serialize)]
 pub struct Overview {
     pub url: String,
+    pub mapped_url: Option<String>,
+    pub rewrites: Vec<String>,
+    pub throttle: Option<String>,
+    pub faults: Vec<String>,
+    pub client_addr: Option<String>,
+    pub connection_id: Option<u64>,
+    pub server_addr: Option<String>,
+    pub bytes_sent: Option<u64>,
+    pub bytes_received: Option<u64>,
+    pub timings: Option<Timings>,
     pub method: String,
     pub status: TransactionState,
     pub code: Option<u16>,
@@ -59,7 +85,13 @@ pub struct Overview {
 #[derive(Debug, Clone, Serialize)]
 pub struct TrafficDetail {
     pub overview: Overview,
+    pub client_tls: Option<TlsSession>,
+    pub server_tls: Option<TlsSession>,
+    pub client_cert: Option<String>,
+    pub upstream_cert_error: Option<String>,
+    pub proxy_route: Option<String>,
     pub req_head_json: Option<String>,
+    pub forwarded_req_head_json: Option<String>,
     pub res_head_json: Option<String>,
     pub req_body_hex: Option<Vec<BodyHex>>,
     pub res_body_hex: Option<Vec<BodyHex>>,
@@ -246,6 +278,18 @@ impl ProxyServer {
         self.start_new_server(port, old_state).await
     }
 
+    // 替换 CA，正在运行的服务会在原端口重启以使用新的 CA 签发证书
+    pub async fn replace_ca(&mut self, ca: CertificateAuthority) -> Result<String, String> {
+        self.ca = Arc::new(ca);
+        if self.server.is_none() {
+            return Ok("Success".to_string());
+        }
+
+        let port = self.current_port;
+        let old_state = self.stop_previous_server().await?;
+        self.start_new_server(port, old_state).await
+    }
+
     // 临时暂停服务（保留状态）
     pub async fn pause_server(&mut self) -> Result<Option<Arc<TrafficState>>, String> {
         self.stop_previous_server().await
@@ -343,6 +387,16 @@ async fn get_traffic_detail(
         let traffic_detail = TrafficDetail {
             overview: Overview {
                 url: traffic.uri.clone(),
+                mapped_url: traffic.mapped_uri.clone(),
+                rewrites: traffic.rewrites.clone(),
+                throttle: traffic.throttle.clone(),
+                faults: traffic.faults.clone(),
+                client_addr: traffic.client_addr.clone(),
+                connection_id: traffic.connection_id,
+                server_addr: traffic.server_addr.clone(),
+                bytes_sent: traffic.bytes_sent,
+                bytes_received: traffic.bytes_received,
+                timings: traffic.timings.clone(),
                 method: traffic.method.clone(),
                 code: traffic.status.clone(),
                 status: traffic.transaction_state.clone(),
@@ -350,7 +404,13 @@ async fn get_traffic_detail(
                 start_time: traffic.start_time.clone(),
                 end_time: traffic.end_time.clone(),
             },
+            client_tls: traffic.client_tls.clone(),
+            server_tls: traffic.server_tls.clone(),
+            client_cert: traffic.client_cert.clone(),
+            upstream_cert_error: traffic.upstream_cert_error.clone(),
+            proxy_route: traffic.proxy_route.clone(),
             req_head_json: traffic.req_head_json(),
+            forwarded_req_head_json: traffic.forwarded_req_head_json(),
             res_head_json: traffic.res_head_json(),
             req_body_hex: traffic.req_body_hex.clone(),
             res_body_hex: traffic.res_body_hex.clone(),
@@ -748,6 +808,98 @@ async fn ez_search(
     }
 }
 
+#[tauri::command]
+async fn get_pinned_hosts(app_handle: tauri::AppHandle) -> Result<Vec<PinnedHost>, String> {
+    let pinning = get_ssl_pinning_config(&app_handle).unwrap_or_default();
+    let mut hosts: Vec<PinnedHost> = pinning.pinned_hosts.into_values().collect();
+    hosts.sort_by(|a, b| a.learned_at.cmp(&b.learned_at));
+    Ok(hosts)
+}
+
+// 清除已识别的证书固定主机，keys 为 host:port，为空时清除全部
+#[tauri::command]
+async fn clear_pinned_hosts(
+    app_handle: tauri::AppHandle,
+    keys: Vec<String>,
+) -> Result<(), String> {
+    let mut pinning = get_ssl_pinning_config(&app_handle).unwrap_or_default();
+    if keys.is_empty() {
+        pinning.pinned_hosts.clear();
+    } else {
+        for key in &keys {
+            pinning.pinned_hosts.remove(key);
+        }
+    }
+    save_ssl_pinning_config(&app_handle, &pinning)
+}
+
+// CA 证书和私钥文件路径，config_dir 实际指向 CA 证书文件
+fn ca_files(config_dir: &Path) -> (PathBuf, PathBuf) {
+    let ca_cert_file = config_dir.to_path_buf();
+    let private_key_file = config_dir.with_file_name(PRIVATE_KEY_FILENAME);
+    (ca_cert_file, private_key_file)
+}
+
+// 以新的私钥类型和有效期重新生成 CA
+#[tauri::command]
+async fn rotate_ca(
+    key_type: CaKeyType,
+    validity_days: u32,
+    config_dir: State<'_, PathBuf>,
+    proxy_server: State<'_, Arc<Mutex<ProxyServer>>>,
+) -> Result<String, String> {
+    let (ca_cert_file, private_key_file) = ca_files(&config_dir);
+    let ca = cert::rotate_ca(&ca_cert_file, &private_key_file, key_type, validity_days)
+        .map_err(|e| format!("{:#}", e))?;
+    proxy_server.lock().await.replace_ca(ca).await
+}
+
+// 导入已有的 CA，key_path 为空时 path 为 PKCS#12 文件
+#[tauri::command]
+async fn import_ca(
+    path: String,
+    key_path: Option<String>,
+    password: Option<String>,
+    config_dir: State<'_, PathBuf>,
+    proxy_server: State<'_, Arc<Mutex<ProxyServer>>>,
+) -> Result<String, String> {
+    let data = fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let key_data = match &key_path {
+        Some(key_path) => {
+            Some(fs::read(key_path).map_err(|e| format!("Failed to read {}: {}", key_path, e))?)
+        }
+        None => None,
+    };
+    let source = match &key_data {
+        Some(key) => CaSource::Pem { cert: &data, key },
+        None => CaSource::Pkcs12 {
+            data: &data,
+            password: password.as_deref().unwrap_or_default(),
+        },
+    };
+
+    let (ca_cert_file, private_key_file) = ca_files(&config_dir);
+    let ca = cert::import_ca(&ca_cert_file, &private_key_file, source)
+        .map_err(|e| format!("{:#}", e))?;
+    proxy_server.lock().await.replace_ca(ca).await
+}
+
+// 导出 CA 到 path，PKCS#12 格式需要设置 password
+#[tauri::command]
+async fn export_ca(
+    format: CaExportFormat,
+    path: String,
+    password: Option<String>,
+    proxy_server: State<'_, Arc<Mutex<ProxyServer>>>,
+) -> Result<String, String> {
+    let ca = Arc::clone(&proxy_server.lock().await.ca);
+    let data = ca
+        .export(format, password.as_deref().unwrap_or_default())
+        .map_err(|e| format!("{:#}", e))?;
+    fs::write(&path, data).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+    Ok("Success".to_string())
+}
+
 #[tauri::command]
 async fn is_charles_running() -> bool {
     charles::is_charles_running()
@@ -758,6 +910,33 @@ async fn kill_charles() -> Result<bool, String> {
     charles::kill_charles_async().await
 }
 
+// 测试代理条目的连通性与认证，通过代理向 target（默认 example.com:443）建立隧道
+#[tauri::command]
+async fn test_external_proxy(
+    app_handle: tauri::AppHandle,
+    proxy_type: String,
+    config: MutableExternalProxyConfiguration,
+    target: Option<String>,
+) -> Result<String, String> {
+    let trust = get_upstream_trust_config(&app_handle).unwrap_or_default();
+    let proxy = UpstreamProxy::from_entry(&proxy_type, &config, &trust)?;
+    let target = target
+        .filter(|target| !target.is_empty())
+        .unwrap_or_else(|| "example.com:443".to_string());
+    let authority: http::uri::Authority = target
+        .parse()
+        .map_err(|e| format!("测试目标 {} 无效: {}", target, e))?;
+    let port = authority.port_u16().unwrap_or(443);
+    let elapsed = proxy_health::test_proxy(&proxy, authority.host(), port).await?;
+    proxy_health::record_success(&proxy);
+    Ok(format!(
+        "Connected to {} via {} in {} ms",
+        target,
+        proxy.label(),
+        elapsed.as_millis()
+    ))
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run(ca: CertificateAuthority, config_dir: PathBuf) {
     let date = Local::now();
@@ -792,6 +971,7 @@ pub fn run(ca: CertificateAuthority, config_dir: PathBuf) {
             app.manage(proxy_server);
 
             cleanup_old_logs(&app.handle());
+            tauri::async_runtime::spawn(proxy_health::run_health_probes(app.handle().clone()));
             Ok(())
         })
         .plugin(
@@ -844,7 +1024,13 @@ pub fn run(ca: CertificateAuthority, config_dir: PathBuf) {
             ez_search,
             delete_traffic,
             is_charles_running,
-            kill_charles
+            kill_charles,
+            get_pinned_hosts,
+            clear_pinned_hosts,
+            rotate_ca,
+            import_ca,
+            export_ca,
+            test_external_proxy
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
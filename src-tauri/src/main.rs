@@ -4,7 +4,7 @@
 // extern crate log;
 
 use anyhow::{anyhow, Result};
-use ez_shark_lib::cert::{init_ca, CertificateAuthority};
+use ez_shark_lib::cert::{init_ca, CertificateAuthority, CA_CERT_FILENAME, PRIVATE_KEY_FILENAME};
 use log::debug;
 // use simplelog::{
 //     format_description, ColorChoice, CombinedLogger, ConfigBuilder, LevelFilter, TermLogger,
@@ -15,9 +15,6 @@ use std::{
     path::{Path, PathBuf},
 };
 
-const CA_CERT_FILENAME: &str = "ez-shark-ca-cert.cer";
-const PRIVATE_KEY_FILENAME: &str = "ez-shark-key.pem";
-
 // todo 去除全局的unwrap 改为在界面报错
 #[tokio::main]
 async fn main() -> Result<()> {
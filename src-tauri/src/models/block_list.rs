@@ -0,0 +1,287 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_store::StoreBuilder;
+
+use super::matcher::RuleMatcher;
+
+/// 拦截后的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BlockAction {
+    /// 返回错误响应
+    #[default]
+    Response,
+    /// 直接断开连接
+    DropConnection,
+}
+
+/// 拦截响应配置，BlockList 与 AllowList 共用
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockResponse {
+    #[serde(default)]
+    pub action: BlockAction,
+    /// 为空或清空（null）时使用 403
+    #[serde(
+        rename = "statusCode",
+        default = "default_status_code",
+        deserialize_with = "deserialize_status_code"
+    )]
+    pub status_code: u16,
+    #[serde(default)]
+    pub body: String,
+}
+
+fn default_status_code() -> u16 {
+    403
+}
+
+fn deserialize_status_code<'de, D>(deserializer: D) -> Result<u16, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = Option::<u16>::deserialize(deserializer)?;
+    Ok(value
+        .filter(|code| (100..=999).contains(code))
+        .unwrap_or_else(default_status_code))
+}
+
+impl Default for BlockResponse {
+    fn default() -> Self {
+        Self {
+            action: BlockAction::default(),
+            status_code: default_status_code(),
+            body: String::new(),
+        }
+    }
+}
+
+/// 名单中的单条规则
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockListItem {
+    pub id: String,
+    pub enabled: bool,
+    #[serde(flatten)]
+    pub matcher: RuleMatcher,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BlockList {
+    #[serde(rename = "toolEnabled")]
+    pub tool_enabled: bool,
+    #[serde(flatten)]
+    pub response: BlockResponse,
+    #[serde(rename = "blockLists")]
+    pub block_lists: HashMap<String, BlockListItem>,
+}
+
+/// 白名单开启后，未命中任何规则的请求都会被拦截
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AllowList {
+    #[serde(rename = "toolEnabled")]
+    pub tool_enabled: bool,
+    #[serde(flatten)]
+    pub response: BlockResponse,
+    #[serde(rename = "allowLists")]
+    pub allow_lists: HashMap<String, BlockListItem>,
+}
+
+/// 从settings.json中读取BlockList配置
+pub fn get_block_list_config<R: Runtime>(app: &AppHandle<R>) -> Result<BlockList, String> {
+    let path = PathBuf::from("settings.json");
+
+    let store = StoreBuilder::new(app, path)
+        .build()
+        .map_err(|e| format!("创建存储失败: {}", e))?;
+
+    match store.get("blockList") {
+        Some(value) => serde_json::from_value(value.clone())
+            .map_err(|e| format!("BlockList配置解析失败: {}. 原始数据: {:?}", e, value)),
+        None => Err("设置中未找到BlockList配置".to_string()),
+    }
+}
+
+/// 从settings.json中读取AllowList配置
+pub fn get_allow_list_config<R: Runtime>(app: &AppHandle<R>) -> Result<AllowList, String> {
+    let path = PathBuf::from("settings.json");
+
+    let store = StoreBuilder::new(app, path)
+        .build()
+        .map_err(|e| format!("创建存储失败: {}", e))?;
+
+    match store.get("allowList") {
+        Some(value) => serde_json::from_value(value.clone())
+            .map_err(|e| format!("AllowList配置解析失败: {}. 原始数据: {:?}", e, value)),
+        None => Err("设置中未找到AllowList配置".to_string()),
+    }
+}
+
+/// 检查请求是否需要拦截，BlockList 优先于 AllowList
+///
+/// CONNECT 请求的 `uri` 为 `https://{authority}`；AllowList 此时只按 scheme/host/port 判断，
+/// 避免带 path 等条件的规则把整个隧道拦截，隧道内解密后的请求仍会逐个检查
+pub fn check_need_block(
+    block_list: Option<BlockList>,
+    allow_list: Option<AllowList>,
+    method: &str,
+    uri: &str,
+) -> Option<BlockResponse> {
+    if let Some(block_list) = block_list.filter(|b| b.tool_enabled) {
+        if block_list
+            .block_lists
+            .values()
+            .any(|item| item.enabled && item.matcher.matches(method, uri))
+        {
+            return Some(block_list.response);
+        }
+    }
+
+    if let Some(allow_list) = allow_list.filter(|a| a.tool_enabled) {
+        let allowed = |item: &BlockListItem| {
+            if method.eq_ignore_ascii_case("CONNECT") {
+                item.matcher.may_match_authority(uri)
+            } else {
+                item.matcher.matches(method, uri)
+            }
+        };
+        if !allow_list
+            .allow_lists
+            .values()
+            .any(|item| item.enabled && allowed(item))
+        {
+            return Some(allow_list.response);
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block_list(response: serde_json::Value, items: serde_json::Value) -> BlockList {
+        let mut value = serde_json::json!({ "toolEnabled": true, "blockLists": items });
+        value
+            .as_object_mut()
+            .unwrap()
+            .extend(response.as_object().unwrap().clone());
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn allow_list(items: serde_json::Value) -> AllowList {
+        serde_json::from_value(serde_json::json!({
+            "toolEnabled": true,
+            "action": "dropConnection",
+            "allowLists": items
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_block_response_status() {
+        let items = serde_json::json!({});
+        assert_eq!(
+            block_list(serde_json::json!({}), items.clone())
+                .response
+                .status_code,
+            403
+        );
+        assert_eq!(
+            block_list(serde_json::json!({ "statusCode": null }), items.clone())
+                .response
+                .status_code,
+            403
+        );
+        assert_eq!(
+            block_list(serde_json::json!({ "statusCode": 0 }), items.clone())
+                .response
+                .status_code,
+            403
+        );
+        assert_eq!(
+            block_list(serde_json::json!({ "statusCode": 451 }), items)
+                .response
+                .status_code,
+            451
+        );
+    }
+
+    #[test]
+    fn test_check_need_block() {
+        let blocked = || {
+            block_list(
+                serde_json::json!({ "statusCode": 451, "body": "blocked" }),
+                serde_json::json!({
+                    "a": { "id": "a", "enabled": true, "host": "ads.example.com" },
+                    "b": { "id": "b", "enabled": false, "host": "*.tracker.com" }
+                }),
+            )
+        };
+        let allowed = || {
+            allow_list(serde_json::json!({
+                "a": { "id": "a", "enabled": true, "host": "*.example.com", "path": "/api/*" }
+            }))
+        };
+
+        // BlockList 优先于 AllowList
+        let response = check_need_block(
+            Some(blocked()),
+            Some(allowed()),
+            "GET",
+            "https://ads.example.com/api/x",
+        )
+        .unwrap();
+        assert_eq!(response.status_code, 451);
+        assert_eq!(response.body, "blocked");
+        assert!(
+            check_need_block(Some(blocked()), None, "GET", "https://cdn.tracker.com/").is_none()
+        );
+
+        // 只开启 AllowList 时未命中的请求使用 AllowList 的响应
+        assert!(check_need_block(
+            None,
+            Some(allowed()),
+            "GET",
+            "https://www.example.com/api/users"
+        )
+        .is_none());
+        let response = check_need_block(
+            None,
+            Some(allowed()),
+            "GET",
+            "https://www.example.com/index.html",
+        )
+        .unwrap();
+        assert_eq!(response.action, BlockAction::DropConnection);
+        assert_eq!(response.status_code, 403);
+        assert!(
+            check_need_block(None, Some(allowed()), "GET", "https://other.com/api/users").is_some()
+        );
+
+        // CONNECT 只按 host 判断，path 条件留到解密后的请求
+        assert!(check_need_block(
+            None,
+            Some(allowed()),
+            "CONNECT",
+            "https://www.example.com:443"
+        )
+        .is_none());
+        assert!(
+            check_need_block(None, Some(allowed()), "CONNECT", "https://other.com:443").is_some()
+        );
+        assert!(check_need_block(
+            Some(blocked()),
+            Some(allowed()),
+            "CONNECT",
+            "https://ads.example.com:443"
+        )
+        .is_some());
+
+        // 工具未启用时不拦截
+        let mut disabled = allowed();
+        disabled.tool_enabled = false;
+        assert!(check_need_block(None, Some(disabled), "GET", "https://other.com/").is_none());
+    }
+}
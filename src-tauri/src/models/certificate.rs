@@ -0,0 +1,31 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_store::StoreBuilder;
+
+/// 叶子证书签发配置
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CertificateConfig {
+    /// 按父域名签发通配符证书，同一父域名下的主机共用一张证书
+    #[serde(rename = "wildcardCerts", default)]
+    pub wildcard_certs: bool,
+    /// 签发叶子证书前先获取上游证书，复制其 subject、SAN 列表和有效期
+    #[serde(rename = "mirrorUpstream", default)]
+    pub mirror_upstream: bool,
+}
+
+/// 从settings.json中读取Certificate配置
+pub fn get_certificate_config<R: Runtime>(app: &AppHandle<R>) -> Result<CertificateConfig, String> {
+    let path = PathBuf::from("settings.json");
+
+    let store = StoreBuilder::new(app, path)
+        .build()
+        .map_err(|e| format!("创建存储失败: {}", e))?;
+
+    match store.get("certificate") {
+        Some(value) => serde_json::from_value(value.clone())
+            .map_err(|e| format!("Certificate配置解析失败: {}. 原始数据: {:?}", e, value)),
+        None => Err("设置中未找到Certificate配置".to_string()),
+    }
+}
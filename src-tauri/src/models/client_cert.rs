@@ -0,0 +1,253 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::PathBuf,
+    sync::{LazyLock, Mutex},
+    time::SystemTime,
+};
+
+use p12_keystore::KeyStore;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_store::StoreBuilder;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use x509_parser::parse_x509_certificate;
+
+use super::crypto::CRYPTO_SERVICE;
+use super::matcher::wildcard_match;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClientCertFormat {
+    #[default]
+    Pem,
+    Pkcs12,
+}
+
+/// 连接上游时出示的客户端证书，`host` 支持通配符
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClientCertItem {
+    pub id: String,
+    pub enabled: bool,
+    pub host: String,
+    /// 身份名称，为空时使用证书 subject
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub format: ClientCertFormat,
+    /// PEM 证书链或 PKCS#12 文件
+    #[serde(rename = "certPath")]
+    pub cert_path: String,
+    /// PEM 私钥文件，为空时从证书文件中读取
+    #[serde(rename = "keyPath", default)]
+    pub key_path: String,
+    /// PKCS#12 的密码
+    #[serde(rename = "encryptedPassword", default)]
+    pub encrypted_password: String,
+}
+
+/// 从证书文件加载的客户端身份
+pub struct ClientIdentity {
+    pub chain: Vec<CertificateDer<'static>>,
+    pub key: PrivateKeyDer<'static>,
+    /// 记录到流量中的身份描述
+    pub label: String,
+}
+
+impl Clone for ClientIdentity {
+    fn clone(&self) -> Self {
+        Self {
+            chain: self.chain.clone(),
+            key: self.key.clone_key(),
+            label: self.label.clone(),
+        }
+    }
+}
+
+/// 影响加载结果的配置项与证书文件的修改时间
+#[derive(PartialEq)]
+struct IdentityStamp {
+    format: ClientCertFormat,
+    name: String,
+    cert_path: String,
+    key_path: String,
+    encrypted_password: String,
+    modified: [Option<SystemTime>; 2],
+}
+
+/// 已加载的客户端身份按配置 id 缓存，避免每个 HTTPS 请求都读取文件和解密
+static IDENTITY_CACHE: LazyLock<Mutex<HashMap<String, (IdentityStamp, ClientIdentity)>>> =
+    LazyLock::new(Default::default);
+
+impl ClientCertItem {
+    /// 配置和证书文件均未修改时返回缓存的身份
+    pub fn load(&self) -> Result<ClientIdentity, String> {
+        let stamp = self.stamp();
+        if let Some((cached_stamp, identity)) = IDENTITY_CACHE.lock().unwrap().get(&self.id) {
+            if *cached_stamp == stamp {
+                return Ok(identity.clone());
+            }
+        }
+        // 在锁外读取和解密，避免一个慢加载阻塞其他请求
+        let identity = self.load_identity()?;
+        IDENTITY_CACHE
+            .lock()
+            .unwrap()
+            .insert(self.id.clone(), (stamp, identity.clone()));
+        Ok(identity)
+    }
+
+    fn stamp(&self) -> IdentityStamp {
+        let modified = |path: &str| fs::metadata(path).and_then(|meta| meta.modified()).ok();
+        IdentityStamp {
+            format: self.format,
+            name: self.name.clone(),
+            cert_path: self.cert_path.clone(),
+            key_path: self.key_path.clone(),
+            encrypted_password: self.encrypted_password.clone(),
+            modified: [
+                modified(&self.cert_path),
+                (!self.key_path.is_empty())
+                    .then(|| modified(&self.key_path))
+                    .flatten(),
+            ],
+        }
+    }
+
+    fn load_identity(&self) -> Result<ClientIdentity, String> {
+        let (chain, key) = match self.format {
+            ClientCertFormat::Pem => self.load_pem()?,
+            ClientCertFormat::Pkcs12 => self.load_pkcs12()?,
+        };
+        let subject = chain
+            .first()
+            .and_then(|cert| parse_x509_certificate(cert).ok())
+            .map(|(_, cert)| cert.subject().to_string())
+            .unwrap_or_default();
+        let label = match (self.name.is_empty(), subject.is_empty()) {
+            (true, _) => subject,
+            (false, true) => self.name.clone(),
+            (false, false) => format!("{} ({})", self.name, subject),
+        };
+        Ok(ClientIdentity { chain, key, label })
+    }
+
+    fn load_pem(&self) -> Result<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>), String> {
+        let cert_data = read_file(&self.cert_path)?;
+        let chain = rustls_pemfile::certs(&mut cert_data.as_slice())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("证书解析失败: {}", e))?;
+        if chain.is_empty() {
+            return Err(format!("{} 中未找到证书", self.cert_path));
+        }
+
+        let key_path = if self.key_path.is_empty() {
+            &self.cert_path
+        } else {
+            &self.key_path
+        };
+        let key = rustls_pemfile::private_key(&mut read_file(key_path)?.as_slice())
+            .map_err(|e| format!("私钥解析失败: {}", e))?
+            .ok_or_else(|| format!("{} 中未找到未加密的私钥", key_path))?;
+        Ok((chain, key))
+    }
+
+    fn load_pkcs12(
+        &self,
+    ) -> Result<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>), String> {
+        let password = if self.encrypted_password.is_empty() {
+            String::new()
+        } else {
+            CRYPTO_SERVICE
+                .decrypt(&self.encrypted_password)
+                .map_err(|e| format!("密码解密失败: {}", e))?
+        };
+        let keystore = KeyStore::from_pkcs12(&read_file(&self.cert_path)?, &password)
+            .map_err(|e| format!("PKCS#12解析失败: {}", e))?;
+        let (_, key_chain) = keystore
+            .private_key_chain()
+            .ok_or_else(|| format!("{} 中未找到私钥", self.cert_path))?;
+        let chain = key_chain
+            .chain()
+            .iter()
+            .map(|cert| CertificateDer::from(cert.as_der().to_vec()))
+            .collect();
+        let key = PrivateKeyDer::Pkcs8(key_chain.key().to_vec().into());
+        Ok((chain, key))
+    }
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|e| format!("读取 {} 失败: {}", path, e))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ClientCerts {
+    #[serde(rename = "toolEnabled")]
+    pub tool_enabled: bool,
+    #[serde(rename = "clientCerts", default)]
+    pub client_certs: HashMap<String, ClientCertItem>,
+}
+
+/// 从settings.json中读取ClientCerts配置
+pub fn get_client_certs_config<R: Runtime>(app: &AppHandle<R>) -> Result<ClientCerts, String> {
+    let path = PathBuf::from("settings.json");
+
+    let store = StoreBuilder::new(app, path)
+        .build()
+        .map_err(|e| format!("创建存储失败: {}", e))?;
+
+    match store.get("clientCerts") {
+        Some(value) => serde_json::from_value(value.clone())
+            .map_err(|e| format!("ClientCerts配置解析失败: {}. 原始数据: {:?}", e, value)),
+        None => Err("设置中未找到ClientCerts配置".to_string()),
+    }
+}
+
+/// 查找上游主机对应的客户端证书，多条命中时优先使用不含通配符的配置
+pub fn check_need_client_cert(
+    client_certs: Option<ClientCerts>,
+    host: &str,
+) -> Option<ClientCertItem> {
+    let client_certs = client_certs.filter(|c| c.tool_enabled)?;
+    let mut items: Vec<ClientCertItem> = client_certs
+        .client_certs
+        .into_values()
+        .filter(|item| item.enabled && wildcard_match(&item.host, host, true))
+        .collect();
+    items.sort_by_key(|item| (item.host.contains('*'), item.id.clone()));
+    items.into_iter().next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client_certs(items: serde_json::Value) -> ClientCerts {
+        serde_json::from_value(serde_json::json!({ "toolEnabled": true, "clientCerts": items }))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_check_need_client_cert() {
+        let config = || {
+            client_certs(serde_json::json!({
+                "b": { "id": "b", "enabled": true, "host": "*.example.com", "certPath": "b.pem" },
+                "a": { "id": "a", "enabled": true, "host": "*.example.com", "certPath": "a.pem" },
+                "c": { "id": "c", "enabled": true, "host": "api.example.com", "certPath": "c.pem" },
+                "d": { "id": "d", "enabled": false, "host": "*", "certPath": "d.pem" }
+            }))
+        };
+        let id = |host: &str| check_need_client_cert(Some(config()), host).map(|item| item.id);
+
+        // 精确主机优先于通配符，多条通配符按 id 顺序
+        assert_eq!(id("API.example.com").as_deref(), Some("c"));
+        assert_eq!(id("www.example.com").as_deref(), Some("a"));
+        assert_eq!(id("other.com"), None);
+
+        let mut disabled = config();
+        disabled.tool_enabled = false;
+        assert!(check_need_client_cert(Some(disabled), "api.example.com").is_none());
+        assert!(check_need_client_cert(None, "api.example.com").is_none());
+    }
+}
@@ -0,0 +1,205 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    io,
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Instant,
+};
+
+use hyper_util::client::legacy::connect::dns::{GaiResolver, Name};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_store::StoreBuilder;
+use tower_service::Service;
+
+use super::matcher::wildcard_match;
+use crate::timings::{ConnectStage, TimingRecorder};
+
+/// 单条 DNS 覆盖规则，`host` 支持通配符
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DnsOverrideItem {
+    pub id: String,
+    pub enabled: bool,
+    pub host: String,
+    #[serde(default)]
+    pub ips: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DnsOverride {
+    #[serde(rename = "toolEnabled")]
+    pub tool_enabled: bool,
+    #[serde(rename = "dnsOverrides")]
+    pub dns_overrides: HashMap<String, DnsOverrideItem>,
+}
+
+/// 从settings.json中读取DNS覆盖配置
+pub fn get_dns_override_config<R: Runtime>(app: &AppHandle<R>) -> Result<DnsOverride, String> {
+    let path = PathBuf::from("settings.json");
+
+    let store = StoreBuilder::new(app, path)
+        .build()
+        .map_err(|e| format!("创建存储失败: {}", e))?;
+
+    match store.get("dnsOverride") {
+        Some(value) => serde_json::from_value(value.clone())
+            .map_err(|e| format!("DnsOverride配置解析失败: {}. 原始数据: {:?}", e, value)),
+        None => Err("设置中未找到DnsOverride配置".to_string()),
+    }
+}
+
+/// 上游连接使用的解析器，命中覆盖表时直接返回配置的 IP，否则使用系统解析
+#[derive(Clone)]
+pub struct OverrideResolver {
+    overrides: Arc<Vec<(String, Vec<IpAddr>)>>,
+    gai: GaiResolver,
+    recorder: Option<TimingRecorder>,
+}
+
+impl OverrideResolver {
+    pub fn new(dns_override: Option<DnsOverride>) -> Self {
+        let mut overrides: Vec<(String, Vec<IpAddr>)> = dns_override
+            .filter(|d| d.tool_enabled)
+            .map(|d| {
+                d.dns_overrides
+                    .into_values()
+                    .filter(|item| item.enabled)
+                    .map(|item| {
+                        let ips = item
+                            .ips
+                            .iter()
+                            .filter_map(|ip| ip.trim().parse::<IpAddr>().ok())
+                            .collect();
+                        (item.host, ips)
+                    })
+                    .filter(|(_, ips): &(String, Vec<IpAddr>)| !ips.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        // 精确主机名优先于通配符
+        overrides.sort_by_key(|(host, _)| (host.contains(['*', '?']), host.clone()));
+
+        Self {
+            overrides: Arc::new(overrides),
+            gai: GaiResolver::new(),
+            recorder: None,
+        }
+    }
+
+    /// 记录 DNS 解析耗时
+    pub(crate) fn with_recorder(mut self, recorder: TimingRecorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+
+    /// 查找主机名对应的覆盖 IP
+    pub fn lookup(&self, host: &str) -> Option<&[IpAddr]> {
+        self.overrides
+            .iter()
+            .find(|(pattern, _)| wildcard_match(pattern, host, true))
+            .map(|(_, ips)| ips.as_slice())
+    }
+}
+
+impl Service<Name> for OverrideResolver {
+    type Response = std::vec::IntoIter<SocketAddr>;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        let start = Instant::now();
+        let recorder = self.recorder.clone();
+        let record = move || {
+            if let Some(recorder) = recorder {
+                recorder.record(ConnectStage::Dns, start, Instant::now());
+            }
+        };
+
+        // 端口由 HttpConnector 根据 URI 设置
+        if let Some(ips) = self.lookup(name.as_str()) {
+            let addrs: Vec<SocketAddr> = ips.iter().map(|ip| SocketAddr::new(*ip, 0)).collect();
+            record();
+            return Box::pin(async move { Ok(addrs.into_iter()) });
+        }
+
+        let fut = self.gai.call(name);
+        Box::pin(async move {
+            let addrs: Vec<SocketAddr> = fut.await?.collect();
+            record();
+            Ok(addrs.into_iter())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    fn override_resolver(tool_enabled: bool) -> OverrideResolver {
+        let config: DnsOverride = serde_json::from_value(serde_json::json!({
+            "toolEnabled": tool_enabled,
+            "dnsOverrides": {
+                "wildcard": {
+                    "id": "wildcard", "enabled": true,
+                    "host": "*.example.com", "ips": ["10.0.0.1", "not-an-ip"]
+                },
+                "exact": {
+                    "id": "exact", "enabled": true,
+                    "host": "api.example.com", "ips": [" ::1 ", "10.0.0.2"]
+                },
+                "disabled": {
+                    "id": "disabled", "enabled": false,
+                    "host": "example.org", "ips": ["10.0.0.3"]
+                },
+                "invalid": {
+                    "id": "invalid", "enabled": true,
+                    "host": "example.net", "ips": ["300.0.0.1"]
+                }
+            }
+        }))
+        .unwrap();
+        OverrideResolver::new(Some(config))
+    }
+
+    #[test]
+    fn test_lookup() {
+        let resolver = override_resolver(true);
+        let ip = |s: &str| IpAddr::from_str(s).unwrap();
+
+        // 精确主机名优先于通配符，无效的 IP 被忽略
+        assert_eq!(
+            resolver.lookup("API.example.com"),
+            Some([ip("::1"), ip("10.0.0.2")].as_slice())
+        );
+        assert_eq!(
+            resolver.lookup("www.example.com"),
+            Some([ip("10.0.0.1")].as_slice())
+        );
+        assert_eq!(resolver.lookup("example.org"), None);
+        assert_eq!(resolver.lookup("example.net"), None);
+
+        assert_eq!(override_resolver(false).lookup("api.example.com"), None);
+        assert_eq!(OverrideResolver::new(None).lookup("api.example.com"), None);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_override() {
+        let mut resolver = override_resolver(true);
+        let addrs: Vec<SocketAddr> = resolver
+            .call(Name::from_str("www.example.com").unwrap())
+            .await
+            .unwrap()
+            .collect();
+        assert_eq!(addrs, [SocketAddr::from(([10, 0, 0, 1], 0))]);
+    }
+}
@@ -1,9 +1,11 @@
+use ipnet::IpNet;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::{collections::HashMap, net::IpAddr, path::PathBuf};
 use tauri::{AppHandle, Runtime};
 use tauri_plugin_store::StoreBuilder;
 
-use crate::utils::{extract_domain, is_local_request};
+use super::matcher::wildcard_match;
+use crate::utils::is_local_request;
 
 /// 外部代理配置的主结构体  
 #[derive(Debug, Serialize, Deserialize)]
@@ -16,6 +18,18 @@ pub struct ExternalProxy {
     #[serde(rename = "proxyType")]
     pub proxy_type: String,
     pub enabled: bool,
+    /// PAC 模式下的脚本位置，本地文件路径或 HTTP(S) 地址
+    #[serde(rename = "pacUrl", default)]
+    pub pac_url: String,
+    /// 具名上游代理，供代理规则引用
+    #[serde(default)]
+    pub upstreams: HashMap<String, NamedUpstream>,
+    /// 按顺序匹配的代理规则，第一条命中的规则决定上游
+    #[serde(rename = "proxyRules", default)]
+    pub proxy_rules: Vec<ProxyRule>,
+    /// 协议条目或 PAC 给出的代理均不可用时，按顺序尝试的具名上游或 `DIRECT`
+    #[serde(rename = "fallbackUpstreams", default)]
+    pub fallback_upstreams: Vec<String>,
 }
 
 /// 绕过域名配置  
@@ -24,6 +38,99 @@ pub struct BypassDomains {
     pub string: Vec<String>,
 }
 
+/// 具名上游代理，`proxyType` 为 `http` 或 `socks`，HTTPS 代理使用 `http` 并开启 `useTls`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NamedUpstream {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "proxyType")]
+    pub proxy_type: String,
+    #[serde(rename = "mutableExternalProxyConfiguration")]
+    pub mutable_external_proxy_configuration: MutableExternalProxyConfiguration,
+}
+
+/// 代理规则中表示直连的上游名称
+pub const DIRECT_UPSTREAM: &str = "DIRECT";
+
+/// 上游名称是否表示直连
+pub fn is_direct_upstream(name: &str) -> bool {
+    name.eq_ignore_ascii_case(DIRECT_UPSTREAM)
+}
+
+/// 按主机选择上游的规则
+///
+/// `host` 支持通配符（如 `*.corp.local`）、IP 或 CIDR（如 `10.0.0.0/8`），
+/// `port` 支持通配符，为空时匹配任意端口；`upstream` 为具名上游的名称或 `DIRECT`，
+/// 不可用时按顺序尝试 `fallbacks`
+///
+/// IP 与 CIDR 只匹配以 IP 地址访问的请求，匹配前不会解析主机名，
+/// 如 `10.0.0.0/8` 不会命中解析到该网段的 `intranet.corp.local`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyRule {
+    pub id: String,
+    pub enabled: bool,
+    pub host: String,
+    #[serde(default)]
+    pub port: String,
+    pub upstream: String,
+    #[serde(default)]
+    pub fallbacks: Vec<String>,
+}
+
+impl ProxyRule {
+    fn matches(&self, host: &str, port: u16) -> bool {
+        self.enabled
+            && host_matches(&self.host, host)
+            && (self.port.is_empty() || wildcard_match(&self.port, &port.to_string(), false))
+    }
+
+    /// 规则的上游及备用上游，按尝试顺序排列
+    pub fn upstreams(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.upstream.as_str()).chain(self.fallbacks.iter().map(String::as_str))
+    }
+}
+
+/// 主机匹配：模式为 CIDR 或 IP 时按网段比较 IP 地址，否则按通配符匹配主机名
+///
+/// 主机为域名时不做 DNS 解析，CIDR 与 IP 模式对其始终不匹配
+pub fn host_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.trim();
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(net) = pattern.parse::<IpNet>() {
+        return host.parse::<IpAddr>().is_ok_and(|ip| net.contains(&ip));
+    }
+    let bare = pattern.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(ip) = bare.parse::<IpAddr>() {
+        return host.parse::<IpAddr>().is_ok_and(|host_ip| host_ip == ip);
+    }
+    wildcard_match(pattern, host, true)
+}
+
+/// 拆分绕过列表中的 `host[:port]`，IPv6 地址需使用 `[::1]:443` 的写法才能带端口
+fn split_host_port(entry: &str) -> (&str, &str) {
+    let entry = entry.trim();
+    if let Some(rest) = entry.strip_prefix('[') {
+        if let Some((host, tail)) = rest.split_once(']') {
+            return (host, tail.strip_prefix(':').unwrap_or(""));
+        }
+    }
+    match entry.split_once(':') {
+        Some((host, port)) if !port.contains(':') => (host, port),
+        _ => (entry, ""),
+    }
+}
+
+/// 从 URL 中取出主机（不含方括号）与端口
+fn host_and_port(url: &str) -> Option<(String, u16)> {
+    let parsed = url::Url::parse(url).ok()?;
+    let host = parsed
+        .host_str()?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+    Some((host, parsed.port_or_known_default().unwrap_or(80)))
+}
+
 /// 代理配置集合  
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Configurations {
@@ -55,7 +162,7 @@ impl From<StringOrNumber> for String {
 }
 
 /// 代理详细配置信息  
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct MutableExternalProxyConfiguration {
     #[serde(deserialize_with = "deserialize_to_string")]
     pub domain: String,
@@ -68,6 +175,19 @@ pub struct MutableExternalProxyConfiguration {
     pub requires_authentication: bool,
     #[serde(deserialize_with = "deserialize_to_string")]
     pub username: String,
+    /// 与代理之间使用 TLS 连接（HTTPS 代理）
+    #[serde(rename = "useTls", default)]
+    pub use_tls: bool,
+}
+
+impl MutableExternalProxyConfiguration {
+    /// 是否填写了完整的代理地址和认证信息
+    pub fn is_configured(&self) -> bool {
+        !self.host.is_empty()
+            && self.port != 0
+            && !(self.requires_authentication
+                && (self.username.is_empty() || self.encrypted_password.is_empty()))
+    }
 }
 
 fn deserialize_to_string<'de, D>(deserializer: D) -> Result<String, D::Error>
@@ -94,6 +214,7 @@ impl ExternalProxy {
                             port: 8080,
                             requires_authentication: false,
                             username: String::new(),
+                            use_tls: false,
                         },
                         string: "http".to_string(),
                     },
@@ -105,6 +226,7 @@ impl ExternalProxy {
                             port: 443,
                             requires_authentication: false,
                             username: String::new(),
+                            use_tls: false,
                         },
                         string: "https".to_string(),
                     },
@@ -116,13 +238,77 @@ impl ExternalProxy {
                             port: 1080,
                             requires_authentication: false,
                             username: String::new(),
+                            use_tls: false,
                         },
                         string: "socks".to_string(),
                     },
                 ],
             },
             enabled: true,
+            pac_url: String::new(),
+            upstreams: HashMap::new(),
+            proxy_rules: Vec::new(),
+            fallback_upstreams: Vec::new(),
+        }
+    }
+
+    /// 按名称（http / https / socks）查找代理条目
+    pub fn entry(&self, name: &str) -> Option<&MutableExternalProxyConfiguration> {
+        self.configurations
+            .entry
+            .iter()
+            .find(|entry| entry.string == name)
+            .map(|entry| &entry.mutable_external_proxy_configuration)
+    }
+
+    /// 请求使用的代理配置：SOCKS 模式统一使用 socks 条目，
+    /// 否则 HTTPS/WSS 请求使用 https 条目（未填写时退回 http 条目），其余请求使用 http 条目
+    pub fn configuration_for(&self, url: &str) -> Option<&MutableExternalProxyConfiguration> {
+        if self.is_socks() {
+            return self.entry("socks");
+        }
+        let secure = url.starts_with("https://") || url.starts_with("wss://");
+        match self.entry("https") {
+            Some(config) if secure && !config.host.is_empty() => Some(config),
+            _ => self.entry("http"),
+        }
+    }
+
+    pub fn is_socks(&self) -> bool {
+        self.proxy_type == "socks"
+    }
+
+    pub fn is_pac(&self) -> bool {
+        self.proxy_type == "pac"
+    }
+
+    /// 按名称查找具名上游
+    pub fn upstream(&self, name: &str) -> Option<&NamedUpstream> {
+        self.upstreams.values().find(|upstream| upstream.name == name)
+    }
+
+    /// 是否命中 localhost 或绕过列表，绕过列表的每项为 `host[:port]`，
+    /// `host` 支持通配符与 CIDR（只匹配 IP 形式的主机），`port` 支持通配符
+    pub fn is_bypassed(&self, url: &str) -> bool {
+        if self.always_bypass_localhost && is_local_request(url) {
+            return true;
         }
+        let Some((host, port)) = host_and_port(url) else {
+            return false;
+        };
+        self.bypass_domains.string.iter().any(|entry| {
+            let (pattern, port_pattern) = split_host_port(entry);
+            !pattern.is_empty()
+                && host_matches(pattern, &host)
+                && (port_pattern.is_empty()
+                    || wildcard_match(port_pattern, &port.to_string(), false))
+        })
+    }
+
+    /// 按顺序查找第一条命中请求的代理规则
+    pub fn match_rule(&self, url: &str) -> Option<&ProxyRule> {
+        let (host, port) = host_and_port(url)?;
+        self.proxy_rules.iter().find(|rule| rule.matches(&host, port))
     }
 }
 
@@ -151,31 +337,68 @@ pub fn check_proxy_config(proxy_config: &ExternalProxy, url: String) -> bool {
         return false;
     }
 
-    // 检查是否勾选了alwaysBypassLocalhost并且是本地请求，如127.0.0.1或者localhost
-    if proxy_config.always_bypass_localhost && is_local_request(&url) {
+    // 检查是否为本地请求或命中bypassDomains
+    if proxy_config.is_bypassed(&url) {
         return false;
     }
+    // PAC 模式由脚本决定是否使用代理
+    if proxy_config.is_pac() {
+        return !proxy_config.pac_url.is_empty();
+    }
+    // 检查请求对应的代理条目是否填写完整
+    match proxy_config.configuration_for(&url) {
+        Some(config) => config.is_configured(),
+        None => false,
+    }
+}
 
-    // 检查是否在bypassDomains中
-    if !proxy_config.bypass_domains.string.is_empty() {
-        let domain = extract_domain(&url);
-        if proxy_config.bypass_domains.string.contains(&domain) {
-            return false;
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_matches() {
+        assert!(host_matches("*.corp.local", "api.CORP.local"));
+        assert!(!host_matches("*.corp.local", "corp.example"));
+        assert!(host_matches("10.0.0.0/8", "10.1.2.3"));
+        assert!(!host_matches("10.0.0.0/8", "192.168.1.1"));
+        assert!(host_matches("fd00::/8", "[fd00::1]"));
+        assert!(host_matches("[::1]", "::1"));
+        assert!(host_matches("127.0.0.1", "127.0.0.1"));
+        assert!(!host_matches("127.0.0.1", "127.0.0.2"));
+        // CIDR 只匹配 IP 字面量的主机，不解析主机名
+        assert!(!host_matches("10.0.0.0/8", "intranet.corp.local"));
     }
-    // 检查是否开启了身份验证并且代理配置为空
-    let proxy_type = proxy_config.proxy_type.as_str();
-    if proxy_config.configurations.entry.iter().any(|entry| {
-        let config = &entry.mutable_external_proxy_configuration;
-        entry.string == proxy_type && (
-            // 检查基本配置是否为空
-            config.host.is_empty() || config.port == 0 ||
-            // 检查认证配置
-            (config.requires_authentication && 
-             (config.username.is_empty() || config.encrypted_password.is_empty()))
-        )
-    }) {
-        return false;
+
+    #[test]
+    fn test_split_host_port() {
+        assert_eq!(split_host_port(" example.com "), ("example.com", ""));
+        assert_eq!(split_host_port("example.com:8*"), ("example.com", "8*"));
+        assert_eq!(split_host_port("[::1]:443"), ("::1", "443"));
+        assert_eq!(split_host_port("[::1]"), ("::1", ""));
+        assert_eq!(split_host_port("fd00::1"), ("fd00::1", ""));
+        assert_eq!(split_host_port("10.0.0.0/8"), ("10.0.0.0/8", ""));
+    }
+
+    #[test]
+    fn test_is_bypassed() {
+        let mut proxy = ExternalProxy::new();
+        proxy.bypass_domains.string = vec![
+            "*.internal".to_string(),
+            "example.com:8443".to_string(),
+            "10.0.0.0/8".to_string(),
+            "[::1]:9000".to_string(),
+            String::new(),
+        ];
+        assert!(proxy.is_bypassed("http://localhost:3000/"));
+        assert!(proxy.is_bypassed("https://api.internal/v1"));
+        assert!(proxy.is_bypassed("https://example.com:8443/"));
+        assert!(!proxy.is_bypassed("https://example.com/"));
+        assert!(proxy.is_bypassed("http://10.2.3.4/"));
+        assert!(proxy.is_bypassed("http://[::1]:9000/"));
+        assert!(!proxy.is_bypassed("https://other.com/"));
+
+        proxy.always_bypass_localhost = false;
+        assert!(!proxy.is_bypassed("http://localhost:3000/"));
     }
-    return true;
 }
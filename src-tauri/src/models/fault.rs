@@ -0,0 +1,203 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use hyper::StatusCode;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_store::StoreBuilder;
+
+use super::matcher::RuleMatcher;
+
+/// 故障类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FaultType {
+    /// 延迟响应
+    Delay,
+    /// 发送部分 body 后断开连接
+    Reset,
+    /// 直接返回错误状态码，不请求上游
+    ErrorStatus,
+    /// 截断响应 body
+    Truncate,
+}
+
+/// 故障注入规则，`probability` 为触发概率（0-100）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FaultItem {
+    pub id: String,
+    pub enabled: bool,
+    #[serde(flatten)]
+    pub matcher: RuleMatcher,
+    #[serde(rename = "faultType")]
+    pub fault_type: FaultType,
+    #[serde(default = "default_probability")]
+    pub probability: u8,
+    #[serde(rename = "delayMs", default)]
+    pub delay_ms: u64,
+    #[serde(rename = "statusCode", default = "default_status_code")]
+    pub status_code: u16,
+    #[serde(default)]
+    pub body: String,
+    /// Reset/Truncate 前发送的字节数，按解码后的 body 计算
+    #[serde(rename = "afterBytes", default)]
+    pub after_bytes: u64,
+}
+
+fn default_probability() -> u8 {
+    100
+}
+
+fn default_status_code() -> u16 {
+    503
+}
+
+impl FaultItem {
+    pub fn status(&self) -> StatusCode {
+        StatusCode::from_u16(self.status_code).unwrap_or(StatusCode::SERVICE_UNAVAILABLE)
+    }
+
+    /// 记录到 Traffic 中的描述
+    pub fn describe(&self) -> String {
+        match self.fault_type {
+            FaultType::Delay => format!("delay {}ms", self.delay_ms),
+            FaultType::Reset => format!("reset after {} bytes", self.after_bytes),
+            FaultType::ErrorStatus => format!("status {}", self.status().as_u16()),
+            FaultType::Truncate => format!("truncate after {} bytes", self.after_bytes),
+        }
+    }
+
+    fn triggered(&self) -> bool {
+        self.probability >= 100
+            || (self.probability > 0 && rand::thread_rng().gen_range(0..100) < self.probability)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FaultInjection {
+    #[serde(rename = "toolEnabled")]
+    pub tool_enabled: bool,
+    pub faults: HashMap<String, FaultItem>,
+}
+
+/// 从settings.json中读取FaultInjection配置
+pub fn get_fault_config<R: Runtime>(app: &AppHandle<R>) -> Result<FaultInjection, String> {
+    let path = PathBuf::from("settings.json");
+
+    let store = StoreBuilder::new(app, path)
+        .build()
+        .map_err(|e| format!("创建存储失败: {}", e))?;
+
+    match store.get("faultInjection") {
+        Some(value) => serde_json::from_value(value.clone())
+            .map_err(|e| format!("FaultInjection配置解析失败: {}. 原始数据: {:?}", e, value)),
+        None => Err("设置中未找到FaultInjection配置".to_string()),
+    }
+}
+
+/// 返回命中当前请求且按概率触发的故障，同一类型只取一条
+pub fn check_need_fault(
+    fault_injection: FaultInjection,
+    method: &str,
+    uri: &str,
+    fault_types: &[FaultType],
+) -> Vec<FaultItem> {
+    if !fault_injection.tool_enabled {
+        return Vec::new();
+    }
+
+    let mut items: Vec<FaultItem> = fault_injection
+        .faults
+        .into_values()
+        .filter(|item| {
+            item.enabled
+                && fault_types.contains(&item.fault_type)
+                && item.matcher.matches(method, uri)
+        })
+        .collect();
+    items.sort_by(|a, b| a.id.cmp(&b.id));
+
+    let mut triggered: Vec<FaultItem> = Vec::new();
+    for item in items {
+        if !triggered.iter().any(|t| t.fault_type == item.fault_type) && item.triggered() {
+            triggered.push(item);
+        }
+    }
+    triggered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fault_injection(faults: serde_json::Value) -> FaultInjection {
+        serde_json::from_value(serde_json::json!({ "toolEnabled": true, "faults": faults }))
+            .unwrap()
+    }
+
+    fn ids(faults: &[FaultItem]) -> Vec<&str> {
+        faults.iter().map(|item| item.id.as_str()).collect()
+    }
+
+    const ALL: [FaultType; 4] = [
+        FaultType::Delay,
+        FaultType::Reset,
+        FaultType::ErrorStatus,
+        FaultType::Truncate,
+    ];
+
+    #[test]
+    fn test_check_need_fault_probability() {
+        let config = || {
+            fault_injection(serde_json::json!({
+                "never": { "id": "never", "enabled": true, "host": "*", "faultType": "delay",
+                           "probability": 0, "delayMs": 100 },
+                "always": { "id": "always", "enabled": true, "host": "*", "faultType": "errorStatus",
+                            "probability": 100 }
+            }))
+        };
+        for _ in 0..20 {
+            let faults = check_need_fault(config(), "GET", "https://example.com/", &ALL);
+            assert_eq!(ids(&faults), vec!["always"]);
+            assert_eq!(faults[0].status(), StatusCode::SERVICE_UNAVAILABLE);
+        }
+    }
+
+    #[test]
+    fn test_check_need_fault_same_type() {
+        let config = || {
+            fault_injection(serde_json::json!({
+                "b": { "id": "b", "enabled": true, "host": "*", "faultType": "delay", "delayMs": 200 },
+                "a": { "id": "a", "enabled": true, "host": "*", "faultType": "delay", "delayMs": 100 },
+                "c": { "id": "c", "enabled": true, "host": "*", "faultType": "truncate",
+                       "afterBytes": 10 },
+                "d": { "id": "d", "enabled": false, "host": "*", "faultType": "reset" }
+            }))
+        };
+        // 同一类型按 id 顺序只取第一条
+        let faults = check_need_fault(config(), "GET", "https://example.com/", &ALL);
+        assert_eq!(ids(&faults), vec!["a", "c"]);
+        assert_eq!(faults[0].delay_ms, 100);
+
+        // 只检查调用方关心的类型
+        let faults = check_need_fault(
+            config(),
+            "GET",
+            "https://example.com/",
+            &[FaultType::Truncate],
+        );
+        assert_eq!(ids(&faults), vec!["c"]);
+
+        // 概率为 0 的规则不占用该类型，继续检查后面的规则
+        let config = fault_injection(serde_json::json!({
+            "a": { "id": "a", "enabled": true, "host": "*", "faultType": "delay", "probability": 0 },
+            "b": { "id": "b", "enabled": true, "host": "*", "faultType": "delay" }
+        }));
+        let faults = check_need_fault(config, "GET", "https://example.com/", &ALL);
+        assert_eq!(ids(&faults), vec!["b"]);
+
+        let mut disabled = fault_injection(serde_json::json!({}));
+        disabled.tool_enabled = false;
+        assert!(check_need_fault(disabled, "GET", "https://example.com/", &ALL).is_empty());
+    }
+}
@@ -1,19 +1,91 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+};
 
+use hyper::StatusCode;
+use percent_encoding::percent_decode_str;
 use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Runtime};
 use tauri_plugin_store::StoreBuilder;
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+use super::matcher::{MatchType, RuleMatcher};
+use crate::traffic::Traffic;
 
 ///  MapLocal配置的主结构体
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MapLocalItem {
     pub id: String,
     pub enabled: bool,
-    pub url: String,
-    #[serde(rename = "headerLocal")]
+    /// 优先级，数值越大越先匹配
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(flatten)]
+    pub matcher: RuleMatcher,
+    #[serde(rename = "headerLocal", default)]
     pub header_local: String,
-    #[serde(rename = "bodyLocal")]
+    #[serde(rename = "bodyLocal", default)]
     pub body_local: String,
+    /// 映射的本地目录，URL 前缀之后的剩余路径在该目录下查找文件
+    #[serde(rename = "dirLocal", default)]
+    pub dir_local: String,
+    /// 自定义响应状态码，为空时为 200
+    #[serde(rename = "statusCode", default)]
+    pub status_code: Option<u16>,
+    /// 自定义状态描述，为空时使用标准描述
+    #[serde(rename = "reasonPhrase", default)]
+    pub reason_phrase: String,
+    /// 是否替换响应体中的占位符
+    #[serde(default)]
+    pub template: bool,
+}
+
+impl MapLocalItem {
+    pub fn status(&self) -> StatusCode {
+        self.status_code
+            .and_then(|code| StatusCode::from_u16(code).ok())
+            .unwrap_or(StatusCode::OK)
+    }
+
+    /// 目录映射时，根据请求 URL 解析出对应的本地文件
+    ///
+    /// 剩余路径中的 `..` 会被拒绝，以 `/` 结尾或指向目录时使用 `index.html`
+    pub fn resolve_dir_file(&self, uri: &str) -> Option<PathBuf> {
+        if self.dir_local.is_empty() {
+            return None;
+        }
+        let without_query = uri.split(['?', '#']).next().unwrap_or(uri);
+        let rest = if !self.matcher.url.is_empty() && self.matcher.match_type == MatchType::Contains
+        {
+            let index = without_query.find(&self.matcher.url)?;
+            without_query[index + self.matcher.url.len()..].to_string()
+        } else {
+            let parsed = url::Url::parse(uri).ok()?;
+            let prefix = self
+                .matcher
+                .path
+                .split(['*', '?'])
+                .next()
+                .unwrap_or_default();
+            let path = parsed.path();
+            path.strip_prefix(prefix).unwrap_or(path).to_string()
+        };
+        let rest = percent_decode_str(&rest).decode_utf8_lossy().to_string();
+
+        let mut file = PathBuf::from(&self.dir_local);
+        for component in Path::new(rest.trim_start_matches('/')).components() {
+            match component {
+                Component::Normal(part) => file.push(part),
+                Component::CurDir => {}
+                _ => return None,
+            }
+        }
+        if rest.is_empty() || rest.ends_with('/') || file.is_dir() {
+            file.push("index.html");
+        }
+        Some(file)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -48,17 +120,174 @@ pub fn get_map_local_config<R: Runtime>(app: &AppHandle<R>) -> Result<MapLocal,
 // };
 
 /// 检查是否需要使用MapLocal
-pub fn check_need_map_local(map_local: MapLocal) -> Result<MapLocalItem, bool> {
+///
+/// 按优先级依次检查所有启用的规则，返回第一条命中当前请求的规则
+pub fn check_need_map_local(map_local: MapLocal, method: &str, uri: &str) -> Option<MapLocalItem> {
     if !map_local.tool_enabled {
-        return Err(false);
+        return None;
     }
 
-    for (_, item) in map_local.map_locals {
-        if item.enabled && item.url != "" && (item.body_local != "" || item.header_local != "") {
-            return Ok(item);
+    let mut items: Vec<MapLocalItem> = map_local
+        .map_locals
+        .into_values()
+        .filter(|item| {
+            item.enabled
+                && (!item.body_local.is_empty()
+                    || !item.header_local.is_empty()
+                    || !item.dir_local.is_empty()
+                    || item.status_code.is_some())
+        })
+        .collect();
+    items.sort_by(|a, b| b.priority.cmp(&a.priority).then_with(|| a.id.cmp(&b.id)));
+
+    items
+        .into_iter()
+        .find(|item| item.matcher.matches(method, uri))
+}
+
+/// 渲染 MapLocal 响应模板
+///
+/// 支持 `{{query.name}}`、`{{header.name}}`、`{{method}}`、`{{url}}`、`{{path}}`、
+/// `{{timestamp}}`（毫秒）与 `{{datetime}}`，无法识别的占位符原样保留
+pub fn render_template(template: &str, traffic: &Traffic) -> String {
+    let parsed = url::Url::parse(&traffic.uri).ok();
+    let resolve = |key: &str| -> Option<String> {
+        if let Some(name) = key.strip_prefix("query.") {
+            return parsed.as_ref().map(|url| {
+                url.query_pairs()
+                    .find(|(k, _)| k == name)
+                    .map(|(_, v)| v.to_string())
+                    .unwrap_or_default()
+            });
+        }
+        if let Some(name) = key.strip_prefix("header.") {
+            let value = traffic.req_headers.as_ref().and_then(|headers| {
+                headers
+                    .items
+                    .iter()
+                    .find(|h| h.name.eq_ignore_ascii_case(name))
+                    .map(|h| h.value.clone())
+            });
+            return Some(value.unwrap_or_default());
+        }
+        match key {
+            "method" => Some(traffic.method.clone()),
+            "url" => Some(traffic.uri.clone()),
+            "path" => parsed.as_ref().map(|url| url.path().to_string()),
+            "timestamp" => {
+                Some((OffsetDateTime::now_utc().unix_timestamp_nanos() / 1_000_000).to_string())
+            }
+            "datetime" => OffsetDateTime::now_utc().format(&Rfc3339).ok(),
+            _ => None,
+        }
+    };
+
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                match resolve(after[..end].trim()) {
+                    Some(value) => output.push_str(&value),
+                    None => output.push_str(&rest[start..start + 2 + end + 2]),
+                }
+                rest = &after[end + 2..];
+            }
+            None => {
+                output.push_str(&rest[start..]);
+                rest = "";
+            }
         }
     }
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traffic::{Header, Headers};
+
+    fn map_local(value: serde_json::Value) -> MapLocalItem {
+        let dir = std::env::temp_dir().join("ez-shark-map-local");
+        let mut value = value;
+        value["id"] = "1".into();
+        value["enabled"] = true.into();
+        value["dirLocal"] = dir.display().to_string().into();
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_resolve_dir_file() {
+        let item = map_local(serde_json::json!({ "url": "https://example.com/static/" }));
+        let dir = PathBuf::from(&item.dir_local);
+        assert_eq!(
+            item.resolve_dir_file("https://example.com/static/js/app.js?v=1"),
+            Some(dir.join("js").join("app.js"))
+        );
+        assert_eq!(
+            item.resolve_dir_file("https://example.com/static/"),
+            Some(dir.join("index.html"))
+        );
+        assert_eq!(
+            item.resolve_dir_file("https://example.com/static/my%20file.txt"),
+            Some(dir.join("my file.txt"))
+        );
+        // 不允许通过 .. 访问目录之外的文件
+        assert_eq!(
+            item.resolve_dir_file("https://example.com/static/../secret"),
+            None
+        );
+        assert_eq!(
+            item.resolve_dir_file("https://example.com/static/%2e%2e/secret"),
+            None
+        );
 
-    // 如果一切正常，可以返回 true
-    return Err(false);
+        let item = map_local(serde_json::json!({
+            "host": "example.com",
+            "path": "/assets/*"
+        }));
+        assert_eq!(
+            item.resolve_dir_file("https://example.com/assets/img/logo.png"),
+            Some(dir.join("img").join("logo.png"))
+        );
+
+        let mut item = item;
+        item.dir_local.clear();
+        assert_eq!(
+            item.resolve_dir_file("https://example.com/assets/a.js"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_render_template() {
+        let mut traffic = Traffic::new("https://example.com/users?id=42", "POST", "1");
+        traffic.req_headers = Some(Headers {
+            items: vec![Header::new("X-Token", "abc")],
+            size: 0,
+        });
+
+        let rendered = render_template(
+            "{{method}} {{ path }} {{query.id}} {{query.missing}}|{{header.x-token}} {{url}}",
+            &traffic,
+        );
+        assert_eq!(
+            rendered,
+            "POST /users 42 |abc https://example.com/users?id=42"
+        );
+
+        // 无法识别或未闭合的占位符原样保留
+        assert_eq!(
+            render_template("{{unknown}} {{method", &traffic),
+            "{{unknown}} {{method"
+        );
+
+        let timestamp = render_template("{{timestamp}}", &traffic);
+        assert!(timestamp.parse::<i64>().unwrap() > 0);
+        let datetime = render_template("{{datetime}}", &traffic);
+        assert!(OffsetDateTime::parse(&datetime, &Rfc3339).is_ok());
+    }
 }
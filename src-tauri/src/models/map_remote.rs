@@ -0,0 +1,189 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_store::StoreBuilder;
+
+use super::matcher::RuleMatcher;
+
+/// MapRemote 单条规则，匹配条件复用 `RuleMatcher`，`to*` 字段为空时保持原值
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MapRemoteItem {
+    pub id: String,
+    pub enabled: bool,
+    /// 优先级，数值越大越先匹配
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(flatten)]
+    pub matcher: RuleMatcher,
+    #[serde(rename = "toScheme", default)]
+    pub to_scheme: String,
+    #[serde(rename = "toHost", default)]
+    pub to_host: String,
+    #[serde(rename = "toPort", default)]
+    pub to_port: Option<u16>,
+    /// 替换匹配路径前缀的新前缀
+    #[serde(rename = "toPath", default)]
+    pub to_path: String,
+    /// 是否保留原始的 Host 请求头
+    #[serde(rename = "preserveHostHeader", default)]
+    pub preserve_host_header: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MapRemote {
+    #[serde(rename = "toolEnabled")]
+    pub tool_enabled: bool,
+    #[serde(rename = "mapRemotes")]
+    pub map_remotes: HashMap<String, MapRemoteItem>,
+}
+
+impl MapRemoteItem {
+    /// 计算映射后的 URL，无法改写时返回 None
+    pub fn map_url(&self, uri: &str) -> Option<String> {
+        let mut url = url::Url::parse(uri).ok()?;
+
+        if !self.to_scheme.is_empty() {
+            url.set_scheme(&self.to_scheme).ok()?;
+        }
+        if !self.to_host.is_empty() {
+            url.set_host(Some(&self.to_host)).ok()?;
+        }
+        if self.to_port.is_some() {
+            url.set_port(self.to_port).ok()?;
+        }
+        if !self.to_path.is_empty() {
+            let prefix = self
+                .matcher
+                .path
+                .split(['*', '?'])
+                .next()
+                .unwrap_or_default();
+            let path = url.path().to_string();
+            let rest = path.strip_prefix(prefix).unwrap_or(&path);
+            let new_path = match (self.to_path.ends_with('/'), rest.starts_with('/')) {
+                (true, true) => format!("{}{}", self.to_path, &rest[1..]),
+                (false, false) if !rest.is_empty() => format!("{}/{}", self.to_path, rest),
+                _ => format!("{}{}", self.to_path, rest),
+            };
+            url.set_path(&new_path);
+        }
+
+        Some(url.to_string())
+    }
+}
+
+/// 从settings.json中读取MapRemote配置
+pub fn get_map_remote_config<R: Runtime>(app: &AppHandle<R>) -> Result<MapRemote, String> {
+    let path = PathBuf::from("settings.json");
+
+    let store = StoreBuilder::new(app, path)
+        .build()
+        .map_err(|e| format!("创建存储失败: {}", e))?;
+
+    match store.get("mapRemote") {
+        Some(value) => serde_json::from_value(value.clone())
+            .map_err(|e| format!("MapRemote配置解析失败: {}. 原始数据: {:?}", e, value)),
+        None => Err("设置中未找到MapRemote配置".to_string()),
+    }
+}
+
+/// 检查是否需要使用MapRemote，按优先级返回第一条命中的规则
+pub fn check_need_map_remote(
+    map_remote: MapRemote,
+    method: &str,
+    uri: &str,
+) -> Option<MapRemoteItem> {
+    if !map_remote.tool_enabled {
+        return None;
+    }
+
+    let mut items: Vec<MapRemoteItem> = map_remote
+        .map_remotes
+        .into_values()
+        .filter(|item| item.enabled)
+        .collect();
+    items.sort_by(|a, b| b.priority.cmp(&a.priority).then_with(|| a.id.cmp(&b.id)));
+
+    items
+        .into_iter()
+        .find(|item| item.matcher.matches(method, uri))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(mapping: serde_json::Value) -> MapRemoteItem {
+        let mut value = serde_json::json!({ "id": "a", "enabled": true });
+        value
+            .as_object_mut()
+            .unwrap()
+            .extend(mapping.as_object().unwrap().clone());
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_map_url_path() {
+        // 替换前缀并保留后缀与 query
+        let mapping = item(serde_json::json!({ "path": "/api/*", "toPath": "/v2/" }));
+        assert_eq!(
+            mapping.map_url("https://example.com/api/users/1?page=2"),
+            Some("https://example.com/v2/users/1?page=2".to_string())
+        );
+        let mapping = item(serde_json::json!({ "path": "/api*", "toPath": "/v2" }));
+        assert_eq!(
+            mapping.map_url("https://example.com/api/users"),
+            Some("https://example.com/v2/users".to_string())
+        );
+
+        // 前缀不匹配时把新前缀加在原路径前
+        let mapping = item(serde_json::json!({ "path": "/api/*", "toPath": "/v2" }));
+        assert_eq!(
+            mapping.map_url("https://example.com/static/app.js"),
+            Some("https://example.com/v2/static/app.js".to_string())
+        );
+
+        // 未配置路径条件时同样按前缀拼接
+        let mapping = item(serde_json::json!({ "toPath": "/mock/" }));
+        assert_eq!(
+            mapping.map_url("https://example.com/users?id=1"),
+            Some("https://example.com/mock/users?id=1".to_string())
+        );
+
+        // toPath 为空时保持原路径
+        let mapping = item(serde_json::json!({ "path": "/api/*", "toHost": "localhost" }));
+        assert_eq!(
+            mapping.map_url("https://example.com/api/users"),
+            Some("https://localhost/api/users".to_string())
+        );
+    }
+
+    #[test]
+    fn test_map_url_location() {
+        // 切换 scheme 后未指定端口时使用新 scheme 的默认端口
+        let mapping = item(serde_json::json!({ "toScheme": "http", "toHost": "127.0.0.1" }));
+        assert_eq!(
+            mapping.map_url("https://example.com/a?b=1"),
+            Some("http://127.0.0.1/a?b=1".to_string())
+        );
+        // 原地址的显式端口保留，指定端口时覆盖
+        assert_eq!(
+            mapping.map_url("https://example.com:8443/a"),
+            Some("http://127.0.0.1:8443/a".to_string())
+        );
+        let mapping = item(serde_json::json!({ "toScheme": "http", "toPort": 3000 }));
+        assert_eq!(
+            mapping.map_url("https://example.com/a"),
+            Some("http://example.com:3000/a".to_string())
+        );
+        // 端口与新 scheme 的默认端口相同时省略
+        let mapping = item(serde_json::json!({ "toScheme": "https", "toPort": 443 }));
+        assert_eq!(
+            mapping.map_url("http://example.com:8080/"),
+            Some("https://example.com/".to_string())
+        );
+
+        assert_eq!(item(serde_json::json!({})).map_url("not a url"), None);
+    }
+}
@@ -0,0 +1,244 @@
+use std::sync::LazyLock;
+
+use moka::sync::Cache;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// 已编译正则的缓存上限
+const REGEX_CACHE_SIZE: u64 = 1000;
+
+/// 已编译正则的缓存，避免每个请求重复编译
+static REGEX_CACHE: LazyLock<Cache<String, Option<Regex>>> =
+    LazyLock::new(|| Cache::new(REGEX_CACHE_SIZE));
+
+/// URL 匹配方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MatchType {
+    /// 包含匹配（旧版 MapLocal 的行为）
+    #[default]
+    Contains,
+    /// 通配符匹配，支持 `*` 与 `?`，需匹配完整 URL
+    Wildcard,
+    /// 正则匹配
+    Regex,
+}
+
+/// 通用的请求匹配规则，各工具通过 `#[serde(flatten)]` 复用
+///
+/// `url` 按 `match_type` 匹配完整 URL；`scheme`/`host`/`port`/`path`/`query`
+/// 为 Charles 风格的 Location 字段，均使用通配符匹配。为空的字段不参与匹配。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RuleMatcher {
+    #[serde(default)]
+    pub url: String,
+    #[serde(rename = "matchType", default)]
+    pub match_type: MatchType,
+    #[serde(default)]
+    pub scheme: String,
+    #[serde(default)]
+    pub host: String,
+    #[serde(default)]
+    pub port: String,
+    #[serde(default)]
+    pub path: String,
+    #[serde(default)]
+    pub query: String,
+    #[serde(default)]
+    pub methods: Vec<String>,
+}
+
+impl RuleMatcher {
+    /// 规则是否未配置任何匹配条件
+    pub fn is_empty(&self) -> bool {
+        self.url.is_empty()
+            && self.scheme.is_empty()
+            && self.host.is_empty()
+            && self.port.is_empty()
+            && self.path.is_empty()
+            && self.query.is_empty()
+    }
+
+    /// 检查请求是否命中规则
+    pub fn matches(&self, method: &str, uri: &str) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+
+        if !self.methods.is_empty()
+            && !self
+                .methods
+                .iter()
+                .any(|m| m == "*" || m.eq_ignore_ascii_case(method))
+        {
+            return false;
+        }
+
+        if !self.url.is_empty() {
+            let url_match = match self.match_type {
+                MatchType::Contains => uri.contains(&self.url),
+                MatchType::Wildcard => wildcard_match(&self.url, uri, false),
+                MatchType::Regex => regex_match(&self.url, uri),
+            };
+            if !url_match {
+                return false;
+            }
+        }
+
+        if self.scheme.is_empty()
+            && self.host.is_empty()
+            && self.port.is_empty()
+            && self.path.is_empty()
+            && self.query.is_empty()
+        {
+            return true;
+        }
+
+        let Ok(parsed) = url::Url::parse(uri) else {
+            return false;
+        };
+        let port = parsed
+            .port_or_known_default()
+            .map(|p| p.to_string())
+            .unwrap_or_default();
+
+        component_match(&self.scheme, parsed.scheme(), true)
+            && component_match(&self.host, parsed.host_str().unwrap_or_default(), true)
+            && component_match(&self.port, &port, false)
+            && component_match(&self.path, parsed.path(), false)
+            && component_match(&self.query, parsed.query().unwrap_or_default(), false)
+    }
+
+    /// 只按 scheme/host/port 判断规则能否命中该地址，用于 CONNECT 等只知道目标地址的场景
+    ///
+    /// URL、方法、path、query 在建立隧道时无法判断，视为可能命中
+    pub fn may_match_authority(&self, uri: &str) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        if self.scheme.is_empty() && self.host.is_empty() && self.port.is_empty() {
+            return true;
+        }
+
+        let Ok(parsed) = url::Url::parse(uri) else {
+            return false;
+        };
+        let port = parsed
+            .port_or_known_default()
+            .map(|p| p.to_string())
+            .unwrap_or_default();
+
+        component_match(&self.scheme, parsed.scheme(), true)
+            && component_match(&self.host, parsed.host_str().unwrap_or_default(), true)
+            && component_match(&self.port, &port, false)
+    }
+}
+
+fn component_match(pattern: &str, value: &str, ignore_case: bool) -> bool {
+    pattern.is_empty() || wildcard_match(pattern, value, ignore_case)
+}
+
+/// 获取缓存的已编译正则，非法正则返回 None
+pub fn cached_regex(pattern: &str) -> Option<Regex> {
+    REGEX_CACHE.get_with_by_ref(pattern, || Regex::new(pattern).ok())
+}
+
+/// 使用缓存的正则进行匹配，非法正则视为不匹配
+pub fn regex_match(pattern: &str, text: &str) -> bool {
+    match cached_regex(pattern) {
+        Some(regex) => regex.is_match(text),
+        None => false,
+    }
+}
+
+/// 通配符匹配，`*` 匹配任意长度字符，`?` 匹配单个字符
+pub fn wildcard_match(pattern: &str, text: &str, ignore_case: bool) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let eq = |a: char, b: char| {
+        if ignore_case {
+            a.eq_ignore_ascii_case(&b)
+        } else {
+            a == b
+        }
+    };
+
+    let (mut p, mut t) = (0, 0);
+    let mut star: Option<usize> = None;
+    let mut star_text = 0;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || eq(pattern[p], text[t])) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some(p);
+            star_text = t;
+            p += 1;
+        } else if let Some(star_pos) = star {
+            p = star_pos + 1;
+            star_text += 1;
+            t = star_text;
+        } else {
+            return false;
+        }
+    }
+    while p < pattern.len() && pattern[p] == '*' {
+        p += 1;
+    }
+    p == pattern.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*.example.com", "api.example.com", false));
+        assert!(wildcard_match("/v?/users/*", "/v1/users/42", false));
+        assert!(wildcard_match("API.example.com", "api.example.com", true));
+        assert!(!wildcard_match("*.example.com", "example.org", false));
+        assert!(!wildcard_match("/v?/users", "/v10/users", false));
+    }
+
+    #[test]
+    fn test_rule_matcher() {
+        let matcher = RuleMatcher {
+            host: "*.example.com".to_string(),
+            path: "/api/*".to_string(),
+            methods: vec!["GET".to_string()],
+            ..Default::default()
+        };
+        assert!(matcher.matches("GET", "https://www.example.com/api/list?page=1"));
+        assert!(!matcher.matches("POST", "https://www.example.com/api/list"));
+        assert!(!matcher.matches("GET", "https://www.example.com/static/app.js"));
+
+        let matcher = RuleMatcher {
+            url: r"^https://example\.com/users/\d+$".to_string(),
+            match_type: MatchType::Regex,
+            port: "443".to_string(),
+            ..Default::default()
+        };
+        assert!(matcher.matches("GET", "https://example.com/users/42"));
+        assert!(!matcher.matches("GET", "https://example.com/users/abc"));
+    }
+
+    #[test]
+    fn test_may_match_authority() {
+        let matcher = RuleMatcher {
+            host: "*.example.com".to_string(),
+            path: "/api/*".to_string(),
+            methods: vec!["GET".to_string()],
+            ..Default::default()
+        };
+        assert!(matcher.may_match_authority("https://www.example.com:443"));
+        assert!(!matcher.may_match_authority("https://example.org:443"));
+
+        let matcher = RuleMatcher {
+            url: "example.com/api".to_string(),
+            ..Default::default()
+        };
+        assert!(matcher.may_match_authority("https://example.org:443"));
+        assert!(!RuleMatcher::default().may_match_authority("https://example.org:443"));
+    }
+}
@@ -1,7 +1,20 @@
+pub mod block_list;
+pub mod certificate;
 pub mod charles;
+pub mod client_cert;
 pub mod crypto;
+pub mod dns_override;
 pub mod external_proxy;
+pub mod fault;
 pub mod map_local;
+pub mod map_remote;
+pub mod matcher;
+pub mod rewrite;
+pub mod ssl_key_log;
+pub mod ssl_pinning;
+pub mod ssl_proxying;
+pub mod throttle;
+pub mod upstream_trust;
 
 pub use crypto::{CryptoConfig, CryptoService, DecryptError};
 pub use external_proxy::{get_proxy_config, ExternalProxy};
@@ -0,0 +1,584 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use hyper::StatusCode;
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_store::StoreBuilder;
+
+use super::matcher::{cached_regex, RuleMatcher};
+use crate::traffic::{Header, Headers, Traffic};
+
+/// Rewrite 规则类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RewriteRuleType {
+    AddHeader,
+    ModifyHeader,
+    RemoveHeader,
+    AddQueryParam,
+    ModifyQueryParam,
+    RemoveQueryParam,
+    Url,
+    Body,
+    Status,
+}
+
+/// 规则作用的阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RewritePhase {
+    Request,
+    Response,
+}
+
+/// 单条 Rewrite 规则
+///
+/// `match_*` 为空时匹配任意值；`match_regex` 开启后 `match_value` 按正则处理，
+/// `replace_value` 中可以使用 `$1` 形式的捕获组
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RewriteRule {
+    #[serde(default)]
+    pub id: String,
+    pub enabled: bool,
+    #[serde(rename = "ruleType")]
+    pub rule_type: RewriteRuleType,
+    #[serde(default)]
+    pub request: bool,
+    #[serde(default)]
+    pub response: bool,
+    #[serde(rename = "matchName", default)]
+    pub match_name: String,
+    #[serde(rename = "matchValue", default)]
+    pub match_value: String,
+    #[serde(rename = "matchRegex", default)]
+    pub match_regex: bool,
+    #[serde(rename = "replaceName", default)]
+    pub replace_name: String,
+    #[serde(rename = "replaceValue", default)]
+    pub replace_value: String,
+    /// 替换全部匹配项，否则只替换第一个
+    #[serde(rename = "replaceAll", default)]
+    pub replace_all: bool,
+}
+
+/// Rewrite 规则集，`locations` 为空时作用于全部请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RewriteSet {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    pub enabled: bool,
+    /// 优先级，数值越大越先执行
+    #[serde(default)]
+    pub priority: i32,
+    #[serde(default)]
+    pub locations: Vec<RuleMatcher>,
+    #[serde(default)]
+    pub rules: Vec<RewriteRule>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Rewrite {
+    #[serde(rename = "toolEnabled")]
+    pub tool_enabled: bool,
+    #[serde(rename = "rewriteSets")]
+    pub rewrite_sets: HashMap<String, RewriteSet>,
+}
+
+/// 命中当前请求的规则及其所属规则集名称
+#[derive(Debug, Clone)]
+pub struct MatchedRewriteRule {
+    pub set_name: String,
+    pub rule: RewriteRule,
+}
+
+impl MatchedRewriteRule {
+    fn applies_to(&self, phase: RewritePhase) -> bool {
+        match phase {
+            RewritePhase::Request => self.rule.request,
+            RewritePhase::Response => self.rule.response,
+        }
+    }
+
+    fn label(&self) -> String {
+        let rule_name = if self.rule.id.is_empty() {
+            format!("{:?}", self.rule.rule_type)
+        } else {
+            self.rule.id.clone()
+        };
+        format!("{}: {}", self.set_name, rule_name)
+    }
+}
+
+impl RewriteRule {
+    fn name_matches(&self, name: &str) -> bool {
+        if self.match_name.is_empty() {
+            return true;
+        }
+        if self.match_regex {
+            cached_regex(&self.match_name)
+                .map(|regex| regex.is_match(name))
+                .unwrap_or(false)
+        } else {
+            self.match_name.eq_ignore_ascii_case(name)
+        }
+    }
+
+    fn value_matches(&self, value: &str) -> bool {
+        if self.match_value.is_empty() {
+            return true;
+        }
+        if self.match_regex {
+            cached_regex(&self.match_value)
+                .map(|regex| regex.is_match(value))
+                .unwrap_or(false)
+        } else {
+            value.contains(&self.match_value)
+        }
+    }
+
+    /// 用 `replace_value` 替换文本中的 `match_value`，未发生变化时返回 None
+    fn replace_text(&self, text: &str) -> Option<String> {
+        if self.match_value.is_empty() {
+            return None;
+        }
+        let limit = if self.replace_all { 0 } else { 1 };
+        let replaced = if self.match_regex {
+            let regex = cached_regex(&self.match_value)?;
+            regex
+                .replacen(text, limit, self.replace_value.as_str())
+                .into_owned()
+        } else if self.replace_all {
+            text.replace(&self.match_value, &self.replace_value)
+        } else {
+            text.replacen(&self.match_value, &self.replace_value, 1)
+        };
+        (replaced != text).then_some(replaced)
+    }
+
+    /// 计算名称/值对修改后的结果，`match_value` 为空时整体替换为 `replace_value`
+    fn modify_pair(&self, name: &str, value: &str) -> (String, String) {
+        let new_name = if self.replace_name.is_empty() {
+            name.to_string()
+        } else {
+            self.replace_name.clone()
+        };
+        let new_value = if self.match_value.is_empty() {
+            self.replace_value.clone()
+        } else {
+            self.replace_text(value)
+                .unwrap_or_else(|| value.to_string())
+        };
+        (new_name, new_value)
+    }
+
+    /// 修改头部，返回是否发生变化
+    fn apply_headers(&self, headers: &mut Headers) -> bool {
+        match self.rule_type {
+            RewriteRuleType::AddHeader => {
+                if self.replace_name.is_empty() {
+                    return false;
+                }
+                headers
+                    .items
+                    .push(Header::new(&self.replace_name, &self.replace_value));
+                true
+            }
+            RewriteRuleType::ModifyHeader => {
+                let mut changed = false;
+                for header in headers.items.iter_mut() {
+                    if self.name_matches(&header.name) && self.value_matches(&header.value) {
+                        let (name, value) = self.modify_pair(&header.name, &header.value);
+                        header.name = name.to_lowercase();
+                        header.value = value;
+                        changed = true;
+                        if !self.replace_all {
+                            break;
+                        }
+                    }
+                }
+                changed
+            }
+            RewriteRuleType::RemoveHeader => {
+                let before = headers.items.len();
+                headers
+                    .items
+                    .retain(|h| !(self.name_matches(&h.name) && self.value_matches(&h.value)));
+                headers.items.len() != before
+            }
+            _ => false,
+        }
+    }
+
+    /// 修改 URL 及查询参数，返回新的 URL
+    fn apply_url(&self, uri: &str) -> Option<String> {
+        if self.rule_type == RewriteRuleType::Url {
+            return self.replace_text(uri);
+        }
+
+        let mut url = url::Url::parse(uri).ok()?;
+        let pairs: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(k, v)| (k.into_owned(), v.into_owned()))
+            .collect();
+        let mut changed = false;
+        let new_pairs: Vec<(String, String)> = match self.rule_type {
+            RewriteRuleType::AddQueryParam => {
+                if self.replace_name.is_empty() {
+                    return None;
+                }
+                changed = true;
+                let mut pairs = pairs;
+                pairs.push((self.replace_name.clone(), self.replace_value.clone()));
+                pairs
+            }
+            RewriteRuleType::ModifyQueryParam => pairs
+                .into_iter()
+                .map(|(k, v)| {
+                    if (self.replace_all || !changed)
+                        && self.name_matches(&k)
+                        && self.value_matches(&v)
+                    {
+                        changed = true;
+                        self.modify_pair(&k, &v)
+                    } else {
+                        (k, v)
+                    }
+                })
+                .collect(),
+            RewriteRuleType::RemoveQueryParam => {
+                let before = pairs.len();
+                let pairs: Vec<(String, String)> = pairs
+                    .into_iter()
+                    .filter(|(k, v)| !(self.name_matches(k) && self.value_matches(v)))
+                    .collect();
+                changed = pairs.len() != before;
+                pairs
+            }
+            _ => return None,
+        };
+        if !changed {
+            return None;
+        }
+
+        if new_pairs.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(new_pairs);
+        }
+        Some(url.to_string())
+    }
+}
+
+/// 从settings.json中读取Rewrite配置
+pub fn get_rewrite_config<R: Runtime>(app: &AppHandle<R>) -> Result<Rewrite, String> {
+    let path = PathBuf::from("settings.json");
+
+    let store = StoreBuilder::new(app, path)
+        .build()
+        .map_err(|e| format!("创建存储失败: {}", e))?;
+
+    match store.get("rewrite") {
+        Some(value) => serde_json::from_value(value.clone())
+            .map_err(|e| format!("Rewrite配置解析失败: {}. 原始数据: {:?}", e, value)),
+        None => Err("设置中未找到Rewrite配置".to_string()),
+    }
+}
+
+/// 收集命中当前请求的全部规则，规则集按优先级排序，集内保持配置顺序
+pub fn check_need_rewrite(rewrite: Rewrite, method: &str, uri: &str) -> Vec<MatchedRewriteRule> {
+    if !rewrite.tool_enabled {
+        return Vec::new();
+    }
+
+    let mut sets: Vec<RewriteSet> = rewrite
+        .rewrite_sets
+        .into_values()
+        .filter(|set| {
+            set.enabled
+                && (set.locations.is_empty()
+                    || set.locations.iter().any(|l| l.matches(method, uri)))
+        })
+        .collect();
+    sets.sort_by(|a, b| b.priority.cmp(&a.priority).then_with(|| a.id.cmp(&b.id)));
+
+    sets.into_iter()
+        .flat_map(|set| {
+            let set_name = if set.name.is_empty() {
+                set.id.clone()
+            } else {
+                set.name.clone()
+            };
+            set.rules
+                .into_iter()
+                .filter(|rule| rule.enabled)
+                .map(move |rule| MatchedRewriteRule {
+                    set_name: set_name.clone(),
+                    rule,
+                })
+        })
+        .collect()
+}
+
+/// 是否存在需要改写 body 的规则
+pub fn has_body_rules(rules: &[MatchedRewriteRule], phase: RewritePhase) -> bool {
+    rules
+        .iter()
+        .any(|r| r.applies_to(phase) && r.rule.rule_type == RewriteRuleType::Body)
+}
+
+/// 对头部、URL 和状态码应用规则，命中的规则记录到 `traffic.rewrites`
+///
+/// 请求阶段改写后的 URL 写入 `traffic.mapped_uri`，不影响客户端看到的原始 URL
+pub fn apply_rewrite_head(
+    rules: &[MatchedRewriteRule],
+    phase: RewritePhase,
+    traffic: &mut Traffic,
+) {
+    for matched in rules.iter().filter(|r| r.applies_to(phase)) {
+        let rule = &matched.rule;
+        let changed = match rule.rule_type {
+            RewriteRuleType::AddHeader
+            | RewriteRuleType::ModifyHeader
+            | RewriteRuleType::RemoveHeader => {
+                match phase {
+                    // 请求头改写到转发请求头上，保留客户端原始请求头
+                    RewritePhase::Request => {
+                        let mut headers =
+                            traffic.upstream_req_headers().cloned().unwrap_or_default();
+                        let changed = rule.apply_headers(&mut headers);
+                        if changed {
+                            headers.update_size();
+                            traffic.forwarded_req_headers = Some(headers);
+                        }
+                        changed
+                    }
+                    RewritePhase::Response => match traffic.res_headers.as_mut() {
+                        Some(headers) => {
+                            let changed = rule.apply_headers(headers);
+                            if changed {
+                                headers.update_size();
+                            }
+                            changed
+                        }
+                        None => false,
+                    },
+                }
+            }
+            RewriteRuleType::AddQueryParam
+            | RewriteRuleType::ModifyQueryParam
+            | RewriteRuleType::RemoveQueryParam
+            | RewriteRuleType::Url => {
+                if phase != RewritePhase::Request {
+                    continue;
+                }
+                let current = traffic
+                    .mapped_uri
+                    .clone()
+                    .unwrap_or_else(|| traffic.uri.clone());
+                match rule.apply_url(&current) {
+                    Some(new_uri) => {
+                        sync_host_header(&current, &new_uri, traffic);
+                        traffic.mapped_uri = Some(new_uri);
+                        true
+                    }
+                    None => false,
+                }
+            }
+            RewriteRuleType::Status => {
+                if phase != RewritePhase::Response {
+                    continue;
+                }
+                let current = traffic.status.map(|s| s.to_string()).unwrap_or_default();
+                match StatusCode::from_bytes(rule.replace_value.trim().as_bytes()) {
+                    Ok(status) if rule.value_matches(&current) => {
+                        traffic.status = Some(status.as_u16());
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            RewriteRuleType::Body => false,
+        };
+        if changed {
+            traffic.rewrites.push(matched.label());
+        }
+    }
+}
+
+/// URL 改写后主机发生变化时同步 Host 请求头
+fn sync_host_header(old_uri: &str, new_uri: &str, traffic: &mut Traffic) {
+    let authority = |uri: &str| {
+        url::Url::parse(uri).ok().and_then(|url| {
+            url.host_str().map(|host| match url.port() {
+                Some(port) => format!("{host}:{port}"),
+                None => host.to_string(),
+            })
+        })
+    };
+    if let Some(host) = authority(new_uri) {
+        if authority(old_uri).as_ref() != Some(&host) {
+            traffic.set_forwarded_req_header("host", &host);
+        }
+    }
+}
+
+/// 对已解压的文本 body 应用规则，未发生变化时返回 None
+pub fn apply_rewrite_body(
+    rules: &[MatchedRewriteRule],
+    phase: RewritePhase,
+    body: &str,
+    traffic: &mut Traffic,
+) -> Option<String> {
+    let mut current: Option<String> = None;
+    for matched in rules
+        .iter()
+        .filter(|r| r.applies_to(phase) && r.rule.rule_type == RewriteRuleType::Body)
+    {
+        let text = current.as_deref().unwrap_or(body);
+        if let Some(replaced) = matched.rule.replace_text(text) {
+            current = Some(replaced);
+            traffic.rewrites.push(matched.label());
+        }
+    }
+    current
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rewrite(sets: serde_json::Value) -> Rewrite {
+        serde_json::from_value(serde_json::json!({ "toolEnabled": true, "rewriteSets": sets }))
+            .unwrap()
+    }
+
+    fn header<'a>(headers: &'a Headers, name: &str) -> Option<&'a str> {
+        headers
+            .items
+            .iter()
+            .find(|h| h.name == name)
+            .map(|h| h.value.as_str())
+    }
+
+    #[test]
+    fn test_check_need_rewrite() {
+        let config = rewrite(serde_json::json!({
+            "low": {
+                "id": "low", "name": "Low", "enabled": true, "priority": 0,
+                "rules": [{ "enabled": true, "ruleType": "body", "response": true }]
+            },
+            "high": {
+                "id": "high", "name": "High", "enabled": true, "priority": 10,
+                "locations": [{ "host": "*.example.com" }],
+                "rules": [
+                    { "enabled": true, "ruleType": "addHeader", "request": true },
+                    { "enabled": false, "ruleType": "removeHeader", "request": true }
+                ]
+            },
+            "off": {
+                "id": "off", "enabled": false,
+                "rules": [{ "enabled": true, "ruleType": "url", "request": true }]
+            }
+        }));
+
+        let rules = check_need_rewrite(config, "GET", "https://api.example.com/");
+        let names: Vec<&str> = rules.iter().map(|r| r.set_name.as_str()).collect();
+        assert_eq!(names, ["High", "Low"]);
+        assert!(has_body_rules(&rules, RewritePhase::Response));
+        assert!(!has_body_rules(&rules, RewritePhase::Request));
+
+        let config = rewrite(serde_json::json!({
+            "high": {
+                "id": "high", "enabled": true,
+                "locations": [{ "host": "*.example.com" }],
+                "rules": [{ "enabled": true, "ruleType": "addHeader", "request": true }]
+            }
+        }));
+        assert!(check_need_rewrite(config, "GET", "https://example.org/").is_empty());
+    }
+
+    #[test]
+    fn test_rewrite_request() {
+        let config = rewrite(serde_json::json!({
+            "a": {
+                "id": "a", "name": "Api", "enabled": true,
+                "rules": [
+                    { "enabled": true, "ruleType": "addHeader", "request": true,
+                      "replaceName": "x-debug", "replaceValue": "1" },
+                    { "enabled": true, "ruleType": "modifyQueryParam", "request": true,
+                      "matchName": "page", "replaceValue": "9" },
+                    { "enabled": true, "ruleType": "removeQueryParam", "request": true,
+                      "matchName": "token" },
+                    { "enabled": true, "ruleType": "url", "request": true,
+                      "matchValue": r"api\.(\w+)\.com", "matchRegex": true,
+                      "replaceValue": "$1.test" }
+                ]
+            }
+        }));
+        let uri = "https://api.example.com/list?page=1&token=abc";
+        let rules = check_need_rewrite(config, "GET", uri);
+
+        let mut traffic = Traffic::new(uri, "GET", "1");
+        traffic.req_headers = Some(Headers {
+            items: vec![Header::new("host", "api.example.com")],
+            size: 0,
+        });
+        apply_rewrite_head(&rules, RewritePhase::Request, &mut traffic);
+
+        assert_eq!(
+            traffic.mapped_uri.as_deref(),
+            Some("https://example.test/list?page=9")
+        );
+        assert_eq!(traffic.rewrites.len(), 4);
+
+        // 客户端原始请求头保持不变，改写结果记录在转发请求头中
+        let original = traffic.req_headers.as_ref().unwrap();
+        assert_eq!(original.items.len(), 1);
+        assert_eq!(header(original, "host"), Some("api.example.com"));
+        let forwarded = traffic.forwarded_req_headers.as_ref().unwrap();
+        assert_eq!(header(forwarded, "x-debug"), Some("1"));
+        assert_eq!(header(forwarded, "host"), Some("example.test"));
+    }
+
+    #[test]
+    fn test_rewrite_response() {
+        let config = rewrite(serde_json::json!({
+            "a": {
+                "id": "a", "enabled": true,
+                "rules": [
+                    { "enabled": true, "ruleType": "status", "response": true,
+                      "matchValue": "200", "replaceValue": "418" },
+                    { "enabled": true, "ruleType": "modifyHeader", "response": true,
+                      "matchName": "set-cookie", "matchValue": "secure",
+                      "replaceValue": "insecure" },
+                    { "enabled": true, "ruleType": "body", "response": true,
+                      "matchValue": r"(\w+)@old", "matchRegex": true,
+                      "replaceValue": "$1@new", "replaceAll": true }
+                ]
+            }
+        }));
+        let rules = check_need_rewrite(config, "GET", "https://example.com/");
+
+        let mut traffic = Traffic::new("https://example.com/", "GET", "1");
+        traffic.status = Some(200);
+        traffic.res_headers = Some(Headers {
+            items: vec![
+                Header::new("set-cookie", "a=1; secure"),
+                Header::new("set-cookie", "b=2; secure"),
+            ],
+            size: 0,
+        });
+        apply_rewrite_head(&rules, RewritePhase::Response, &mut traffic);
+
+        assert_eq!(traffic.status, Some(418));
+        // 未开启 replaceAll 时只修改第一个命中的头部
+        let headers = traffic.res_headers.as_ref().unwrap();
+        assert_eq!(headers.items[0].value, "a=1; insecure");
+        assert_eq!(headers.items[1].value, "b=2; secure");
+
+        let body = apply_rewrite_body(&rules, RewritePhase::Response, "a@old b@old", &mut traffic);
+        assert_eq!(body.as_deref(), Some("a@new b@new"));
+        assert!(apply_rewrite_body(&rules, RewritePhase::Response, "none", &mut traffic).is_none());
+        assert!(apply_rewrite_body(&rules, RewritePhase::Request, "a@old", &mut traffic).is_none());
+    }
+}
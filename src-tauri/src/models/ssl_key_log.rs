@@ -0,0 +1,44 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_store::StoreBuilder;
+
+/// NSS key log 输出配置，`path` 为空时使用 SSLKEYLOGFILE 环境变量
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SslKeyLog {
+    #[serde(rename = "toolEnabled")]
+    pub tool_enabled: bool,
+    #[serde(default)]
+    pub path: String,
+}
+
+impl SslKeyLog {
+    /// 生效的输出文件，未启用或未配置时为 None
+    pub fn output_path(&self) -> Option<PathBuf> {
+        if !self.tool_enabled {
+            return None;
+        }
+        if !self.path.trim().is_empty() {
+            return Some(PathBuf::from(self.path.trim()));
+        }
+        std::env::var_os("SSLKEYLOGFILE")
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+    }
+}
+
+/// 从settings.json中读取SslKeyLog配置
+pub fn get_ssl_key_log_config<R: Runtime>(app: &AppHandle<R>) -> Result<SslKeyLog, String> {
+    let path = PathBuf::from("settings.json");
+
+    let store = StoreBuilder::new(app, path)
+        .build()
+        .map_err(|e| format!("创建存储失败: {}", e))?;
+
+    match store.get("sslKeyLog") {
+        Some(value) => serde_json::from_value(value.clone())
+            .map_err(|e| format!("SslKeyLog配置解析失败: {}. 原始数据: {:?}", e, value)),
+        None => Err("设置中未找到SslKeyLog配置".to_string()),
+    }
+}
@@ -0,0 +1,165 @@
+use std::{collections::HashMap, io, path::PathBuf, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_store::StoreBuilder;
+use tokio_rustls::rustls::{AlertDescription, Error as TlsError};
+
+/// 自动识别出的证书固定主机
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PinnedHost {
+    pub host: String,
+    pub port: u16,
+    /// 最后一次握手失败的原因
+    #[serde(default)]
+    pub reason: String,
+    #[serde(rename = "learnedAt", default)]
+    pub learned_at: String,
+}
+
+/// 证书固定检测配置，`pinnedHosts` 以 `host:port` 为 key
+///
+/// `toolEnabled` 只控制是否自动检测，默认关闭；已识别的主机在清除前始终透传
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SslPinning {
+    #[serde(rename = "toolEnabled", default)]
+    pub tool_enabled: bool,
+    /// 连续握手失败多少次后判定为证书固定
+    #[serde(default = "default_threshold")]
+    pub threshold: u32,
+    #[serde(rename = "pinnedHosts", default)]
+    pub pinned_hosts: HashMap<String, PinnedHost>,
+}
+
+fn default_threshold() -> u32 {
+    3
+}
+
+impl Default for SslPinning {
+    fn default() -> Self {
+        Self {
+            tool_enabled: false,
+            threshold: default_threshold(),
+            pinned_hosts: HashMap::new(),
+        }
+    }
+}
+
+impl SslPinning {
+    pub fn is_pinned(&self, host: &str, port: u16) -> bool {
+        self.pinned_hosts.contains_key(&pinned_key(host, port))
+    }
+}
+
+pub fn pinned_key(host: &str, port: u16) -> String {
+    format!("{}:{}", host.to_lowercase(), port)
+}
+
+/// 从settings.json中读取SslPinning配置
+pub fn get_ssl_pinning_config<R: Runtime>(app: &AppHandle<R>) -> Result<SslPinning, String> {
+    let path = PathBuf::from("settings.json");
+
+    let store = StoreBuilder::new(app, path)
+        .build()
+        .map_err(|e| format!("创建存储失败: {}", e))?;
+
+    match store.get("sslPinning") {
+        Some(value) => serde_json::from_value(value.clone())
+            .map_err(|e| format!("SslPinning配置解析失败: {}. 原始数据: {:?}", e, value)),
+        None => Err("设置中未找到SslPinning配置".to_string()),
+    }
+}
+
+/// 将SslPinning配置写回settings.json
+pub fn save_ssl_pinning_config<R: Runtime>(
+    app: &AppHandle<R>,
+    ssl_pinning: &SslPinning,
+) -> Result<(), String> {
+    let path = PathBuf::from("settings.json");
+
+    let store = StoreBuilder::new(app, path)
+        .build()
+        .map_err(|e| format!("创建存储失败: {}", e))?;
+    let value = serde_json::to_value(ssl_pinning)
+        .map_err(|e| format!("SslPinning配置序列化失败: {}", e))?;
+    store.set("sslPinning", value);
+    store.save().map_err(|e| format!("保存设置失败: {}", e))
+}
+
+/// 发送 ServerHello 后多久内断开连接视为客户端拒绝证书
+const RESET_AFTER_SERVER_HELLO: Duration = Duration::from_secs(1);
+
+/// 判断握手失败是否由客户端拒绝伪造证书引起，返回失败原因
+///
+/// 只统计客户端发送的证书相关告警，以及发送 ServerHello 后 `elapsed` 很短时间内的断开；
+/// 其他错误（超时、协议不匹配等）不计入
+pub fn pinning_failure_reason(err: &io::Error, elapsed: Duration) -> Option<String> {
+    if let Some(TlsError::AlertReceived(alert)) =
+        err.get_ref().and_then(|e| e.downcast_ref::<TlsError>())
+    {
+        return matches!(
+            alert,
+            AlertDescription::UnknownCA
+                | AlertDescription::BadCertificate
+                | AlertDescription::CertificateUnknown
+                | AlertDescription::UnsupportedCertificate
+                | AlertDescription::CertificateRevoked
+                | AlertDescription::CertificateExpired
+                | AlertDescription::AccessDenied
+        )
+        .then(|| format!("received alert {:?}", alert));
+    }
+
+    match err.kind() {
+        io::ErrorKind::ConnectionReset
+        | io::ErrorKind::ConnectionAborted
+        | io::ErrorKind::UnexpectedEof
+            if elapsed <= RESET_AFTER_SERVER_HELLO =>
+        {
+            Some(format!("connection closed right after ServerHello: {err}"))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn alert(description: AlertDescription) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            TlsError::AlertReceived(description),
+        )
+    }
+
+    #[test]
+    fn test_certificate_alert() {
+        let elapsed = Duration::from_secs(10);
+        assert!(pinning_failure_reason(&alert(AlertDescription::UnknownCA), elapsed).is_some());
+        assert!(
+            pinning_failure_reason(&alert(AlertDescription::BadCertificate), elapsed).is_some()
+        );
+        assert!(
+            pinning_failure_reason(&alert(AlertDescription::ProtocolVersion), elapsed).is_none()
+        );
+        assert!(
+            pinning_failure_reason(&alert(AlertDescription::HandshakeFailure), elapsed).is_none()
+        );
+    }
+
+    #[test]
+    fn test_reset_after_server_hello() {
+        let reset = io::Error::from(io::ErrorKind::ConnectionReset);
+        assert!(pinning_failure_reason(&reset, Duration::from_millis(50)).is_some());
+        assert!(pinning_failure_reason(&reset, Duration::from_secs(5)).is_none());
+
+        let eof = io::Error::from(io::ErrorKind::UnexpectedEof);
+        assert!(pinning_failure_reason(&eof, Duration::from_millis(50)).is_some());
+
+        let broken_pipe = io::Error::from(io::ErrorKind::BrokenPipe);
+        assert!(pinning_failure_reason(&broken_pipe, Duration::from_millis(50)).is_none());
+        let timed_out = io::Error::from(io::ErrorKind::TimedOut);
+        assert!(pinning_failure_reason(&timed_out, Duration::ZERO).is_none());
+    }
+}
@@ -0,0 +1,145 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_store::StoreBuilder;
+
+use super::matcher::wildcard_match;
+
+/// SSL Proxying 的 Location，`host` 与 `port` 均支持通配符，`port` 为空时匹配任意端口
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SslProxyingLocation {
+    pub id: String,
+    pub enabled: bool,
+    pub host: String,
+    #[serde(default)]
+    pub port: String,
+}
+
+impl SslProxyingLocation {
+    fn matches(&self, host: &str, port: u16) -> bool {
+        self.enabled
+            && wildcard_match(&self.host, host, true)
+            && (self.port.is_empty() || wildcard_match(&self.port, &port.to_string(), false))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SslProxying {
+    #[serde(rename = "toolEnabled")]
+    pub tool_enabled: bool,
+    #[serde(default)]
+    pub includes: HashMap<String, SslProxyingLocation>,
+    #[serde(default)]
+    pub excludes: HashMap<String, SslProxyingLocation>,
+}
+
+/// 从settings.json中读取SslProxying配置
+pub fn get_ssl_proxying_config<R: Runtime>(app: &AppHandle<R>) -> Result<SslProxying, String> {
+    let path = PathBuf::from("settings.json");
+
+    let store = StoreBuilder::new(app, path)
+        .build()
+        .map_err(|e| format!("创建存储失败: {}", e))?;
+
+    match store.get("sslProxying") {
+        Some(value) => serde_json::from_value(value.clone())
+            .map_err(|e| format!("SslProxying配置解析失败: {}. 原始数据: {:?}", e, value)),
+        None => Err("设置中未找到SslProxying配置".to_string()),
+    }
+}
+
+/// 检查 CONNECT 目标是否需要解密
+///
+/// 未配置或未启用时解密全部主机；启用后 `includes` 为空表示包含全部主机，
+/// 命中 `excludes` 的主机直接透传
+pub fn check_need_ssl_proxying(ssl_proxying: Option<SslProxying>, host: &str, port: u16) -> bool {
+    let Some(ssl_proxying) = ssl_proxying.filter(|s| s.tool_enabled) else {
+        return true;
+    };
+
+    let included = ssl_proxying.includes.values().all(|item| !item.enabled)
+        || ssl_proxying
+            .includes
+            .values()
+            .any(|item| item.matches(host, port));
+    included
+        && !ssl_proxying
+            .excludes
+            .values()
+            .any(|item| item.matches(host, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ssl_proxying(includes: serde_json::Value, excludes: serde_json::Value) -> SslProxying {
+        serde_json::from_value(serde_json::json!({
+            "toolEnabled": true,
+            "includes": includes,
+            "excludes": excludes
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_check_need_ssl_proxying() {
+        // 未配置或未启用时解密全部主机
+        assert!(check_need_ssl_proxying(None, "example.com", 443));
+        let mut disabled = ssl_proxying(serde_json::json!({}), serde_json::json!({}));
+        disabled.tool_enabled = false;
+        assert!(check_need_ssl_proxying(Some(disabled), "example.com", 443));
+
+        let config = || {
+            ssl_proxying(
+                serde_json::json!({
+                    "a": { "id": "a", "enabled": true, "host": "*.example.com", "port": "443" },
+                    "b": { "id": "b", "enabled": false, "host": "example.org" }
+                }),
+                serde_json::json!({
+                    "c": { "id": "c", "enabled": true, "host": "pinned.example.com" }
+                }),
+            )
+        };
+        assert!(check_need_ssl_proxying(
+            Some(config()),
+            "API.example.com",
+            443
+        ));
+        assert!(!check_need_ssl_proxying(
+            Some(config()),
+            "api.example.com",
+            8443
+        ));
+        assert!(!check_need_ssl_proxying(Some(config()), "example.org", 443));
+        assert!(!check_need_ssl_proxying(
+            Some(config()),
+            "pinned.example.com",
+            443
+        ));
+
+        // includes 为空或全部禁用时包含全部主机，只排除 excludes
+        let config = || {
+            ssl_proxying(
+                serde_json::json!({
+                    "b": { "id": "b", "enabled": false, "host": "example.org" }
+                }),
+                serde_json::json!({
+                    "c": { "id": "c", "enabled": true, "host": "*.bank.com", "port": "44?" }
+                }),
+            )
+        };
+        assert!(check_need_ssl_proxying(Some(config()), "example.com", 443));
+        assert!(check_need_ssl_proxying(
+            Some(config()),
+            "www.bank.com",
+            8443
+        ));
+        assert!(!check_need_ssl_proxying(
+            Some(config()),
+            "www.bank.com",
+            443
+        ));
+    }
+}
@@ -0,0 +1,81 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_store::StoreBuilder;
+
+use super::matcher::wildcard_match;
+
+/// 限速配置，带宽单位为 kbps，为 0 时不限制
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThrottleProfile {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(rename = "downloadKbps", default)]
+    pub download_kbps: u32,
+    #[serde(rename = "uploadKbps", default)]
+    pub upload_kbps: u32,
+    /// 每个请求额外增加的延迟
+    #[serde(rename = "latencyMs", default)]
+    pub latency_ms: u64,
+    /// 生效的主机，支持通配符，为空时作用于全部主机
+    #[serde(default)]
+    pub hosts: Vec<String>,
+}
+
+impl ThrottleProfile {
+    pub fn display_name(&self) -> String {
+        if self.name.is_empty() {
+            self.id.clone()
+        } else {
+            self.name.clone()
+        }
+    }
+
+    fn matches_host(&self, host: &str) -> bool {
+        self.hosts.is_empty()
+            || self
+                .hosts
+                .iter()
+                .any(|pattern| wildcard_match(pattern, host, true))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Throttle {
+    #[serde(rename = "toolEnabled")]
+    pub tool_enabled: bool,
+    #[serde(rename = "activeProfile", default)]
+    pub active_profile: String,
+    #[serde(rename = "throttleProfiles")]
+    pub throttle_profiles: HashMap<String, ThrottleProfile>,
+}
+
+/// 从settings.json中读取Throttle配置
+pub fn get_throttle_config<R: Runtime>(app: &AppHandle<R>) -> Result<Throttle, String> {
+    let path = PathBuf::from("settings.json");
+
+    let store = StoreBuilder::new(app, path)
+        .build()
+        .map_err(|e| format!("创建存储失败: {}", e))?;
+
+    match store.get("throttle") {
+        Some(value) => serde_json::from_value(value.clone())
+            .map_err(|e| format!("Throttle配置解析失败: {}. 原始数据: {:?}", e, value)),
+        None => Err("设置中未找到Throttle配置".to_string()),
+    }
+}
+
+/// 返回当前主机生效的限速配置
+pub fn check_need_throttle(throttle: Throttle, host: &str) -> Option<ThrottleProfile> {
+    if !throttle.tool_enabled {
+        return None;
+    }
+
+    throttle
+        .throttle_profiles
+        .into_values()
+        .find(|profile| profile.id == throttle.active_profile)
+        .filter(|profile| profile.matches_host(host))
+}
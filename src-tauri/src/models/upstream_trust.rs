@@ -0,0 +1,52 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Runtime};
+use tauri_plugin_store::StoreBuilder;
+
+use super::matcher::wildcard_match;
+
+/// 允许上游证书无效的主机，`host` 支持通配符
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllowInvalidCertHost {
+    pub id: String,
+    pub enabled: bool,
+    pub host: String,
+}
+
+/// 上游证书校验配置，默认只信任内置的 webpki 根证书
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UpstreamTrust {
+    /// 同时信任系统证书库
+    #[serde(rename = "nativeRoots", default)]
+    pub native_roots: bool,
+    /// 额外信任的 CA 证书文件（PEM）
+    #[serde(rename = "caFiles", default)]
+    pub ca_files: Vec<String>,
+    #[serde(rename = "allowInvalidHosts", default)]
+    pub allow_invalid_hosts: HashMap<String, AllowInvalidCertHost>,
+}
+
+impl UpstreamTrust {
+    /// 主机的证书校验失败时是否继续连接
+    pub fn allow_invalid_cert(&self, host: &str) -> bool {
+        self.allow_invalid_hosts
+            .values()
+            .any(|item| item.enabled && wildcard_match(&item.host, host, true))
+    }
+}
+
+/// 从settings.json中读取UpstreamTrust配置
+pub fn get_upstream_trust_config<R: Runtime>(app: &AppHandle<R>) -> Result<UpstreamTrust, String> {
+    let path = PathBuf::from("settings.json");
+
+    let store = StoreBuilder::new(app, path)
+        .build()
+        .map_err(|e| format!("创建存储失败: {}", e))?;
+
+    match store.get("upstreamTrust") {
+        Some(value) => serde_json::from_value(value.clone())
+            .map_err(|e| format!("UpstreamTrust配置解析失败: {}. 原始数据: {:?}", e, value)),
+        None => Err("设置中未找到UpstreamTrust配置".to_string()),
+    }
+}
@@ -0,0 +1,173 @@
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+
+use bytes::Bytes;
+use http::{
+    header::{CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_TYPE},
+    HeaderValue, Response, StatusCode,
+};
+
+use crate::cert::CertificateAuthority;
+
+/// 证书安装页面的域名，设备配置代理后访问 `http://ez-shark.cert/` 即可下载 CA
+pub(crate) const CERT_HOST: &str = "ez-shark.cert";
+
+const PEM_PATH: &str = "/ez-shark-ca.pem";
+const DER_PATH: &str = "/ez-shark-ca.cer";
+
+pub(crate) fn is_cert_host(host: &str) -> bool {
+    host.eq_ignore_ascii_case(CERT_HOST)
+}
+
+/// 根据路径返回安装页面或 CA 证书，`local_addr` 为客户端连接到代理时使用的本地地址
+pub(crate) fn cert_page_response(
+    ca: &CertificateAuthority,
+    path: &str,
+    local_addr: Option<SocketAddr>,
+) -> Response<Bytes> {
+    match path {
+        "/" | "" => response(
+            StatusCode::OK,
+            "text/html; charset=utf-8",
+            render_page(lan_addr(local_addr)).into(),
+            None,
+        ),
+        PEM_PATH => response(
+            StatusCode::OK,
+            "application/x-pem-file",
+            ca.ca_cert_pem().into(),
+            Some("ez-shark-ca.pem"),
+        ),
+        DER_PATH => match ca.ca_cert_der() {
+            Ok(der) => response(
+                StatusCode::OK,
+                "application/x-x509-ca-cert",
+                der.to_vec().into(),
+                Some("ez-shark-ca.cer"),
+            ),
+            Err(err) => response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "text/plain; charset=utf-8",
+                format!("{err:#}").into(),
+                None,
+            ),
+        },
+        _ => response(
+            StatusCode::NOT_FOUND,
+            "text/plain; charset=utf-8",
+            Bytes::from_static(b"Not Found"),
+            None,
+        ),
+    }
+}
+
+fn response(
+    status: StatusCode,
+    content_type: &'static str,
+    body: Bytes,
+    filename: Option<&str>,
+) -> Response<Bytes> {
+    let content_length = body.len();
+    let mut res = Response::new(body);
+    *res.status_mut() = status;
+    let headers = res.headers_mut();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    headers.insert(CONTENT_LENGTH, HeaderValue::from(content_length));
+    headers.insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    if let Some(filename) = filename {
+        if let Ok(value) = HeaderValue::from_str(&format!("attachment; filename=\"{filename}\"")) {
+            headers.insert(CONTENT_DISPOSITION, value);
+        }
+    }
+    res
+}
+
+/// 代理的局域网地址，客户端通过回环地址访问时探测默认路由所在网卡的地址
+fn lan_addr(local_addr: Option<SocketAddr>) -> Option<SocketAddr> {
+    let local_addr = local_addr?;
+    if !local_addr.ip().is_loopback() && !local_addr.ip().is_unspecified() {
+        return Some(local_addr);
+    }
+    // UDP connect 不会发送数据，只用于选出路由对应的本地地址
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect("8.8.8.8:80").ok()?;
+    match socket.local_addr().ok()?.ip() {
+        IpAddr::V4(ip) if !ip.is_unspecified() => {
+            Some(SocketAddr::new(IpAddr::V4(ip), local_addr.port()))
+        }
+        _ => Some(local_addr),
+    }
+}
+
+fn render_page(addr: Option<SocketAddr>) -> String {
+    let (host, port) = match addr {
+        Some(addr) => (addr.ip().to_string(), addr.port().to_string()),
+        None => ("unknown".to_string(), "unknown".to_string()),
+    };
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>ez-shark certificate</title>
+<style>
+body {{ font-family: -apple-system, "Segoe UI", Roboto, sans-serif; max-width: 720px; margin: 0 auto; padding: 16px; color: #222; }}
+h1 {{ font-size: 22px; }}
+h2 {{ font-size: 17px; margin-top: 24px; }}
+code {{ background: #f2f2f2; padding: 1px 4px; border-radius: 3px; }}
+.proxy {{ background: #f6f8fa; border: 1px solid #ddd; border-radius: 6px; padding: 12px; }}
+.download a {{ display: inline-block; margin: 8px 8px 0 0; padding: 8px 14px; background: #1677ff; color: #fff; border-radius: 6px; text-decoration: none; }}
+</style>
+</head>
+<body>
+<h1>ez-shark CA certificate</h1>
+<div class="proxy">
+Proxy address: <code>{host}</code> &nbsp; Port: <code>{port}</code>
+</div>
+<div class="download">
+<a href="{DER_PATH}">Download .cer (DER)</a>
+<a href="{PEM_PATH}">Download .pem (PEM)</a>
+</div>
+
+<h2>iOS / iPadOS</h2>
+<ol>
+<li>Open this page in Safari and download the <code>.cer</code> certificate, then tap Allow.</li>
+<li>Settings &rarr; General &rarr; VPN &amp; Device Management, select <b>ez-shark</b> and tap Install.</li>
+<li>Settings &rarr; General &rarr; About &rarr; Certificate Trust Settings, enable full trust for <b>ez-shark</b>.</li>
+</ol>
+
+<h2>Android</h2>
+<ol>
+<li>Download the <code>.cer</code> certificate.</li>
+<li>Settings &rarr; Security &rarr; Encryption &amp; credentials &rarr; Install a certificate &rarr; CA certificate, then pick the downloaded file.</li>
+<li>Apps targeting Android 7+ only trust user CAs when their network security config allows it.</li>
+</ol>
+
+<h2>macOS</h2>
+<ol>
+<li>Download the <code>.cer</code> certificate and open it to add it to Keychain Access.</li>
+<li>Double-click <b>ez-shark</b> in the System keychain, expand Trust and choose Always Trust.</li>
+</ol>
+
+<h2>Windows</h2>
+<ol>
+<li>Download the <code>.cer</code> certificate and open it, then click Install Certificate.</li>
+<li>Choose Local Machine and place it in <b>Trusted Root Certification Authorities</b>.</li>
+</ol>
+
+<h2>Linux</h2>
+<ol>
+<li>Download the <code>.pem</code> certificate.</li>
+<li>Debian/Ubuntu: copy it to <code>/usr/local/share/ca-certificates/ez-shark.crt</code> and run <code>sudo update-ca-certificates</code>.</li>
+<li>Fedora/RHEL: copy it to <code>/etc/pki/ca-trust/source/anchors/</code> and run <code>sudo update-ca-trust</code>.</li>
+</ol>
+
+<h2>Firefox</h2>
+<ol>
+<li>Firefox uses its own trust store: Settings &rarr; Privacy &amp; Security &rarr; Certificates &rarr; View Certificates &rarr; Authorities &rarr; Import.</li>
+</ol>
+</body>
+</html>
+"#
+    )
+}
@@ -0,0 +1,542 @@
+use std::{
+    net::{IpAddr, ToSocketAddrs, UdpSocket},
+    sync::{mpsc, Arc, LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use boa_engine::{js_string, Context, JsArgs, JsResult, JsString, JsValue, NativeFunction, Source};
+use bytes::Bytes;
+use http::uri::Authority;
+use http_body_util::{BodyExt, Empty};
+use hyper::Uri;
+use hyper_rustls::HttpsConnectorBuilder;
+use hyper_util::{client::legacy::Client, rt::TokioExecutor};
+use log::{error, info};
+use moka::future::Cache;
+use tokio::sync::oneshot;
+use tokio_rustls::rustls::ClientConfig;
+
+use crate::{models::upstream_trust::UpstreamTrust, upstream_tls::root_store};
+
+/// PAC 脚本缓存时间，过期后重新加载
+const PAC_TTL: Duration = Duration::from_secs(300);
+/// 加载失败后的重试间隔，避免每个请求都等待下载超时
+const PAC_RETRY: Duration = Duration::from_secs(30);
+const PAC_FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+/// FindProxyForURL 结果的缓存，避免每个请求都重新执行脚本
+const PAC_RESULT_CACHE_SIZE: u64 = 1_000;
+const PAC_RESULT_TTL: Duration = Duration::from_secs(60);
+/// 防止脚本中的死循环卡住请求
+const PAC_LOOP_LIMIT: u64 = 1_000_000;
+/// 执行 PAC 脚本的常驻线程数，脚本中的 DNS 查询会阻塞线程
+const PAC_WORKERS: usize = 2;
+
+/// PAC 标准辅助函数，dnsResolve / myIpAddress 由原生函数提供
+const PAC_UTILS: &str = r#"
+function dnsDomainIs(host, domain) {
+  return host.length >= domain.length &&
+    host.substring(host.length - domain.length) == domain;
+}
+function dnsDomainLevels(host) {
+  return host.split('.').length - 1;
+}
+function isPlainHostName(host) {
+  return host.indexOf('.') == -1;
+}
+function isResolvable(host) {
+  return dnsResolve(host) != null;
+}
+function localHostOrDomainIs(host, hostdom) {
+  return host == hostdom || hostdom.lastIndexOf(host + '.', 0) == 0;
+}
+function convert_addr(ipchars) {
+  var bytes = ipchars.split('.');
+  return ((bytes[0] & 0xff) << 24) | ((bytes[1] & 0xff) << 16) |
+    ((bytes[2] & 0xff) << 8) | (bytes[3] & 0xff);
+}
+function isInNet(ipaddr, pattern, maskstr) {
+  if (!/^\d{1,3}\.\d{1,3}\.\d{1,3}\.\d{1,3}$/.test(ipaddr)) {
+    ipaddr = dnsResolve(ipaddr);
+    if (ipaddr == null) return false;
+  }
+  var mask = convert_addr(maskstr);
+  return (convert_addr(ipaddr) & mask) == (convert_addr(pattern) & mask);
+}
+function shExpMatch(url, pattern) {
+  pattern = pattern.replace(/[.+^${}()|[\]\\]/g, '\\$&');
+  pattern = pattern.replace(/\*/g, '.*').replace(/\?/g, '.');
+  return new RegExp('^' + pattern + '$').test(url);
+}
+var wdays = { SUN: 0, MON: 1, TUE: 2, WED: 3, THU: 4, FRI: 5, SAT: 6 };
+var months = { JAN: 0, FEB: 1, MAR: 2, APR: 3, MAY: 4, JUN: 5, JUL: 6, AUG: 7, SEP: 8, OCT: 9, NOV: 10, DEC: 11 };
+function weekdayRange() {
+  var argc = arguments.length;
+  if (argc < 1) return false;
+  var date = new Date();
+  var wday = date.getDay();
+  if (arguments[argc - 1] == 'GMT') { argc--; wday = date.getUTCDay(); }
+  var wd1 = arguments[0] in wdays ? wdays[arguments[0]] : -1;
+  var wd2 = argc == 2 ? (arguments[1] in wdays ? wdays[arguments[1]] : -1) : wd1;
+  if (wd1 == -1 || wd2 == -1) return false;
+  return wd1 <= wd2 ? wd1 <= wday && wday <= wd2 : wd2 >= wday || wday >= wd1;
+}
+function pacNow(isGMT) {
+  var date = new Date();
+  if (!isGMT) return date;
+  return new Date(date.getUTCFullYear(), date.getUTCMonth(), date.getUTCDate(),
+    date.getUTCHours(), date.getUTCMinutes(), date.getUTCSeconds());
+}
+function dateRange() {
+  var argc = arguments.length;
+  if (argc < 1) return false;
+  var isGMT = arguments[argc - 1] == 'GMT';
+  if (isGMT) argc--;
+  var date = pacNow(isGMT);
+  if (argc == 1) {
+    var value = parseInt(arguments[0]);
+    if (isNaN(value)) return date.getMonth() == months[arguments[0]];
+    if (value < 32) return date.getDate() == value;
+    return date.getFullYear() == value;
+  }
+  var year = date.getFullYear();
+  var date1 = new Date(year, 0, 1, 0, 0, 0);
+  var date2 = new Date(year, 11, 31, 23, 59, 59);
+  var adjustMonth = false;
+  for (var i = 0; i < argc; i++) {
+    var target = i < (argc >> 1) ? date1 : date2;
+    var value = parseInt(arguments[i]);
+    if (isNaN(value)) {
+      target.setMonth(months[arguments[i]]);
+    } else if (value < 32) {
+      adjustMonth = argc <= 2;
+      target.setDate(value);
+    } else {
+      target.setFullYear(value);
+    }
+  }
+  if (adjustMonth) {
+    date1.setMonth(date.getMonth());
+    date2.setMonth(date.getMonth());
+  }
+  return date1 <= date2 ? date1 <= date && date <= date2 : date2 >= date || date >= date1;
+}
+function timeRange() {
+  var argc = arguments.length;
+  if (argc < 1) return false;
+  var isGMT = arguments[argc - 1] == 'GMT';
+  if (isGMT) argc--;
+  var date = pacNow(isGMT);
+  if (argc == 1) return date.getHours() == arguments[0];
+  if (argc == 2) return arguments[0] <= date.getHours() && date.getHours() <= arguments[1];
+  var date1 = new Date(date.getTime());
+  var date2 = new Date(date.getTime());
+  var middle = argc >> 1;
+  date1.setHours(arguments[0], arguments[1], argc == 6 ? arguments[2] : 0);
+  date2.setHours(arguments[middle], arguments[middle + 1], argc == 6 ? arguments[5] : 59);
+  return date1 <= date2 ? date1 <= date && date <= date2 : date2 >= date || date >= date1;
+}
+"#;
+
+/// FindProxyForURL 返回的一项
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum PacProxy {
+    Direct,
+    Http(String, u16),
+    Https(String, u16),
+    Socks(String, u16),
+}
+
+struct CachedScript {
+    location: String,
+    loaded_at: Instant,
+    script: Result<Arc<str>, String>,
+}
+
+impl CachedScript {
+    fn is_fresh(&self) -> bool {
+        let ttl = match self.script {
+            Ok(_) => PAC_TTL,
+            Err(_) => PAC_RETRY,
+        };
+        self.loaded_at.elapsed() < ttl
+    }
+}
+
+static PAC_SCRIPT: LazyLock<Mutex<Option<CachedScript>>> = LazyLock::new(Default::default);
+
+/// 以 (脚本位置, 传给脚本的 URL) 为键，HTTPS 地址只保留到端口，相当于按 scheme+host+port 缓存，
+/// 明文 HTTP 地址按完整 URL 缓存；脚本重新加载后清空
+static PAC_RESULTS: LazyLock<Cache<(String, String), Vec<PacProxy>>> = LazyLock::new(|| {
+    Cache::builder()
+        .max_capacity(PAC_RESULT_CACHE_SIZE)
+        .time_to_live(PAC_RESULT_TTL)
+        .build()
+});
+
+/// 执行 PAC 脚本选择请求的代理，`location` 为本地文件路径或 HTTP(S) 地址
+pub(crate) async fn find_proxy_for_url(
+    location: &str,
+    url: &str,
+    trust: &UpstreamTrust,
+) -> Result<Vec<PacProxy>, String> {
+    let script = load_script(location, trust).await?;
+    let (url, host) = pac_arguments(url)?;
+    let key = (location.to_string(), url.clone());
+    if let Some(proxies) = PAC_RESULTS.get(&key).await {
+        return Ok(proxies);
+    }
+    let (sender, receiver) = oneshot::channel();
+    PAC_JOBS
+        .send(PacJob {
+            script,
+            url,
+            host,
+            result: sender,
+        })
+        .map_err(|_| "PAC 执行线程不可用".to_string())?;
+    let result = receiver
+        .await
+        .map_err(|_| "PAC 执行线程不可用".to_string())??;
+    let proxies = parse_pac_result(&result);
+    PAC_RESULTS.insert(key, proxies.clone()).await;
+    Ok(proxies)
+}
+
+/// 传给 FindProxyForURL 的参数，与浏览器一样 HTTPS/WSS 地址只保留到主机和端口，
+/// 明文地址传入去掉认证信息和片段的完整 URL，脚本中按 path 区分的规则只对明文请求生效
+fn pac_arguments(url: &str) -> Result<(String, String), String> {
+    let mut parsed = url::Url::parse(url).map_err(|e| format!("无效的 URL {}: {}", url, e))?;
+    let host = parsed
+        .host_str()
+        .unwrap_or_default()
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+    let url = match parsed.scheme() {
+        "https" | "wss" => format!(
+            "{}://{}/",
+            parsed.scheme(),
+            &parsed[url::Position::BeforeHost..url::Position::AfterPort]
+        ),
+        _ => {
+            let _ = parsed.set_username("");
+            let _ = parsed.set_password(None);
+            parsed.set_fragment(None);
+            parsed.to_string()
+        }
+    };
+    Ok((url, host))
+}
+
+async fn load_script(location: &str, trust: &UpstreamTrust) -> Result<Arc<str>, String> {
+    let stale = {
+        let cached = PAC_SCRIPT.lock().unwrap();
+        match cached.as_ref().filter(|cached| cached.location == location) {
+            Some(cached) if cached.is_fresh() => return cached.script.clone(),
+            Some(cached) => cached.script.clone().ok(),
+            None => None,
+        }
+    };
+
+    // 重新加载失败时继续使用旧脚本，等下一个缓存周期再重试
+    let script = match fetch_script(location, trust).await {
+        Ok(script) => {
+            PAC_RESULTS.invalidate_all();
+            Ok(Arc::from(script))
+        }
+        Err(err) => match stale {
+            Some(script) => {
+                error!("Failed to reload PAC script {}: {}", location, err);
+                Ok(script)
+            }
+            None => Err(err),
+        },
+    };
+    *PAC_SCRIPT.lock().unwrap() = Some(CachedScript {
+        location: location.to_string(),
+        loaded_at: Instant::now(),
+        script: script.clone(),
+    });
+    script
+}
+
+async fn fetch_script(location: &str, trust: &UpstreamTrust) -> Result<String, String> {
+    if !location.starts_with("http://") && !location.starts_with("https://") {
+        let path = location.strip_prefix("file://").unwrap_or(location);
+        return tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| format!("读取 PAC 文件 {} 失败: {}", path, e));
+    }
+
+    let uri: Uri = location
+        .parse()
+        .map_err(|e| format!("无效的 PAC 地址 {}: {}", location, e))?;
+    let tls = ClientConfig::builder()
+        .with_root_certificates(root_store(trust.native_roots, &trust.ca_files))
+        .with_no_client_auth();
+    let https = HttpsConnectorBuilder::new()
+        .with_tls_config(tls)
+        .https_or_http()
+        .enable_http1()
+        .build();
+    let client = Client::builder(TokioExecutor::new()).build::<_, Empty<Bytes>>(https);
+    let fetch = async {
+        let res = client
+            .get(uri)
+            .await
+            .map_err(|e| format!("下载 PAC 文件失败: {}", e))?;
+        if !res.status().is_success() {
+            return Err(format!(
+                "下载 PAC 文件失败: {} 返回 {}",
+                location,
+                res.status()
+            ));
+        }
+        let body = res
+            .into_body()
+            .collect()
+            .await
+            .map_err(|e| format!("下载 PAC 文件失败: {}", e))?
+            .to_bytes();
+        String::from_utf8(body.to_vec()).map_err(|e| format!("PAC 文件不是 UTF-8 文本: {}", e))
+    };
+    tokio::time::timeout(PAC_FETCH_TIMEOUT, fetch)
+        .await
+        .map_err(|_| format!("下载 PAC 文件超时: {}", location))?
+}
+
+/// 交给 PAC 线程执行的一次 FindProxyForURL 调用
+struct PacJob {
+    script: Arc<str>,
+    url: String,
+    host: String,
+    result: oneshot::Sender<Result<String, String>>,
+}
+
+/// 常驻的 PAC 执行线程，boa 的 Context 不能跨线程使用，且在线程退出时析构会出错，
+/// 因此不使用 spawn_blocking
+static PAC_JOBS: LazyLock<mpsc::Sender<PacJob>> = LazyLock::new(|| {
+    let (sender, receiver) = mpsc::channel();
+    let receiver = Arc::new(Mutex::new(receiver));
+    for index in 0..PAC_WORKERS {
+        let receiver = Arc::clone(&receiver);
+        let spawned = std::thread::Builder::new()
+            .name(format!("pac-worker-{index}"))
+            .spawn(move || pac_worker(&receiver));
+        if let Err(err) = spawned {
+            error!("Failed to spawn PAC worker: {}", err);
+        }
+    }
+    sender
+});
+
+/// 每个线程保留已解析脚本的 Context，以脚本 Arc 的指针区分，脚本重新加载后才重新解析
+fn pac_worker(jobs: &Mutex<mpsc::Receiver<PacJob>>) {
+    let mut parsed: Option<(Arc<str>, Context)> = None;
+    loop {
+        let job = jobs.lock().unwrap().recv();
+        let Ok(job) = job else {
+            return;
+        };
+        if !parsed
+            .as_ref()
+            .is_some_and(|(script, _)| Arc::ptr_eq(script, &job.script))
+        {
+            parsed = None;
+            match new_context(&job.script) {
+                Ok(context) => parsed = Some((Arc::clone(&job.script), context)),
+                Err(err) => {
+                    let _ = job.result.send(Err(err));
+                    continue;
+                }
+            }
+        }
+        if let Some((_, context)) = parsed.as_mut() {
+            let _ = job.result.send(find_proxy(context, &job.url, &job.host));
+        }
+    }
+}
+
+fn find_proxy(context: &mut Context, url: &str, host: &str) -> Result<String, String> {
+    let function = context
+        .global_object()
+        .get(js_string!("FindProxyForURL"), context)
+        .map_err(|e| format!("PAC 执行失败: {}", e))?;
+    let function = function
+        .as_callable()
+        .ok_or("PAC 脚本中未定义 FindProxyForURL")?
+        .clone();
+    let args = [JsString::from(url).into(), JsString::from(host).into()];
+    function
+        .call(&JsValue::undefined(), &args, context)
+        .and_then(|result| result.to_string(context))
+        .map(|result| result.to_std_string_escaped())
+        .map_err(|e| format!("PAC 执行失败: {}", e))
+}
+
+fn new_context(script: &str) -> Result<Context, String> {
+    let mut context = Context::default();
+    context
+        .runtime_limits_mut()
+        .set_loop_iteration_limit(PAC_LOOP_LIMIT);
+    let natives: [(JsString, usize, NativeFunction); 3] = [
+        (
+            js_string!("dnsResolve"),
+            1,
+            NativeFunction::from_fn_ptr(dns_resolve),
+        ),
+        (
+            js_string!("myIpAddress"),
+            0,
+            NativeFunction::from_fn_ptr(my_ip_address),
+        ),
+        (js_string!("alert"), 1, NativeFunction::from_fn_ptr(alert)),
+    ];
+    for (name, length, function) in natives {
+        context
+            .register_global_builtin_callable(name, length, function)
+            .map_err(|e| format!("PAC 初始化失败: {}", e))?;
+    }
+    context
+        .eval(Source::from_bytes(PAC_UTILS))
+        .map_err(|e| format!("PAC 初始化失败: {}", e))?;
+    context
+        .eval(Source::from_bytes(script))
+        .map_err(|e| format!("PAC 脚本解析失败: {}", e))?;
+    Ok(context)
+}
+
+fn dns_resolve(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let host = args
+        .get_or_undefined(0)
+        .to_string(context)?
+        .to_std_string_escaped();
+    let ip = (host.as_str(), 0)
+        .to_socket_addrs()
+        .ok()
+        .and_then(|mut addrs| addrs.find(|addr| addr.is_ipv4()));
+    Ok(match ip {
+        Some(addr) => JsString::from(addr.ip().to_string()).into(),
+        None => JsValue::null(),
+    })
+}
+
+fn my_ip_address(_: &JsValue, _: &[JsValue], _: &mut Context) -> JsResult<JsValue> {
+    // UDP connect 不会发送数据，只用于选出默认路由对应的本地地址
+    let ip = UdpSocket::bind("0.0.0.0:0")
+        .and_then(|socket| socket.connect("8.8.8.8:80").map(|_| socket))
+        .and_then(|socket| socket.local_addr())
+        .map(|addr| addr.ip())
+        .unwrap_or(IpAddr::from([127, 0, 0, 1]));
+    Ok(JsString::from(ip.to_string()).into())
+}
+
+fn alert(_: &JsValue, args: &[JsValue], context: &mut Context) -> JsResult<JsValue> {
+    let message = args
+        .get_or_undefined(0)
+        .to_string(context)?
+        .to_std_string_escaped();
+    info!("PAC alert: {}", message);
+    Ok(JsValue::undefined())
+}
+
+/// 解析 FindProxyForURL 的返回值，如 `PROXY a:8080; SOCKS5 b:1080; DIRECT`
+/// 不支持的类型会被跳过，没有可用项时直连
+pub(crate) fn parse_pac_result(result: &str) -> Vec<PacProxy> {
+    let mut proxies: Vec<PacProxy> = result
+        .split(';')
+        .filter_map(|item| {
+            let mut parts = item.split_whitespace();
+            let kind = parts.next()?.to_ascii_uppercase();
+            if kind == "DIRECT" {
+                return Some(PacProxy::Direct);
+            }
+            let default_port = match kind.as_str() {
+                "PROXY" | "HTTP" => 80,
+                "HTTPS" => 443,
+                "SOCKS" | "SOCKS5" => 1080,
+                _ => {
+                    error!("Unsupported PAC proxy type: {}", item.trim());
+                    return None;
+                }
+            };
+            let authority: Authority = parts.next()?.parse().ok()?;
+            let host = authority.host().to_string();
+            let port = authority.port_u16().unwrap_or(default_port);
+            Some(match kind.as_str() {
+                "HTTPS" => PacProxy::Https(host, port),
+                "SOCKS" | "SOCKS5" => PacProxy::Socks(host, port),
+                _ => PacProxy::Http(host, port),
+            })
+        })
+        .collect();
+    if proxies.is_empty() {
+        proxies.push(PacProxy::Direct);
+    }
+    proxies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pac_result() {
+        assert_eq!(
+            parse_pac_result("PROXY a:1; SOCKS5 b; DIRECT"),
+            vec![
+                PacProxy::Http("a".to_string(), 1),
+                PacProxy::Socks("b".to_string(), 1080),
+                PacProxy::Direct,
+            ]
+        );
+        assert_eq!(
+            parse_pac_result(" https proxy.example ;; socks [::1]:1081 "),
+            vec![
+                PacProxy::Https("proxy.example".to_string(), 443),
+                PacProxy::Socks("[::1]".to_string(), 1081),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_pac_result_fallback() {
+        assert_eq!(
+            parse_pac_result("QUIC a:443; PROXY b:8080"),
+            vec![PacProxy::Http("b".to_string(), 8080)]
+        );
+        assert_eq!(parse_pac_result("QUIC a:443"), vec![PacProxy::Direct]);
+        assert_eq!(parse_pac_result(""), vec![PacProxy::Direct]);
+        assert_eq!(parse_pac_result("PROXY"), vec![PacProxy::Direct]);
+    }
+
+    #[test]
+    fn test_pac_arguments() {
+        assert_eq!(
+            pac_arguments("https://api.example.com/users?id=1").unwrap(),
+            (
+                "https://api.example.com/".to_string(),
+                "api.example.com".to_string()
+            )
+        );
+        assert_eq!(
+            pac_arguments("wss://example.com:8443/socket").unwrap(),
+            (
+                "wss://example.com:8443/".to_string(),
+                "example.com".to_string()
+            )
+        );
+        assert_eq!(
+            pac_arguments("http://user:pw@example.com:8080/path?q=1#top").unwrap(),
+            (
+                "http://example.com:8080/path?q=1".to_string(),
+                "example.com".to_string()
+            )
+        );
+        assert_eq!(
+            pac_arguments("http://[::1]:3000/").unwrap(),
+            ("http://[::1]:3000/".to_string(), "::1".to_string())
+        );
+        assert!(pac_arguments("not a url").is_err());
+    }
+}
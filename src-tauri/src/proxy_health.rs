@@ -0,0 +1,263 @@
+use std::{
+    collections::HashMap,
+    io,
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+
+use log::{info, warn};
+use tauri::{AppHandle, Runtime};
+use tokio::time::timeout;
+
+use crate::{
+    models::{
+        dns_override::OverrideResolver, external_proxy::get_proxy_config,
+        upstream_trust::get_upstream_trust_config,
+    },
+    upstream_proxy::UpstreamProxy,
+};
+
+/// 连续失败达到该次数后熔断
+const FAILURE_THRESHOLD: u32 = 3;
+/// 熔断后跳过代理的时长
+const COOLDOWN: Duration = Duration::from_secs(30);
+/// 健康检查的间隔
+const PROBE_INTERVAL: Duration = Duration::from_secs(15);
+/// 单次健康检查或连通性测试的超时
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+/// 超过该时长未被请求使用的代理不再检查，外部代理配置中的代理始终检查
+const IDLE_EXPIRY: Duration = Duration::from_secs(600);
+
+/// 单个代理的熔断状态，以 [`UpstreamProxy::health_key`] 为键
+struct ProxyHealth {
+    proxy: UpstreamProxy,
+    failures: u32,
+    open_until: Option<Instant>,
+    last_used: Instant,
+}
+
+impl ProxyHealth {
+    fn new(proxy: UpstreamProxy, now: Instant) -> Self {
+        Self {
+            proxy,
+            failures: 0,
+            open_until: None,
+            last_used: now,
+        }
+    }
+
+    /// 熔断期间不可用，熔断到期后允许一次尝试，再次失败立即重新熔断
+    fn is_available(&self, now: Instant) -> bool {
+        self.open_until.is_none_or(|until| until <= now)
+    }
+
+    /// 记录一次失败，`used` 为 false 时来自健康检查，不刷新最近使用时间；返回是否熔断
+    fn fail(&mut self, now: Instant, used: bool) -> bool {
+        self.failures += 1;
+        if used {
+            self.last_used = now;
+        }
+        if self.failures >= FAILURE_THRESHOLD {
+            self.open_until = Some(now + COOLDOWN);
+        }
+        self.open_until.is_some()
+    }
+
+    fn is_idle(&self, now: Instant) -> bool {
+        now.duration_since(self.last_used) >= IDLE_EXPIRY
+    }
+}
+
+static PROXY_HEALTH: LazyLock<Mutex<HashMap<String, ProxyHealth>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// 代理是否可用，熔断期间返回 false
+pub(crate) fn is_available(proxy: &UpstreamProxy) -> bool {
+    let health = PROXY_HEALTH.lock().unwrap();
+    match health.get(&proxy.health_key()) {
+        Some(state) => state.is_available(Instant::now()),
+        None => true,
+    }
+}
+
+pub(crate) fn record_success(proxy: &UpstreamProxy) {
+    let key = proxy.health_key();
+    if PROXY_HEALTH.lock().unwrap().remove(&key).is_some() {
+        info!("Upstream proxy {} recovered", proxy.label());
+    }
+}
+
+/// 记录连接失败，只统计代理本身不可达的错误；代理拒绝 CONNECT 等说明代理仍然可用
+pub(crate) fn record_failure(proxy: &UpstreamProxy, err: &io::Error) {
+    count_failure(proxy, err, true);
+}
+
+fn count_failure(proxy: &UpstreamProxy, err: &io::Error, used: bool) {
+    if !is_unreachable(err) {
+        return;
+    }
+    let key = proxy.health_key();
+    let now = Instant::now();
+    let mut health = PROXY_HEALTH.lock().unwrap();
+    let state = health
+        .entry(key)
+        .or_insert_with(|| ProxyHealth::new(proxy.clone(), now));
+    if state.fail(now, used) {
+        warn!(
+            "Upstream proxy {} failed {} times, skipping for {}s: {}",
+            proxy.label(),
+            state.failures,
+            COOLDOWN.as_secs(),
+            err
+        );
+    }
+}
+
+fn is_unreachable(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::TimedOut
+            | io::ErrorKind::UnexpectedEof
+            | io::ErrorKind::HostUnreachable
+            | io::ErrorKind::NetworkUnreachable
+            | io::ErrorKind::AddrNotAvailable
+    )
+}
+
+/// 定期探测外部代理配置中的代理以及出现过失败的代理（如 PAC 给出的代理）
+///
+/// 恢复后提前结束熔断，熔断期间仍不可用时延长熔断；
+/// 未熔断的代理探测失败与请求失败一样计数，请求到来前即可跳过不可用的代理
+pub(crate) async fn run_health_probes<R: Runtime>(app: AppHandle<R>) {
+    loop {
+        tokio::time::sleep(PROBE_INTERVAL).await;
+
+        let mut proxies: HashMap<String, UpstreamProxy> = {
+            let mut health = PROXY_HEALTH.lock().unwrap();
+            let now = Instant::now();
+            health.retain(|_, state| !state.is_idle(now));
+            health
+                .iter()
+                .map(|(key, state)| (key.clone(), state.proxy.clone()))
+                .collect()
+        };
+        if let Ok(proxy_config) = get_proxy_config(&app) {
+            let trust = get_upstream_trust_config(&app).unwrap_or_default();
+            for proxy in UpstreamProxy::configured(&proxy_config, &trust) {
+                proxies.entry(proxy.health_key()).or_insert(proxy);
+            }
+        }
+
+        for (key, proxy) in proxies {
+            let result = timeout(PROBE_TIMEOUT, proxy.probe())
+                .await
+                .unwrap_or_else(|_| Err(io::ErrorKind::TimedOut.into()));
+            let mut health = PROXY_HEALTH.lock().unwrap();
+            match result {
+                Ok(()) => {
+                    if health.remove(&key).is_some() {
+                        info!("Upstream proxy {} is reachable again", proxy.label());
+                    }
+                }
+                Err(err) => match health.get_mut(&key) {
+                    Some(state) if state.open_until.is_some() => {
+                        state.open_until = Some(Instant::now() + COOLDOWN);
+                    }
+                    _ => {
+                        drop(health);
+                        count_failure(&proxy, &err, false);
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// 通过代理建立到 `host:port` 的隧道，验证代理的连通性与认证，返回耗时
+pub(crate) async fn test_proxy(
+    proxy: &UpstreamProxy,
+    host: &str,
+    port: u16,
+) -> Result<Duration, String> {
+    let start = Instant::now();
+    let resolver = OverrideResolver::new(None);
+    timeout(PROBE_TIMEOUT, proxy.connect(host, port, &resolver))
+        .await
+        .map_err(|_| format!("连接 {} 超时", proxy.label()))?
+        .map_err(|e| format!("{}: {}", proxy.label(), e))?;
+    Ok(start.elapsed())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{
+        external_proxy::MutableExternalProxyConfiguration, upstream_trust::UpstreamTrust,
+    };
+
+    fn proxy(port: u16) -> UpstreamProxy {
+        let config: MutableExternalProxyConfiguration = serde_json::from_value(serde_json::json!({
+            "domain": "", "encryptedPassword": "", "host": "127.0.0.1", "port": port,
+            "requiresAuthentication": false, "username": ""
+        }))
+        .unwrap();
+        UpstreamProxy::from_entry("socks", &config, &UpstreamTrust::default()).unwrap()
+    }
+
+    #[test]
+    fn test_proxy_health_transitions() {
+        let now = Instant::now();
+        let mut state = ProxyHealth::new(proxy(1), now);
+        assert!(!state.fail(now, true));
+        assert!(!state.fail(now, true));
+        assert!(state.is_available(now));
+        assert!(state.fail(now, true));
+        assert!(!state.is_available(now + COOLDOWN - Duration::from_secs(1)));
+
+        // 熔断到期后允许尝试，第一次失败即重新熔断
+        let later = now + COOLDOWN;
+        assert!(state.is_available(later));
+        assert!(state.fail(later, true));
+        assert!(!state.is_available(later + Duration::from_secs(1)));
+        assert!(state.is_available(later + COOLDOWN));
+    }
+
+    #[test]
+    fn test_proxy_health_idle() {
+        let now = Instant::now();
+        let mut state = ProxyHealth::new(proxy(1), now);
+        let expiry = now + IDLE_EXPIRY;
+        assert!(!state.is_idle(expiry - Duration::from_secs(1)));
+        // 健康检查的失败不算使用
+        state.fail(expiry, false);
+        assert!(state.is_idle(expiry));
+        state.fail(expiry, true);
+        assert!(!state.is_idle(expiry));
+    }
+
+    #[test]
+    fn test_record_failure() {
+        let proxy = proxy(2);
+        let key = proxy.health_key();
+        // 代理拒绝 CONNECT、认证失败等不计入熔断
+        for _ in 0..FAILURE_THRESHOLD {
+            record_failure(&proxy, &io::ErrorKind::PermissionDenied.into());
+            record_failure(&proxy, &io::Error::other("HTTP proxy refused CONNECT"));
+        }
+        assert!(is_available(&proxy));
+        assert!(!PROXY_HEALTH.lock().unwrap().contains_key(&key));
+
+        for _ in 0..FAILURE_THRESHOLD {
+            record_failure(&proxy, &io::ErrorKind::ConnectionRefused.into());
+        }
+        assert!(!is_available(&proxy));
+
+        record_success(&proxy);
+        assert!(is_available(&proxy));
+        assert!(!PROXY_HEALTH.lock().unwrap().contains_key(&key));
+    }
+}
@@ -1,15 +1,40 @@
 use crate::frontend_message::{send_to_frontend, Payload, SendData, Status};
-use crate::models::crypto::CRYPTO_SERVICE;
-use crate::models::external_proxy::check_proxy_config;
-use crate::models::map_local::{check_need_map_local, get_map_local_config};
+use crate::models::block_list::{
+    check_need_block, get_allow_list_config, get_block_list_config, BlockAction,
+};
+use crate::models::certificate::get_certificate_config;
+use crate::models::client_cert::{check_need_client_cert, get_client_certs_config, ClientIdentity};
+use crate::models::dns_override::{get_dns_override_config, OverrideResolver};
+use crate::models::fault::{check_need_fault, get_fault_config, FaultItem, FaultType};
+use crate::models::map_local::{
+    check_need_map_local, get_map_local_config, render_template, MapLocalItem,
+};
+use crate::models::map_remote::{check_need_map_remote, get_map_remote_config};
+use crate::models::rewrite::{
+    apply_rewrite_body, apply_rewrite_head, check_need_rewrite, get_rewrite_config, has_body_rules,
+    MatchedRewriteRule, RewritePhase,
+};
+use crate::models::ssl_pinning::{
+    get_ssl_pinning_config, pinned_key, pinning_failure_reason, save_ssl_pinning_config, PinnedHost,
+};
+use crate::models::ssl_proxying::{check_need_ssl_proxying, get_ssl_proxying_config};
+use crate::models::throttle::{check_need_throttle, get_throttle_config, ThrottleProfile};
+use crate::models::upstream_trust::get_upstream_trust_config;
 use crate::models::{get_proxy_config, ExternalProxy};
 use crate::state::BreakpointsConfig;
 use crate::traffic::{bytes_to_hex_structs, TrafficHead};
 use crate::{
     cert::CertificateAuthority,
+    key_log::{configure_key_log, KEY_LOG},
+    onboarding::{cert_page_response, is_cert_host},
     rewind::Rewind,
     state::{BreakpointMatchResult, PausedTrafficInfo, State, TrafficData},
+    throttled::{RateLimiter, ThrottledStream},
+    timings::{ConnectStage, TimedConnector, TimingRecorder},
+    tls_info::{TlsInspector, TlsSession, UpstreamCertError},
     traffic::{extract_mime, Body as TrafficBody, Header, Traffic, TransactionState},
+    upstream_proxy::{connect_direct, ProxyRoute, TcpConnector, UpstreamRoute, UpstreamStream},
+    upstream_tls::{root_store, AcceptAnyCert, CertErrorSlot, RecordingVerifier},
     utils::*,
 };
 use anyhow::{anyhow, Context as _, Result};
@@ -18,23 +43,25 @@ use async_compression::tokio::{
     write::{BrotliEncoder, DeflateEncoder, GzipEncoder, ZstdEncoder},
 };
 use bytes::Bytes;
-use headers::Authorization;
 use http::HeaderMap;
 use http::{
-    header::{CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE},
+    header::{CONTENT_ENCODING, CONTENT_LENGTH, CONTENT_TYPE, PROXY_AUTHORIZATION},
     uri::{Authority, Scheme},
     HeaderValue,
 };
-use http_body_util::{combinators::BoxBody, BodyExt, Full};
+use http_body_util::{
+    combinators::{BoxBody, UnsyncBoxBody},
+    BodyExt, Full, StreamBody,
+};
 use hyper::{
     body::{Body, Frame, Incoming},
+    ext::ReasonPhrase,
     header::HeaderName,
     service::service_fn,
     Method, StatusCode, Uri,
 };
-use hyper_proxy2::{Intercept, Proxy, ProxyConnector};
 use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
-use hyper_util::client::legacy::connect::{Connect, HttpConnector};
+use hyper_util::client::legacy::connect::{Connect, HttpConnector, HttpInfo};
 use hyper_util::{
     client::legacy::Client,
     rt::{TokioExecutor, TokioIo},
@@ -48,17 +75,21 @@ use std::fs;
 use std::fs::File;
 use std::io::BufReader;
 use std::marker::Unpin;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::{
     collections::HashMap,
     convert::Infallible,
+    future::Future,
     io::Write,
     net::SocketAddr,
     path::PathBuf,
     pin::Pin,
     process,
-    sync::Arc,
-    task::{Context, Poll},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{ready, Context, Poll},
 };
 use tauri_plugin_store::StoreBuilder;
 use time::OffsetDateTime;
@@ -66,13 +97,41 @@ use tokio::{
     io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
     net::{TcpListener, TcpStream},
     sync::{oneshot, Mutex, Notify},
+    time::Sleep,
 };
 use tokio_graceful::Shutdown;
-use tokio_rustls::TlsAcceptor;
+use tokio_rustls::{
+    rustls::{
+        pki_types::{CertificateDer, ServerName},
+        server::Acceptor,
+        ClientConfig, ServerConfig,
+    },
+    server::TlsStream as ServerTlsStream,
+    LazyConfigAcceptor, TlsConnector,
+};
 use uuid::Uuid;
 // type TrafficDoneSender = mpsc::UnboundedSender<(usize, u64)>;
 type Request = hyper::Request<Incoming>;
 type Response = hyper::Response<BoxBody<Bytes, anyhow::Error>>;
+type UpstreamConnector = TimedConnector<TlsInspector<HttpsConnector<TimedConnector<TcpConnector>>>>;
+
+static NEXT_CONNECTION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// 握手失败计数最多跟踪的主机数
+const HANDSHAKE_FAILURE_CAPACITY: u64 = 1000;
+/// 握手失败计数的有效期，长时间未再失败的主机重新计数
+const HANDSHAKE_FAILURE_TTL: Duration = Duration::from_secs(600);
+
+/// 客户端连接信息，通过请求扩展传递到 `handle`
+#[derive(Debug, Clone)]
+struct ConnectionInfo {
+    client_addr: SocketAddr,
+    /// 客户端连接到代理时使用的本地地址
+    local_addr: Option<SocketAddr>,
+    id: u64,
+    /// 客户端 TLS 握手信息，仅在 CONNECT 隧道内解密时存在
+    tls: Option<Arc<TlsSession>>,
+}
 pub type TrafficTuple = (Option<String>, TrafficData);
 
 #[derive(Debug, Clone, Serialize)]
@@ -150,6 +209,10 @@ impl ServerBuilder {
             state: Arc::new(State::new(self.print_mode, self.app_handle.clone())),
             temp_dir,
             app_handle: self.app_handle,
+            handshake_failures: moka::sync::Cache::builder()
+                .max_capacity(HANDSHAKE_FAILURE_CAPACITY)
+                .time_to_live(HANDSHAKE_FAILURE_TTL)
+                .build(),
         })
     }
 }
@@ -159,6 +222,8 @@ pub struct Server {
     state: Arc<State>,
     pub temp_dir: PathBuf,
     app_handle: tauri::AppHandle,
+    /// 各主机连续的客户端握手失败次数，用于识别证书固定
+    handshake_failures: moka::sync::Cache<String, u32>,
 }
 
 impl Server {
@@ -187,6 +252,7 @@ impl Server {
                         let Ok((cnx, addr)) = res else {
                             continue;
                         };
+                        let local_addr = cnx.local_addr().ok();
 
                         // 转换为标准库的 TcpStream
                         let std_stream = match cnx.into_std() {
@@ -230,11 +296,19 @@ impl Server {
                         let server_cloned = server_cloned.clone();
                         // let active_connections = active_connections_clone.clone();
 
+                        let connection_info = ConnectionInfo {
+                            client_addr: addr,
+                            local_addr,
+                            id: NEXT_CONNECTION_ID.fetch_add(1, Ordering::Relaxed),
+                            tls: None,
+                        };
+
                         shutdown.spawn_task(async move {
                             let io = TokioIo::new(stream_for_hyper);
 
-                            let hyper_service = service_fn(move |request: hyper::Request<Incoming>| {
-                                server_cloned.clone().handle(request)
+                            let hyper_service = service_fn(move |mut request: hyper::Request<Incoming>| {
+                                request.extensions_mut().insert(connection_info.clone());
+                                server_cloned.clone().serve(request)
                             });
 
                             let res = hyper_util::server::conn::auto::Builder::new(TokioExecutor::new())
@@ -400,8 +474,7 @@ impl Server {
         session_id: String,
     ) -> Result<Response, hyper::Error>
     where
-        B: Body + Send + Unpin + 'static,
-        B::Data: Send,
+        B: Body<Data = Bytes> + Send + Unpin + 'static,
         B::Error: Into<Box<dyn StdError + Send + Sync>>,
     {
         let method = match traffic.method.to_lowercase().as_str() {
@@ -420,138 +493,151 @@ impl Server {
             }
         };
 
+        // 限速：记录生效的配置并模拟额外延迟
+        let throttle = self.throttle_profile(&traffic.uri);
+        if let Some(profile) = &throttle {
+            let mut traffic_clone = Traffic::clone(&traffic);
+            traffic_clone.throttle = Some(profile.display_name());
+            traffic = Arc::new(traffic_clone);
+            if profile.latency_ms > 0 {
+                tokio::time::sleep(Duration::from_millis(profile.latency_ms)).await;
+            }
+        }
+
+        // 故障注入：直接返回错误状态码
+        if let Some(fault) = self.faults(&traffic, &[FaultType::ErrorStatus]).pop() {
+            return self.fault_status_response(fault, traffic, head_id).await;
+        }
+
         if let Ok(map_local) = get_map_local_config(&self.app_handle) {
             // debug!("map_local:{:?}", map_local);
-            if let Ok(item) = check_need_map_local(map_local) {
-                // 检查是否需要MapLocal
+            // 检查是否需要MapLocal
+            if let Some(item) = check_need_map_local(map_local, &traffic.method, &traffic.uri) {
                 // debug!("item:{:?}", item);
-                if traffic.uri.contains(&item.url) {
-                    // debug!("匹配到MapLocal规则");
-                    let mut res = Response::default();
-                    *res.status_mut() = StatusCode::OK;
+                return self.map_local_response(item, traffic, head_id).await;
+            }
+        }
+
+        // 应用 Rewrite 规则，URL 改写结果写入 mapped_uri
+        let rewrite_rules = self.rewrite_rules(&traffic);
+        if !rewrite_rules.is_empty() {
+            let mut traffic_clone = Traffic::clone(&traffic);
+            apply_rewrite_head(&rewrite_rules, RewritePhase::Request, &mut traffic_clone);
+            traffic = Arc::new(traffic_clone);
+        }
+        let bytes: UnsyncBoxBody<Bytes, Box<dyn StdError + Send + Sync>> =
+            if has_body_rules(&rewrite_rules, RewritePhase::Request) {
+                let raw = match self.get_body_bytes(Some(bytes)).await {
+                    Ok(v) => v,
+                    Err(err) => return self.internal_server_error(err, traffic, head_id).await,
+                };
+                let mut traffic_clone = Traffic::clone(&traffic);
+                let body = self
+                    .rewrite_body(
+                        &rewrite_rules,
+                        RewritePhase::Request,
+                        raw,
+                        &mut traffic_clone,
+                    )
+                    .await;
+                traffic = Arc::new(traffic_clone);
+                Full::new(body)
+                    .map_err(|never: Infallible| match never {})
+                    .boxed_unsync()
+            } else {
+                bytes.map_err(Into::into).boxed_unsync()
+            };
+        let recorder = TimingRecorder::default();
+        let bytes = BodyWrapper::new(bytes, None, None, None, None)
+            .with_throttle(
+                throttle
+                    .as_ref()
+                    .and_then(|profile| RateLimiter::new(profile.upload_kbps)),
+            )
+            .with_timing(recorder.clone());
+
+        if let Ok(map_remote) = get_map_remote_config(&self.app_handle) {
+            // 检查是否需要MapRemote，匹配 Rewrite 之后的 URL
+            let current_uri = traffic
+                .mapped_uri
+                .clone()
+                .unwrap_or_else(|| traffic.uri.clone());
+            if let Some(item) = check_need_map_remote(map_remote, &traffic.method, &current_uri) {
+                if let Some(mapped_uri) = item.map_url(&current_uri) {
+                    debug!("map remote: {} -> {}", current_uri, mapped_uri);
                     let mut traffic_clone = Traffic::clone(&traffic);
-                    traffic_clone.set_res_status(StatusCode::OK);
-                    // 添加头部
-                    if !item.header_local.is_empty() {
-                        // debug!("读取header_local:{}", item.header_local);
-                        if let Ok(header_data) = File::open(item.header_local) {
-                            let reader = BufReader::new(header_data);
-                            if let Ok(json_value) =
-                                serde_json::from_reader::<_, serde_json::Value>(reader)
-                            {
-                                if let serde_json::Value::Object(map) = json_value {
-                                    let mut headers = HeaderMap::new();
-                                    for (key, value) in map.iter() {
-                                        if let (Ok(header_name), Ok(header_value)) = (
-                                            HeaderName::from_bytes(key.as_bytes()),
-                                            HeaderValue::from_str(
-                                                &value.to_string().trim_matches('"'),
-                                            ),
-                                        ) {
-                                            res.headers_mut()
-                                                .insert(&header_name, header_value.clone());
-                                            headers.insert(header_name, header_value);
-                                        }
-                                    }
-                                    traffic_clone.set_res_headers(&headers);
-                                }
+                    if !item.preserve_host_header {
+                        if let Ok(url) = url::Url::parse(&mapped_uri) {
+                            let host = match (url.host_str(), url.port()) {
+                                (Some(host), Some(port)) => format!("{host}:{port}"),
+                                (Some(host), None) => host.to_string(),
+                                _ => String::new(),
+                            };
+                            if !host.is_empty() {
+                                traffic_clone.set_forwarded_req_header("host", &host);
                             }
                         }
                     }
-
-                    // 构建 body
-                    let body = if !item.body_local.is_empty() {
-                        traffic_clone.res_body_file = Some(item.body_local.clone());
-                        // debug!("读取body_local:{}", item.body_local);
-                        if let Ok(b) = fs::read(item.body_local.clone()) {
-                            let bytes = Bytes::from(b);
-                            traffic_clone.res_body_hex = Some(bytes_to_hex_structs(&bytes));
-                            // debug!("设置 res_body_hex");
-                            BoxBody::new(Full::new(bytes))
-                                .map_err(|_: Infallible| anyhow::Error::msg("Body error"))
-                                .boxed()
-                        } else {
-                            // 文件读取失败，使用空 body
-                            BoxBody::new(Full::new(Bytes::new()))
-                                .map_err(|_: Infallible| anyhow::Error::msg("Empty body"))
-                                .boxed()
-                        }
-                    } else {
-                        // debug!("body_local为空，使用空 body");
-                        // 没有本地文件，使用空 body
-                        BoxBody::new(Full::new(Bytes::new()))
-                            .map_err(|_: Infallible| anyhow::Error::msg("Empty body"))
-                            .boxed()
-                    };
-                    let res_body: BodyWrapper<BoxBody<Bytes, anyhow::Error>> = {
-                        let body_file = match &traffic_clone.res_body_file {
-                            Some(file) => match File::open(file) {
-                                Ok(f) => Some(f),
-                                Err(_) => None,
-                            },
-                            None => None,
-                        };
-
-                        BodyWrapper::new(
-                            body,
-                            body_file,
-                            traffic.res_body_file.clone(),
-                            Some((head_id, self.state.clone())),
-                            Some(res.headers().clone()),
-                        )
-                    };
-                    *res.body_mut() = BoxBody::new(res_body);
-                    if let Some(hd_id) = head_id {
-                        traffic = Arc::new(traffic_clone);
-
-                        self.state.traffics.insert(hd_id, traffic).await;
-                    }
-                    // debug!("返回伪造响应");
-                    return Ok(res);
+                    traffic_clone.mapped_uri = Some(mapped_uri);
+                    traffic = Arc::new(traffic_clone);
                 }
             }
         }
+        let upstream_uri = traffic
+            .mapped_uri
+            .clone()
+            .unwrap_or_else(|| traffic.uri.clone());
 
         // 获取代理配置并检查是否需要使用代理
         match get_proxy_config(&self.app_handle) {
             Ok(proxy_config) => {
-                let mut builder = hyper::Request::builder().uri(&traffic.uri).method(method);
+                let mut builder = hyper::Request::builder().uri(&upstream_uri).method(method);
 
-                if let Some(req_headers) = &traffic.req_headers {
+                if let Some(req_headers) = traffic.upstream_req_headers() {
                     for header in &req_headers.items {
                         builder = builder.header(&header.name, &header.value);
                     }
                 }
 
-                let proxy_req = match builder.body(bytes) {
+                let mut proxy_req = match builder.body(bytes) {
                     Ok(v) => v,
                     Err(err) => {
                         return self.internal_server_error(err, traffic, head_id).await;
                     }
                 };
 
-                let https = HttpsConnectorBuilder::new()
-                    .with_webpki_roots()
-                    .https_or_http()
-                    .enable_all_versions()
-                    .build();
-
-                let need_proxy = check_proxy_config(&proxy_config, traffic.uri.clone());
-                debug!("need_proxy={}", need_proxy);
-                if need_proxy {
-                    self.send_request_with_proxy(
-                        &proxy_config,
-                        https,
-                        proxy_req,
-                        traffic,
-                        head_id,
-                        session_id,
-                    )
-                    .await
-                } else {
-                    self.send_request_direct(https, proxy_req, traffic, head_id, session_id)
-                        .await
+                let client_cert = match self.client_cert(&upstream_uri) {
+                    Ok(client_cert) => client_cert,
+                    Err(err) => return self.internal_server_error(err, traffic, head_id).await,
+                };
+                if let Some(identity) = &client_cert {
+                    let mut traffic_clone = Traffic::clone(&traffic);
+                    traffic_clone.client_cert = Some(identity.label.clone());
+                    traffic = Arc::new(traffic_clone);
+                }
+                let route = match self.upstream_route(&proxy_config, &upstream_uri).await {
+                    Ok(route) => route,
+                    Err(err) => return self.internal_server_error(err, traffic, head_id).await,
+                };
+                debug!("need_proxy={}", route.is_some());
+                // 明文请求直接发给 HTTP 代理时需要携带代理认证
+                if let Some(authorization) = route
+                    .as_ref()
+                    .filter(|_| upstream_uri.starts_with("http://"))
+                    .and_then(|route| route.authorization())
+                {
+                    proxy_req
+                        .headers_mut()
+                        .insert(PROXY_AUTHORIZATION, authorization.clone());
                 }
+                let https = self.https_connector(&recorder, &upstream_uri, client_cert, route);
+                let https = match https {
+                    Ok(https) => https,
+                    Err(err) => return self.internal_server_error(err, traffic, head_id).await,
+                };
+
+                self.send_request(https, proxy_req, traffic, head_id, session_id, recorder)
+                    .await
             }
             Err(err) => {
                 error!("Failed to get proxy config: {}", err);
@@ -560,81 +646,305 @@ impl Server {
         }
     }
 
-    // 使用代理发送请求
-    async fn send_request_with_proxy<B>(
+    // 构造上游连接器，DNS 覆盖表通过自定义解析器生效，SNI 与 Host 保持不变
+    // 各层连接器共享同一个计时器，用于计算 HAR timings 并在连接建立时记录上游地址
+    // 命中客户端证书配置时向上游出示该证书
+    // 允许无效证书的主机校验失败时继续握手，错误通过响应扩展记录到流量中
+    fn https_connector(
+        &self,
+        recorder: &TimingRecorder,
+        upstream_uri: &str,
+        client_cert: Option<ClientIdentity>,
+        route: Option<UpstreamRoute>,
+    ) -> Result<UpstreamConnector> {
+        let resolver = OverrideResolver::new(get_dns_override_config(&self.app_handle).ok());
+        let mut http = HttpConnector::new_with_resolver(resolver.with_recorder(recorder.clone()));
+        http.enforce_http(false);
+        let tcp = TcpConnector::new(http, route, resolver).with_recorder(recorder.clone());
+        configure_key_log(&self.app_handle);
+        let trust = get_upstream_trust_config(&self.app_handle).unwrap_or_default();
+        let roots = root_store(trust.native_roots, &trust.ca_files);
+        let allow_invalid_cert = url::Url::parse(upstream_uri)
+            .ok()
+            .and_then(|url| url.host_str().map(|host| trust.allow_invalid_cert(host)))
+            .unwrap_or(false);
+        let cert_error = allow_invalid_cert.then(CertErrorSlot::default);
+        let tls_builder = match &cert_error {
+            Some(cert_error) => ClientConfig::builder()
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(RecordingVerifier::new(
+                    roots,
+                    Arc::clone(cert_error),
+                )?)),
+            None => ClientConfig::builder().with_root_certificates(roots),
+        };
+        let mut tls_config = match client_cert {
+            Some(identity) => tls_builder
+                .with_client_auth_cert(identity.chain, identity.key)
+                .with_context(|| format!("Invalid client certificate '{}'", identity.label))?,
+            None => tls_builder.with_no_client_auth(),
+        };
+        tls_config.key_log = KEY_LOG.clone();
+        let https = HttpsConnectorBuilder::new()
+            .with_tls_config(tls_config)
+            .https_or_http()
+            .enable_all_versions()
+            .wrap_connector(TimedConnector::new(
+                tcp,
+                recorder.clone(),
+                ConnectStage::Tcp,
+            ));
+        Ok(TimedConnector::new(
+            TlsInspector::new(https).with_cert_error(cert_error),
+            recorder.clone(),
+            ConnectStage::Connect,
+        ))
+    }
+
+    // 请求对应的外部代理路由，HTTPS 代理的证书按上游证书校验配置校验
+    async fn upstream_route(
         &self,
         proxy_config: &ExternalProxy,
-        https: HttpsConnector<HttpConnector>,
-        mut proxy_req: hyper::Request<B>,
+        url: &str,
+    ) -> Result<Option<UpstreamRoute>, String> {
+        let trust = get_upstream_trust_config(&self.app_handle).unwrap_or_default();
+        UpstreamRoute::resolve(proxy_config, url, &trust).await
+    }
+
+    // 读取上游主机对应的客户端证书，仅 HTTPS 请求需要
+    fn client_cert(&self, uri: &str) -> Result<Option<ClientIdentity>> {
+        let Ok(url) = url::Url::parse(uri) else {
+            return Ok(None);
+        };
+        if url.scheme() != "https" {
+            return Ok(None);
+        }
+        let Some(host) = url.host_str() else {
+            return Ok(None);
+        };
+        let item = check_need_client_cert(get_client_certs_config(&self.app_handle).ok(), host);
+        match item {
+            Some(item) => item.load().map(Some).map_err(|err| anyhow!(err)),
+            None => Ok(None),
+        }
+    }
+
+    // 读取当前请求生效的限速配置
+    fn throttle_profile(&self, uri: &str) -> Option<ThrottleProfile> {
+        let host = url::Url::parse(uri).ok()?.host_str()?.to_string();
+        let throttle = get_throttle_config(&self.app_handle).ok()?;
+        check_need_throttle(throttle, &host)
+    }
+
+    // 读取命中当前请求的 Rewrite 规则
+    fn rewrite_rules(&self, traffic: &Traffic) -> Vec<MatchedRewriteRule> {
+        match get_rewrite_config(&self.app_handle) {
+            Ok(rewrite) => check_need_rewrite(rewrite, &traffic.method, &traffic.uri),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    // 按 Rewrite 规则改写 body，非文本或解压失败时保持原样
+    async fn rewrite_body(
+        &self,
+        rules: &[MatchedRewriteRule],
+        phase: RewritePhase,
+        bytes: Bytes,
+        traffic: &mut Traffic,
+    ) -> Bytes {
+        let headers = match phase {
+            RewritePhase::Request => traffic.upstream_req_headers(),
+            RewritePhase::Response => traffic.res_headers.as_ref(),
+        };
+        let encoding = headers
+            .and_then(|headers| {
+                headers
+                    .items
+                    .iter()
+                    .find(|h| h.name.eq_ignore_ascii_case(CONTENT_ENCODING.as_str()))
+            })
+            .map(|h| h.value.trim().to_lowercase());
+
+        let decoded = match self
+            .handle_body(bytes.clone(), encoding.clone().unwrap_or_default())
+            .await
+        {
+            Ok(v) => v,
+            Err(err) => {
+                error!("Rewrite body decode error: {}", err);
+                return bytes;
+            }
+        };
+        let Ok(text) = std::str::from_utf8(&decoded) else {
+            return bytes;
+        };
+        let Some(new_text) = apply_rewrite_body(rules, phase, text, traffic) else {
+            return bytes;
+        };
+        let body = match self.repack_body(Bytes::from(new_text), encoding).await {
+            Ok(v) => v,
+            Err(err) => {
+                error!("Rewrite body encode error: {}", err);
+                return bytes;
+            }
+        };
+
+        let content_length = body.len().to_string();
+        match phase {
+            RewritePhase::Request => {
+                traffic.set_forwarded_req_header(CONTENT_LENGTH.as_str(), &content_length);
+            }
+            RewritePhase::Response => {
+                traffic.set_res_header(CONTENT_LENGTH.as_str(), &content_length);
+            }
+        }
+        body
+    }
+
+    // 读取命中当前请求并按概率触发的故障
+    fn faults(&self, traffic: &Traffic, fault_types: &[FaultType]) -> Vec<FaultItem> {
+        match get_fault_config(&self.app_handle) {
+            Ok(config) => check_need_fault(config, &traffic.method, &traffic.uri, fault_types),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    // 构造故障注入的错误响应
+    async fn fault_status_response(
+        &self,
+        fault: FaultItem,
         traffic: Arc<Traffic>,
         head_id: Option<u64>,
-        session_id: String,
-    ) -> Result<Response, hyper::Error>
-    where
-        B: Body + Send + Unpin + 'static,
-        B::Data: Send,
-        B::Error: Into<Box<dyn StdError + Send + Sync>>,
-    {
-        let uri: Uri = traffic.uri.parse().unwrap();
-        let proxy = {
-            let mutable_external_proxy_configuration = &proxy_config
-                .configurations
-                .entry
-                .iter()
-                .find(|entry| entry.string == proxy_config.proxy_type)
-                .unwrap()
-                .mutable_external_proxy_configuration;
-            debug!(
-                "mutable_external_proxy_configuration={:?}",
-                mutable_external_proxy_configuration
-            );
-            let host = &mutable_external_proxy_configuration.host;
-
-            let port = mutable_external_proxy_configuration.port;
+    ) -> Result<Response, hyper::Error> {
+        let bytes = Bytes::from(fault.body.clone());
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("text/plain; charset=utf-8"),
+        );
+        headers.insert(CONTENT_LENGTH, HeaderValue::from(bytes.len()));
 
-            let proxy_uri: Uri = format!("http://{}:{}", host, port).parse().unwrap();
-            let mut proxy = Proxy::new(Intercept::All, proxy_uri.clone());
+        let traffic = {
+            let mut traffic_clone = Traffic::clone(&traffic);
+            traffic_clone
+                .set_res_status(fault.status())
+                .set_res_headers(&headers);
+            traffic_clone.res_body_hex = Some(bytes_to_hex_structs(&bytes));
+            traffic_clone.faults.push(fault.describe());
+            Arc::new(traffic_clone)
+        };
 
-            if mutable_external_proxy_configuration.requires_authentication {
-                let username = &mutable_external_proxy_configuration.username;
-                let encrypted_password = &mutable_external_proxy_configuration.encrypted_password;
+        let body = Full::new(bytes).map_err(|never| match never {}).boxed();
+        self.continue_response(body, traffic, String::new(), head_id)
+            .await
+    }
 
-                let decrypted_password = match CRYPTO_SERVICE.decrypt(encrypted_password) {
-                    Ok(decrypted_text) => decrypted_text,
-                    Err(e) => {
-                        return self.internal_server_error(e, traffic, head_id).await;
+    // 构造 MapLocal 伪造响应
+    async fn map_local_response(
+        &self,
+        item: MapLocalItem,
+        traffic: Arc<Traffic>,
+        head_id: Option<u64>,
+    ) -> Result<Response, hyper::Error> {
+        let mut status = item.status();
+        let mut headers = HeaderMap::new();
+
+        // 添加头部
+        if !item.header_local.is_empty() {
+            // debug!("读取header_local:{}", item.header_local);
+            if let Ok(header_data) = File::open(&item.header_local) {
+                let reader = BufReader::new(header_data);
+                if let Ok(serde_json::Value::Object(map)) =
+                    serde_json::from_reader::<_, serde_json::Value>(reader)
+                {
+                    for (key, value) in map.iter() {
+                        if let (Ok(header_name), Ok(header_value)) = (
+                            HeaderName::from_bytes(key.as_bytes()),
+                            HeaderValue::from_str(value.to_string().trim_matches('"')),
+                        ) {
+                            headers.insert(header_name, header_value);
+                        }
                     }
-                };
+                }
+            }
+        }
 
-                proxy.set_authorization(Authorization::basic(username, &decrypted_password));
+        // 构建 body，目录映射优先于单个文件
+        let body_path = match item.resolve_dir_file(&traffic.uri) {
+            Some(path) => {
+                if !headers.contains_key(CONTENT_TYPE) {
+                    let mime = path
+                        .extension()
+                        .and_then(|ext| ext.to_str())
+                        .and_then(from_ext_name)
+                        .unwrap_or("application/octet-stream");
+                    if let Ok(value) = HeaderValue::from_str(mime) {
+                        headers.insert(CONTENT_TYPE, value);
+                    }
+                }
+                Some(path)
             }
-            proxy
+            None if !item.body_local.is_empty() => Some(PathBuf::from(&item.body_local)),
+            None => None,
+        };
+        let mut bytes = match &body_path {
+            Some(path) => match fs::read(path) {
+                Ok(data) => Bytes::from(data),
+                Err(err) => {
+                    debug!("Failed to read map local file {}: {}", path.display(), err);
+                    if !item.dir_local.is_empty() && item.status_code.is_none() {
+                        status = StatusCode::NOT_FOUND;
+                    }
+                    Bytes::new()
+                }
+            },
+            None => Bytes::new(),
         };
 
-        let connector = ProxyConnector::from_proxy(https, proxy).unwrap();
-        let client = Client::builder(TokioExecutor::new())
-            .pool_idle_timeout(Duration::from_secs(30))
-            .build(connector.clone());
-        // 对 HTTP 请求添加代理头
-        let is_https = uri.scheme_str() == Some("https");
-        if !is_https {
-            if let Some(headers) = connector.http_headers(&uri) {
-                proxy_req.headers_mut().extend(headers.clone().into_iter());
+        if item.template {
+            if let Ok(text) = std::str::from_utf8(&bytes) {
+                bytes = Bytes::from(render_template(text, &traffic));
             }
         }
 
-        self.send_and_process_request(client, proxy_req, traffic, head_id, session_id)
-            .await
+        // 本地文件均为未压缩内容
+        headers.remove(CONTENT_ENCODING);
+        if let Ok(value) = HeaderValue::from_str(&bytes.len().to_string()) {
+            headers.insert(CONTENT_LENGTH, value);
+        }
+
+        let traffic = {
+            let mut traffic_clone = Traffic::clone(&traffic);
+            traffic_clone
+                .set_res_status(status)
+                .set_res_headers(&headers);
+            traffic_clone.res_body_hex = Some(bytes_to_hex_structs(&bytes));
+            Arc::new(traffic_clone)
+        };
+
+        let body = Full::new(bytes).map_err(|never| match never {}).boxed();
+        let mut res = self
+            .continue_response(body, traffic, String::new(), head_id)
+            .await?;
+
+        if !item.reason_phrase.is_empty() {
+            if let Ok(reason) = ReasonPhrase::try_from(item.reason_phrase.into_bytes()) {
+                res.extensions_mut().insert(reason);
+            }
+        }
+        // debug!("返回伪造响应");
+        Ok(res)
     }
 
-    // 直接发送请求（不使用代理）
-    async fn send_request_direct<B>(
+    // 发送请求，外部代理在连接器中处理
+    async fn send_request<B>(
         &self,
-        https: HttpsConnector<HttpConnector>,
+        https: UpstreamConnector,
         proxy_req: hyper::Request<B>,
         traffic: Arc<Traffic>,
         head_id: Option<u64>,
         session_id: String,
+        recorder: TimingRecorder,
     ) -> Result<Response, hyper::Error>
     where
         B: Body + Send + Unpin + 'static,
@@ -645,7 +955,7 @@ impl Server {
             .pool_idle_timeout(Duration::from_secs(30))
             .build(https);
 
-        self.send_and_process_request(client, proxy_req, traffic, head_id, session_id)
+        self.send_and_process_request(client, proxy_req, traffic, head_id, session_id, recorder)
             .await
     }
 
@@ -654,9 +964,10 @@ impl Server {
         &self,
         client: Client<C, B>,
         req: hyper::Request<B>,
-        traffic: Arc<Traffic>,
+        mut traffic: Arc<Traffic>,
         head_id: Option<u64>,
         session_id: String,
+        recorder: TimingRecorder,
     ) -> Result<Response, hyper::Error>
     where
         C: Connect + Clone + Send + Sync + 'static,
@@ -664,7 +975,32 @@ impl Server {
         B::Data: Send,
         B::Error: Into<Box<dyn StdError + Send + Sync>>,
     {
-        let proxy_res = client.request(req).await;
+        let request_start = Instant::now();
+        let request = client.request(req);
+        tokio::pin!(request);
+        // 连接建立后立即推送带上游地址的 traffic head，不必等到收到响应
+        let proxy_res = tokio::select! {
+            res = &mut request => res,
+            remote_addr = recorder.connected() => {
+                let mut traffic_clone = Traffic::clone(&traffic);
+                traffic_clone.server_addr = remote_addr.map(|addr| addr.to_string());
+                traffic = Arc::new(traffic_clone);
+                if let Some(hd_id) = head_id {
+                    let _ = self
+                        .state
+                        .create_traffic_head(&traffic, hd_id, session_id.clone())
+                        .await;
+                }
+                request.await
+            }
+        };
+        let response_at = Instant::now();
+        {
+            let mut traffic_clone = Traffic::clone(&traffic);
+            traffic_clone.timings = Some(recorder.timings(request_start, response_at));
+            traffic_clone.response_at = Some(response_at);
+            traffic = Arc::new(traffic_clone);
+        }
 
         if let Some(hd_id) = head_id {
             // 创建一个可变的 Traffic 副本
@@ -683,7 +1019,9 @@ impl Server {
             Ok(v) => v,
             Err(err) => {
                 error!("Request Error: {:#?}", err);
-                return self.internal_server_error(err, traffic, head_id).await;
+                return self
+                    .internal_server_error(error_chain(&err), traffic, head_id)
+                    .await;
             }
         };
 
@@ -912,7 +1250,69 @@ impl Server {
         Ok((body_bytes, body_content, content_encoding))
     }
 
+    // 先检查 BlockList/AllowList，返回 Err 时 hyper 会直接断开连接
+    async fn serve(
+        self: Arc<Self>,
+        req: Request,
+    ) -> Result<Response, Box<dyn StdError + Send + Sync>> {
+        // CONNECT 只有 authority，按 https 地址匹配，在升级为隧道前拦截
+        let target = if req.method() == Method::CONNECT {
+            req.uri()
+                .authority()
+                .map(|authority| format!("https://{authority}"))
+        } else {
+            Some(req.uri().to_string()).filter(|uri| !uri.starts_with('/'))
+        };
+        if let Some(target) = target {
+            let uri = req.uri().to_string();
+            let block = check_need_block(
+                get_block_list_config(&self.app_handle).ok(),
+                get_allow_list_config(&self.app_handle).ok(),
+                req.method().as_str(),
+                &target,
+            );
+            if let Some(block) = block {
+                debug!("blocked: {} {}", req.method(), target);
+                let current_session = self.state.get_current_session();
+                let mut traffic = Traffic::new(&uri, req.method().as_str(), &current_session);
+                set_connection_info(&mut traffic, &req);
+                traffic.set_start_time();
+                traffic.check_match();
+                traffic.set_req_headers(req.headers());
+                traffic.end_time = Some(OffsetDateTime::now_utc());
+                traffic.set_transaction_state(TransactionState::Blocked);
+
+                let mut res = Response::default();
+                if block.action == BlockAction::Response {
+                    *res.status_mut() =
+                        StatusCode::from_u16(block.status_code).unwrap_or(StatusCode::FORBIDDEN);
+                    set_res_body(&mut res, &block.body);
+                    traffic
+                        .set_res_status(res.status())
+                        .set_res_headers(res.headers());
+                }
+                if self.state.is_monitor_traffic().await {
+                    let _ = self
+                        .state
+                        .add_traffic(Arc::new(traffic), &current_session)
+                        .await;
+                }
+
+                return match block.action {
+                    BlockAction::Response => Ok(res),
+                    BlockAction::DropConnection => Err(format!("Blocked request: {uri}").into()),
+                };
+            }
+        }
+
+        self.handle(req).await.map_err(Into::into)
+    }
+
     async fn handle(self: Arc<Self>, req: Request) -> Result<Response, hyper::Error> {
+        if let Some(res) = self.cert_page(&req) {
+            return Ok(res);
+        }
+
         let req_uri = req.uri().to_string();
         let headers = req.headers().clone();
         let method = req.method().clone();
@@ -934,6 +1334,7 @@ impl Server {
         traffic_obj.set_start_time();
         traffic_obj.check_match();
         traffic_obj.set_req_headers(req.headers());
+        set_connection_info(&mut traffic_obj, &req);
 
         // 设置完成后再包装到 Arc 中
         let mut traffic = Arc::new(traffic_obj);
@@ -1206,6 +1607,27 @@ impl Server {
         }
     }
 
+    // 证书安装页面，设备直接访问代理地址时也返回该页面
+    fn cert_page(&self, req: &Request) -> Option<Response> {
+        if req.method() == Method::CONNECT {
+            return None;
+        }
+        let is_cert_req = match req.uri().host() {
+            Some(host) => is_cert_host(host),
+            None => req.method() == Method::GET && req.uri().to_string().starts_with('/'),
+        };
+        if !is_cert_req {
+            return None;
+        }
+
+        let local_addr = req
+            .extensions()
+            .get::<ConnectionInfo>()
+            .and_then(|info| info.local_addr);
+        let res = cert_page_response(&self.ca, req.uri().path(), local_addr);
+        Some(res.map(|body| Full::new(body).map_err(|err| anyhow!("{err}")).boxed()))
+    }
+
     fn handle_connect(
         self: Arc<Self>,
         mut req: Request,
@@ -1221,66 +1643,64 @@ impl Server {
         };
         let server = self.clone();
         let mut traffic_clone = Traffic::clone(&traffic);
+        let connection_info = req.extensions().get::<ConnectionInfo>().cloned();
 
         let fut = async move {
             match hyper::upgrade::on(&mut req).await {
-                Ok(upgraded) => {
+                Ok(upgraded) => 'connect: {
                     let mut upgraded = TokioIo::new(upgraded);
 
                     let mut buffer = [0; 4];
                     let bytes_read = match upgraded.read_exact(&mut buffer).await {
                         Ok(bytes_read) => bytes_read,
                         Err(err) => {
-                            traffic_clone.add_error(format!(
-                                "Failed to read from upgraded connection: {err}"
-                            ));
-
-                            return;
+                            traffic_clone
+                                .fail(format!("Failed to read from upgraded connection: {err}"));
+                            break 'connect;
                         }
                     };
 
-                    let mut upgraded = Rewind::new_buffered(
+                    let upgraded = Rewind::new_buffered(
                         upgraded,
                         bytes::Bytes::copy_from_slice(buffer[..bytes_read].as_ref()),
                     );
                     if buffer == *b"GET " {
                         if let Err(err) = self
-                            .serve_connect_stream(upgraded, Scheme::HTTP, authority)
+                            .serve_connect_stream(
+                                upgraded,
+                                Scheme::HTTP,
+                                authority,
+                                connection_info,
+                            )
                             .await
                         {
                             traffic_clone.add_error(format!(
                                 "Failed to read from upgraded connection: {err}"
                             ));
                         }
-                    } else if buffer[..2] == *b"\x16\x03" {
-                        let server_config = match self.ca.gen_server_config(&authority).await {
-                            Ok(server_config) => server_config,
-                            Err(err) => {
-                                error!("Failed to build server config");
-
-                                traffic_clone
-                                    .add_error(format!("Failed to build server config: {err}"));
-                                // 将修改后的 Traffic 包装回 Arc
-
-                                return;
-                            }
+                    } else if buffer[..2] == *b"\x16\x03" && self.ssl_proxying(&authority) {
+                        configure_key_log(&self.app_handle);
+                        let accepted = accept_client_tls(
+                            upgraded,
+                            &mut traffic_clone,
+                            |client_tls| self.server_config(&authority, client_tls.sni),
+                            |err, elapsed| self.record_handshake_failure(&authority, err, elapsed),
+                        )
+                        .await;
+                        // 握手失败时 traffic 已标记为失败，同样记录到会话中
+                        let Some((stream, client_tls)) = accepted else {
+                            break 'connect;
                         };
-                        // println!("server_config:{:#?}", server_config);
-                        let stream = match TlsAcceptor::from(server_config).accept(upgraded).await {
-                            Ok(stream) => stream,
-                            Err(err) => {
-                                error!("Failed to establish TLS Connection");
-
-                                traffic_clone.add_error(format!(
-                                    "Failed to establish TLS Connection: {err}"
-                                ));
+                        self.handshake_failures
+                            .invalidate(&pinned_key(authority.host(), authority_port(&authority)));
 
-                                return;
-                            }
-                        };
+                        let connection_info = connection_info.map(|info| ConnectionInfo {
+                            tls: Some(Arc::new(client_tls)),
+                            ..info
+                        });
 
                         if let Err(err) = self
-                            .serve_connect_stream(stream, Scheme::HTTPS, authority)
+                            .serve_connect_stream(stream, Scheme::HTTPS, authority, connection_info)
                             .await
                         {
                             if !err
@@ -1291,31 +1711,13 @@ impl Server {
                             }
                         }
                     } else {
-                        {
+                        if buffer[..2] != *b"\x16\x03" {
                             traffic_clone.add_error(format!(
                                 "Unknown protocol, read '{:02X?}' from upgraded connection",
                                 &buffer[..bytes_read]
                             ));
                         }
-
-                        let mut server = match TcpStream::connect(authority.as_str()).await {
-                            Ok(server) => server,
-                            Err(err) => {
-                                traffic_clone
-                                    .add_error(format! {"Failed to connect to {authority}: {err}"});
-
-                                return;
-                            }
-                        };
-
-                        if let Err(err) =
-                            tokio::io::copy_bidirectional(&mut upgraded, &mut server).await
-                        {
-                            traffic_clone.add_error(format!(
-                                "Failed to tunnel unknown protocol to {}: {}",
-                                authority, err
-                            ));
-                        }
+                        self.tunnel(upgraded, &authority, &mut traffic_clone).await;
                     }
                 }
                 Err(err) => {
@@ -1338,11 +1740,210 @@ impl Server {
         Ok(Response::default())
     }
 
+    // 为 CONNECT 目标生成伪造证书的 TLS 配置，开启镜像时失败回退到普通证书
+    async fn server_config(
+        &self,
+        authority: &Authority,
+        sni: Option<String>,
+    ) -> Result<Arc<ServerConfig>> {
+        let certificate = get_certificate_config(&self.app_handle).unwrap_or_default();
+        if certificate.mirror_upstream {
+            let mirrored = self
+                .ca
+                .gen_mirrored_server_config(authority, || {
+                    self.fetch_upstream_cert(authority, sni.as_deref())
+                })
+                .await;
+            match mirrored {
+                Ok(server_config) => return Ok(server_config),
+                Err(err) => error!("Failed to mirror certificate of {authority}: {err}"),
+            }
+        }
+        self.ca
+            .gen_server_config(authority, certificate.wildcard_certs)
+            .await
+    }
+
+    // 检查 CONNECT 目标是否在 SSL Proxying 范围内，不在范围内以及识别为证书固定的主机直接透传
+    fn ssl_proxying(&self, authority: &Authority) -> bool {
+        let port = authority_port(authority);
+        if get_ssl_pinning_config(&self.app_handle)
+            .is_ok_and(|pinning| pinning.is_pinned(authority.host(), port))
+        {
+            return false;
+        }
+        check_need_ssl_proxying(
+            get_ssl_proxying_config(&self.app_handle).ok(),
+            authority.host(),
+            port,
+        )
+    }
+
+    // 记录客户端握手失败，连续失败达到阈值后将主机加入透传列表并通知前端
+    fn record_handshake_failure(
+        &self,
+        authority: &Authority,
+        err: &std::io::Error,
+        elapsed: Duration,
+    ) {
+        let port = authority_port(authority);
+        let key = pinned_key(authority.host(), port);
+        // 其他原因的失败打断连续计数
+        let Some(reason) = pinning_failure_reason(err, elapsed) else {
+            self.handshake_failures.invalidate(&key);
+            return;
+        };
+        let mut pinning = get_ssl_pinning_config(&self.app_handle).unwrap_or_default();
+        if !pinning.tool_enabled {
+            return;
+        }
+
+        let count = self
+            .handshake_failures
+            .entry(key.clone())
+            .and_upsert_with(|entry| entry.map_or(1, |entry| entry.into_value() + 1))
+            .into_value();
+        if count < pinning.threshold.max(1) {
+            return;
+        }
+        self.handshake_failures.invalidate(&key);
+
+        let pinned_host = PinnedHost {
+            host: authority.host().to_string(),
+            port,
+            reason,
+            learned_at: OffsetDateTime::now_utc()
+                .format(&time::format_description::well_known::Rfc3339)
+                .unwrap_or_default(),
+        };
+        pinning
+            .pinned_hosts
+            .insert(key.clone(), pinned_host.clone());
+        if let Err(err) = save_ssl_pinning_config(&self.app_handle, &pinning) {
+            error!("Failed to save pinned host {}: {}", key, err);
+        }
+        info!("{} looks certificate pinned, switching to passthrough", key);
+
+        let send_data = SendData {
+            event_name: "ssl-pinning-detected".to_string(),
+            payload: Payload {
+                status: Status::Success,
+                message: format!("{} 疑似启用了证书固定，已切换为透传", key),
+                data: Some(pinned_host),
+            },
+        };
+        send_to_frontend(send_data, &self.app_handle);
+    }
+
+    // 将客户端连接原样转发到上游，记录双向字节数和隧道持续时间
+    async fn tunnel<I>(&self, upgraded: I, authority: &Authority, traffic: &mut Traffic)
+    where
+        I: AsyncRead + AsyncWrite + Unpin,
+    {
+        let throttle = self.throttle_profile(&format!("https://{authority}"));
+        if let Some(profile) = &throttle {
+            traffic.throttle = Some(profile.display_name());
+            if profile.latency_ms > 0 {
+                tokio::time::sleep(Duration::from_millis(profile.latency_ms)).await;
+            }
+        }
+        let mut upgraded = ThrottledStream::new(
+            upgraded,
+            throttle
+                .as_ref()
+                .and_then(|profile| RateLimiter::new(profile.upload_kbps)),
+            throttle
+                .as_ref()
+                .and_then(|profile| RateLimiter::new(profile.download_kbps)),
+        );
+
+        let mut server = match self.connect_upstream(authority).await {
+            Ok(server) => server,
+            Err(err) => {
+                traffic.fail(format!("Failed to connect to {authority}: {err}"));
+                return;
+            }
+        };
+
+        if let Ok(addr) = server.peer_addr() {
+            traffic.server_addr = Some(addr.to_string());
+        }
+        traffic.proxy_route = server.route().map(str::to_string);
+        let result = tokio::io::copy_bidirectional(&mut upgraded, &mut server).await;
+        // 按客户端一侧统计的字节数记录，对端重置连接时同样保留已转发的数据量
+        traffic.bytes_sent = Some(upgraded.bytes_read());
+        traffic.bytes_received = Some(upgraded.bytes_written());
+        traffic.res_body_size = Some(upgraded.bytes_written());
+        match result {
+            Ok(_) => {
+                traffic.set_transaction_state(TransactionState::Completed);
+            }
+            Err(err) => {
+                traffic.add_error(format!("Failed to tunnel to {}: {}", authority, err));
+                traffic.set_transaction_state(TransactionState::Failed);
+            }
+        }
+        traffic.end_time = Some(OffsetDateTime::now_utc());
+    }
+
+    // 连接 CONNECT 目标，DNS 覆盖表同样生效
+    async fn connect_upstream(&self, authority: &Authority) -> std::io::Result<UpstreamStream> {
+        let resolver = OverrideResolver::new(get_dns_override_config(&self.app_handle).ok());
+        let port = authority_port(authority);
+        match self.tunnel_route(authority).await? {
+            Some(route) => route.connect(authority.host(), port, &resolver).await,
+            None => connect_direct(authority.host(), port, &resolver).await,
+        }
+    }
+
+    // CONNECT 透传隧道使用的外部代理路由，按 HTTPS 请求选择代理条目
+    async fn tunnel_route(&self, authority: &Authority) -> std::io::Result<Option<UpstreamRoute>> {
+        let Ok(proxy_config) = get_proxy_config(&self.app_handle) else {
+            return Ok(None);
+        };
+        self.upstream_route(&proxy_config, &format!("https://{}", authority))
+            .await
+            .map_err(std::io::Error::other)
+    }
+
+    // 获取上游的叶子证书，用于生成镜像证书，不校验证书有效性
+    async fn fetch_upstream_cert(
+        &self,
+        authority: &Authority,
+        sni: Option<&str>,
+    ) -> Result<CertificateDer<'static>> {
+        let fetch = async {
+            let stream = self.connect_upstream(authority).await?;
+            let host = authority
+                .host()
+                .trim_start_matches('[')
+                .trim_end_matches(']');
+            let server_name = ServerName::try_from(sni.unwrap_or(host).to_string())?;
+            let config = ClientConfig::builder()
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(AcceptAnyCert::new()))
+                .with_no_client_auth();
+            let tls = TlsConnector::from(Arc::new(config))
+                .connect(server_name, stream)
+                .await?;
+            tls.get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|certs| certs.first())
+                .map(|cert| cert.clone().into_owned())
+                .ok_or_else(|| anyhow!("Upstream sent no certificate"))
+        };
+        tokio::time::timeout(Duration::from_secs(10), fetch)
+            .await
+            .map_err(|_| anyhow!("Timed out fetching certificate from {authority}"))?
+    }
+
     async fn serve_connect_stream<I>(
         self: Arc<Self>,
         stream: I,
         scheme: Scheme,
         authority: Authority,
+        connection_info: Option<ConnectionInfo>,
     ) -> Result<(), Box<dyn std::error::Error + Sync + Send>>
     where
         I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
@@ -1361,8 +1962,11 @@ impl Server {
 
                 req = Request::from_parts(parts, body);
             };
+            if let Some(connection_info) = &connection_info {
+                req.extensions_mut().insert(connection_info.clone());
+            }
 
-            self.clone().handle(req)
+            self.clone().serve(req)
         });
 
         hyper_util::server::conn::auto::Builder::new(TokioExecutor::new())
@@ -1523,6 +2127,10 @@ impl Server {
             traffic.res_body_file.clone(),
             Some((head_id, self.state.clone())),
             Some(res.headers().clone()),
+        )
+        .with_throttle(
+            self.throttle_profile(&traffic.uri)
+                .and_then(|profile| RateLimiter::new(profile.download_kbps)),
         );
 
         *res.body_mut() = BoxBody::new(res_body);
@@ -1557,7 +2165,7 @@ impl Server {
         session_id: String,
     ) -> Result<Response, hyper::Error> {
         // let before = time::Instant::now();
-        let proxy_res = {
+        let mut proxy_res = {
             let (parts, body) = proxy_res.into_parts();
             Response::from_parts(parts, body.map_err(|_| anyhow!("Invalid response")).boxed())
         };
@@ -1567,6 +2175,20 @@ impl Server {
         let proxy_res_headers = proxy_res.headers().clone();
         {
             let mut traffic_clone = Traffic::clone(&traffic);
+            // 记录实际连接的上游地址
+            if let Some(info) = proxy_res.extensions().get::<HttpInfo>() {
+                traffic_clone.server_addr = Some(info.remote_addr().to_string());
+            }
+            // 上游 TLS 握手信息，经过 HTTP 代理的 HTTPS 请求由代理连接器完成握手，无法获取
+            if let Some(tls) = proxy_res.extensions().get::<TlsSession>() {
+                traffic_clone.server_tls = Some(tls.clone());
+            }
+            if let Some(ProxyRoute(route)) = proxy_res.extensions().get() {
+                traffic_clone.proxy_route = Some(route.clone());
+            }
+            if let Some(UpstreamCertError(err)) = proxy_res.extensions().get() {
+                traffic_clone.upstream_cert_error = Some(err.clone());
+            }
             traffic_clone
                 .set_res_status(proxy_res_status)
                 .set_http_version(&proxy_res_version)
@@ -1581,6 +2203,32 @@ impl Server {
             traffic = Arc::new(traffic_clone);
         }
 
+        // 应用 Rewrite 规则，响应头和状态码由 continue_response 从 traffic 中读取
+        let rewrite_rules = self.rewrite_rules(&traffic);
+        if !rewrite_rules.is_empty() {
+            let mut traffic_clone = Traffic::clone(&traffic);
+            apply_rewrite_head(&rewrite_rules, RewritePhase::Response, &mut traffic_clone);
+            if has_body_rules(&rewrite_rules, RewritePhase::Response) {
+                let (parts, body) = proxy_res.into_parts();
+                let raw = match self.get_body_bytes(Some(body)).await {
+                    Ok(v) => v,
+                    Err(e) => return self.internal_server_error(e, traffic, head_id).await,
+                };
+                let body = self
+                    .rewrite_body(
+                        &rewrite_rules,
+                        RewritePhase::Response,
+                        raw,
+                        &mut traffic_clone,
+                    )
+                    .await;
+                let body = Full::new(body)
+                    .map_err(|never: Infallible| -> anyhow::Error { match never {} });
+                proxy_res = Response::from_parts(parts, BoxBody::new(body));
+            }
+            traffic = Arc::new(traffic_clone);
+        }
+
         let mut encoding: String = String::new();
         for (key, value) in proxy_res_headers.iter() {
             if key == CONTENT_ENCODING {
@@ -1588,6 +2236,64 @@ impl Server {
             }
         }
 
+        // 故障注入：延迟响应、发送部分 body 后断开或截断 body，同时命中时 Reset 优先于 Truncate
+        let mut faults = self.faults(
+            &traffic,
+            &[FaultType::Delay, FaultType::Reset, FaultType::Truncate],
+        );
+        if faults.iter().any(|f| f.fault_type == FaultType::Reset) {
+            faults.retain(|f| f.fault_type != FaultType::Truncate);
+        }
+        if !faults.is_empty() {
+            let mut traffic_clone = Traffic::clone(&traffic);
+            traffic_clone
+                .faults
+                .extend(faults.iter().map(|fault| fault.describe()));
+            traffic = Arc::new(traffic_clone);
+        }
+        let delay_ms: u64 = faults
+            .iter()
+            .filter(|f| f.fault_type == FaultType::Delay)
+            .map(|f| f.delay_ms)
+            .sum();
+        if delay_ms > 0 {
+            tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+        }
+        if let Some(fault) = faults.iter().find(|f| f.fault_type != FaultType::Delay) {
+            let bytes = match self.get_body_bytes(Some(proxy_res.into_body())).await {
+                Ok(v) => v,
+                Err(e) => return self.internal_server_error(e, traffic, head_id).await,
+            };
+            // 按解码后的内容截断，部分压缩流无法解码，去掉 Content-Encoding 以明文发送
+            let mut traffic_clone = Traffic::clone(&traffic);
+            let bytes = if encoding.is_empty() {
+                bytes
+            } else {
+                match self.handle_body(bytes, encoding.clone()).await {
+                    Ok(v) => v,
+                    Err(e) => return self.internal_server_error(e, traffic, head_id).await,
+                }
+            };
+            if let Some(headers) = traffic_clone.res_headers.as_mut() {
+                headers.remove(CONTENT_ENCODING.as_str());
+            }
+            let sent = bytes.slice(..bytes.len().min(fault.after_bytes as usize));
+            traffic_clone.res_body_hex = Some(bytes_to_hex_structs(&sent));
+
+            // Content-Length 均为完整 body 的长度，客户端能观察到响应不完整：
+            // Reset 在 body 出错时由 hyper 直接断开连接，Truncate 提前结束 body 后关闭连接
+            traffic_clone.set_res_header(CONTENT_LENGTH.as_str(), &bytes.len().to_string());
+            let mut frames = vec![Ok(Frame::data(sent))];
+            if fault.fault_type == FaultType::Reset {
+                frames.push(Err(anyhow!("Connection reset by fault injection")));
+            }
+            let body: BoxBody<Bytes, anyhow::Error> =
+                StreamBody::new(futures_util::stream::iter(frames)).boxed();
+            return self
+                .continue_response(body, Arc::new(traffic_clone), String::new(), head_id)
+                .await;
+        }
+
         if !self.state.is_monitor_traffic().await {
             match head_id {
                 Some(_) => {}
@@ -1827,6 +2533,79 @@ impl Server {
     }
 }
 
+fn authority_port(authority: &Authority) -> u16 {
+    authority.port_u16().unwrap_or(443)
+}
+
+/// 与客户端完成 TLS 握手，`server_config` 根据 ClientHello 生成伪造证书配置
+///
+/// 任一步骤失败时把错误记录到 traffic 并标记为失败，已解析的 SNI 和 ALPN 仍会保留；
+/// 握手本身失败时以错误和发送 ServerHello 后的耗时调用 `on_handshake_error`，用于识别证书固定
+async fn accept_client_tls<IO, F, Fut>(
+    io: IO,
+    traffic: &mut Traffic,
+    server_config: F,
+    on_handshake_error: impl FnOnce(&std::io::Error, Duration),
+) -> Option<(ServerTlsStream<IO>, TlsSession)>
+where
+    IO: AsyncRead + AsyncWrite + Unpin,
+    F: FnOnce(TlsSession) -> Fut,
+    Fut: Future<Output = Result<Arc<ServerConfig>>>,
+{
+    let start = match LazyConfigAcceptor::new(Acceptor::default(), io).await {
+        Ok(start) => start,
+        Err(err) => {
+            error!("Failed to read ClientHello");
+            traffic.fail(format!("Failed to read ClientHello: {err}"));
+            return None;
+        }
+    };
+    let mut client_tls = TlsSession::from_client_hello(&start.client_hello());
+    traffic.client_tls = Some(client_tls.clone());
+
+    let server_config = match server_config(client_tls.clone()).await {
+        Ok(server_config) => server_config,
+        Err(err) => {
+            error!("Failed to build server config");
+            traffic.fail(format!("Failed to build server config: {err}"));
+            return None;
+        }
+    };
+    let handshake_start = Instant::now();
+    let stream = match start.into_stream(server_config).await {
+        Ok(stream) => stream,
+        Err(err) => {
+            error!("Failed to establish TLS Connection");
+            on_handshake_error(&err, handshake_start.elapsed());
+            traffic.fail(format!("Failed to establish TLS Connection: {err}"));
+            return None;
+        }
+    };
+    client_tls.set_negotiated(stream.get_ref().1);
+    traffic.client_tls = Some(client_tls.clone());
+    Some((stream, client_tls))
+}
+
+fn set_connection_info(traffic: &mut Traffic, req: &Request) {
+    if let Some(info) = req.extensions().get::<ConnectionInfo>() {
+        traffic.client_addr = Some(info.client_addr.to_string());
+        traffic.connection_id = Some(info.id);
+        traffic.client_tls = info.tls.as_deref().cloned();
+    }
+}
+
+// 拼接错误及其 source，避免只显示 "client error (Connect)" 这类外层信息
+fn error_chain(err: &dyn StdError) -> String {
+    let mut message = err.to_string();
+    let mut source = err.source();
+    while let Some(err) = source {
+        message.push_str(": ");
+        message.push_str(&err.to_string());
+        source = err.source();
+    }
+    message
+}
+
 fn set_res_body<T: std::fmt::Display>(res: &mut Response, body: T) {
     let body = Bytes::from(body.to_string());
     if let Ok(header_value) = HeaderValue::from_str(&body.len().to_string()) {
@@ -1843,6 +2622,10 @@ pin_project! {
         file_path: Option<String>,
         traffic_done: Option<(Option<u64>, Arc<State>)>,
         raw_size: u64,   headers: Option<HeaderMap>,
+        throttle: Option<RateLimiter>,
+        delay: Option<Pin<Box<Sleep>>>,
+        pending: Option<Bytes>,
+        timing: Option<TimingRecorder>,
     }
      impl<B> PinnedDrop for BodyWrapper<B>
       {
@@ -1901,13 +2684,49 @@ impl<B> BodyWrapper<B> {
             traffic_done,
             raw_size: 0,
             headers,
+            throttle: None,
+            delay: None,
+            pending: None,
+            timing: None,
         }
     }
+
+    // 请求 body 发送完毕时记录时间
+    pub(crate) fn with_timing(mut self, timing: TimingRecorder) -> Self {
+        self.timing = Some(timing);
+        self
+    }
+
+    // 按限速配置分块发送 body
+    pub(crate) fn with_throttle(mut self, throttle: Option<RateLimiter>) -> Self {
+        self.throttle = throttle;
+        self
+    }
+}
+
+// 限速时每次只发送一块数据，剩余部分留到下次发送
+fn throttle_data(
+    throttle: &mut Option<RateLimiter>,
+    delay: &mut Option<Pin<Box<Sleep>>>,
+    pending: &mut Option<Bytes>,
+    mut data: Bytes,
+) -> Bytes {
+    let Some(limiter) = throttle.as_mut() else {
+        return data;
+    };
+    if data.len() > limiter.chunk_size() {
+        *pending = Some(data.split_off(limiter.chunk_size()));
+    }
+    let wait = limiter.consume(data.len());
+    if !wait.is_zero() {
+        *delay = Some(Box::pin(tokio::time::sleep(wait)));
+    }
+    data
 }
 
 impl<B> Body for BodyWrapper<B>
 where
-    B: Body<Data = Bytes> + Send + Sync + 'static,
+    B: Body<Data = Bytes> + Send + 'static,
 {
     type Data = B::Data;
     type Error = B::Error;
@@ -1918,6 +2737,15 @@ where
     ) -> Poll<Option<Result<hyper::body::Frame<Self::Data>, Self::Error>>> {
         let mut this = self.project();
 
+        if let Some(delay) = this.delay.as_mut() {
+            ready!(delay.as_mut().poll(cx));
+            *this.delay = None;
+        }
+        if let Some(data) = this.pending.take() {
+            let data = throttle_data(this.throttle, this.delay, this.pending, data);
+            return Poll::Ready(Some(Ok(Frame::data(data))));
+        }
+
         match Pin::new(&mut this.inner).poll_frame(cx) {
             Poll::Ready(Some(Ok(frame))) => match frame.into_data() {
                 Ok(data) => {
@@ -1957,12 +2785,18 @@ where
                         }
                     }
                     *this.raw_size += data.len() as u64;
+                    let data = throttle_data(this.throttle, this.delay, this.pending, data);
                     Poll::Ready(Some(Ok(Frame::data(data))))
                 }
                 Err(e) => Poll::Ready(Some(Ok(e))),
             },
             Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(e))),
-            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Ready(None) => {
+                if let Some(timing) = this.timing.take() {
+                    timing.mark_sent();
+                }
+                Poll::Ready(None)
+            }
             Poll::Pending => Poll::Pending,
         }
     }
@@ -1983,3 +2817,65 @@ pub enum PrintMode {
     #[default]
     Markdown,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_rustls::rustls::{pki_types::PrivateKeyDer, RootCertStore};
+
+    fn self_signed_config() -> Arc<ServerConfig> {
+        let cert = rcgen::generate_simple_self_signed(vec!["example.com".to_string()]).unwrap();
+        let key = PrivateKeyDer::Pkcs8(cert.key_pair.serialize_der().into());
+        Arc::new(
+            ServerConfig::builder()
+                .with_no_client_auth()
+                .with_single_cert(vec![cert.cert.der().clone()], key)
+                .unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_rejected_handshake_is_recorded() {
+        let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+        // 客户端不信任伪造的证书，握手时发送告警
+        let client = tokio::spawn(async move {
+            let config = ClientConfig::builder()
+                .with_root_certificates(RootCertStore::empty())
+                .with_no_client_auth();
+            let name = ServerName::try_from("example.com").unwrap();
+            TlsConnector::from(Arc::new(config))
+                .connect(name, client_io)
+                .await
+        });
+
+        let mut traffic = Traffic::new("https://example.com:443", "CONNECT", "1");
+        let mut handshake_error = None;
+        let accepted = accept_client_tls(
+            server_io,
+            &mut traffic,
+            |_| async { Ok(self_signed_config()) },
+            |err, elapsed| handshake_error = Some((err.to_string(), elapsed)),
+        )
+        .await;
+
+        assert!(accepted.is_none());
+        assert!(client.await.unwrap().is_err());
+        assert!(handshake_error.is_some());
+        assert_eq!(
+            traffic
+                .client_tls
+                .as_ref()
+                .and_then(|tls| tls.sni.as_deref()),
+            Some("example.com")
+        );
+        assert!(matches!(
+            traffic.transaction_state,
+            TransactionState::Failed
+        ));
+        assert!(traffic.end_time.is_some());
+        assert!(traffic
+            .error
+            .as_deref()
+            .is_some_and(|err| err.starts_with("Failed to establish TLS Connection")));
+    }
+}
@@ -1,5 +1,6 @@
 use crate::extract_mime;
 use crate::frontend_message::{send_to_frontend, NewTrafficHeadData, Payload, SendData, Status};
+use crate::key_log::{configure_key_log, KEY_LOG};
 use crate::server::{PrintMode, Server};
 use crate::traffic::{
     self, string_to_body_hex, wrap_entries, Body as TrafficBody, Header, Headers, SearchQuery,
@@ -12,7 +13,7 @@ use bytes::Bytes;
 use http::{Method, Uri};
 use http_body_util::Full;
 use hyper_proxy2::{Intercept, Proxy, ProxyConnector};
-use hyper_rustls::HttpsConnectorBuilder;
+use hyper_rustls::{ConfigBuilderExt, HttpsConnectorBuilder};
 use hyper_util::client::legacy::Client;
 use hyper_util::rt::TokioExecutor;
 use log::{debug, error};
@@ -31,6 +32,7 @@ use tauri_plugin_store::StoreBuilder;
 use time::format_description::well_known::Rfc3339;
 use time::OffsetDateTime;
 use tokio::sync::{broadcast, Mutex, Notify};
+use tokio_rustls::rustls::ClientConfig;
 use uuid::Uuid;
 
 use base64::Engine;
@@ -424,8 +426,13 @@ impl State {
         };
 
         // 创建 HTTPS 连接器
-        let https = HttpsConnectorBuilder::new()
+        configure_key_log(&self.app_handle);
+        let mut tls_config = ClientConfig::builder()
             .with_webpki_roots()
+            .with_no_client_auth();
+        tls_config.key_log = KEY_LOG.clone();
+        let https = HttpsConnectorBuilder::new()
+            .with_tls_config(tls_config)
             .https_or_http()
             .enable_all_versions()
             .build();
@@ -811,6 +818,9 @@ impl State {
         {
             error!("Failed to create traffic head: {}", e);
         }
+        self.traffics
+            .insert(head_id, Arc::new(traffic_clone.clone()))
+            .await;
 
         // 打印日志
         match self.print_mode {
@@ -1035,6 +1045,7 @@ impl State {
                     gid: gid as u64,
                     session_id: session_id.clone(),
                     uri,
+                    mapped_uri: None,
                     method: entry["request"]["method"]
                         .as_str()
                         .unwrap_or("")
@@ -1055,6 +1066,27 @@ impl State {
                     start_time,
                     end_time,
                     error: None,
+                    rewrites: Vec::new(),
+                    throttle: None,
+                    faults: Vec::new(),
+                    client_addr: None,
+                    connection_id: entry["connection"]
+                        .as_str()
+                        .and_then(|id| id.parse::<u64>().ok()),
+                    server_addr: entry["serverIPAddress"]
+                        .as_str()
+                        .filter(|ip| !ip.is_empty())
+                        .map(|ip| ip.to_string()),
+                    timings: serde_json::from_value(entry["timings"].clone()).ok(),
+                    client_tls: None,
+                    server_tls: None,
+                    client_cert: None,
+                    upstream_cert_error: None,
+                    forwarded_req_headers: None,
+                    proxy_route: None,
+                    bytes_sent: None,
+                    bytes_received: None,
+                    response_at: None,
                     valid: true,
                 };
 
@@ -0,0 +1,240 @@
+use std::{
+    cmp,
+    future::Future,
+    io,
+    marker::Unpin,
+    pin::Pin,
+    task::{self, ready, Poll},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, ReadBuf},
+    time::{Instant, Sleep},
+};
+
+/// 按固定带宽计算每块数据的发送时间
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    bytes_per_sec: u64,
+    available_at: Instant,
+}
+
+impl RateLimiter {
+    /// `kbps` 为 0 时不限速
+    pub(crate) fn new(kbps: u32) -> Option<Self> {
+        if kbps == 0 {
+            return None;
+        }
+        Some(Self {
+            bytes_per_sec: cmp::max(kbps as u64 * 1000 / 8, 1),
+            available_at: Instant::now(),
+        })
+    }
+
+    /// 单次发送的最大字节数，约为 100ms 的流量，使大块数据也能平滑发送
+    pub(crate) fn chunk_size(&self) -> usize {
+        cmp::max(self.bytes_per_sec / 10, 1) as usize
+    }
+
+    /// 记录发送了 `len` 字节，返回下一块数据需要等待的时间
+    pub(crate) fn consume(&mut self, len: usize) -> Duration {
+        let now = Instant::now();
+        let cost = Duration::from_secs_f64(len as f64 / self.bytes_per_sec as f64);
+        self.available_at = cmp::max(self.available_at, now) + cost;
+        self.available_at.saturating_duration_since(now)
+    }
+}
+
+/// 对隧道连接限速，读方向对应上行，写方向对应下行
+///
+/// 同时统计双向的字节数，隧道出错断开时也能取得已转发的字节数
+pub(crate) struct ThrottledStream<T> {
+    inner: T,
+    read_limiter: Option<RateLimiter>,
+    write_limiter: Option<RateLimiter>,
+    read_delay: Option<Pin<Box<Sleep>>>,
+    write_delay: Option<Pin<Box<Sleep>>>,
+    bytes_read: u64,
+    bytes_written: u64,
+}
+
+impl<T> ThrottledStream<T> {
+    pub(crate) fn new(
+        inner: T,
+        read_limiter: Option<RateLimiter>,
+        write_limiter: Option<RateLimiter>,
+    ) -> Self {
+        Self {
+            inner,
+            read_limiter,
+            write_limiter,
+            read_delay: None,
+            write_delay: None,
+            bytes_read: 0,
+            bytes_written: 0,
+        }
+    }
+
+    /// 已读取的字节数
+    pub(crate) fn bytes_read(&self) -> u64 {
+        self.bytes_read
+    }
+
+    /// 已写入的字节数
+    pub(crate) fn bytes_written(&self) -> u64 {
+        self.bytes_written
+    }
+}
+
+fn poll_delay(delay: &mut Option<Pin<Box<Sleep>>>, cx: &mut task::Context<'_>) -> Poll<()> {
+    if let Some(sleep) = delay.as_mut() {
+        ready!(sleep.as_mut().poll(cx));
+        *delay = None;
+    }
+    Poll::Ready(())
+}
+
+fn schedule_delay(limiter: &mut RateLimiter, len: usize) -> Option<Pin<Box<Sleep>>> {
+    let wait = limiter.consume(len);
+    (!wait.is_zero()).then(|| Box::pin(tokio::time::sleep(wait)))
+}
+
+impl<T> AsyncRead for ThrottledStream<T>
+where
+    T: AsyncRead + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        ready!(poll_delay(&mut this.read_delay, cx));
+
+        let Some(limiter) = this.read_limiter.as_mut() else {
+            let filled = buf.filled().len();
+            ready!(Pin::new(&mut this.inner).poll_read(cx, buf))?;
+            this.bytes_read += (buf.filled().len() - filled) as u64;
+            return Poll::Ready(Ok(()));
+        };
+
+        // 直接读入 buf 的未填充部分，只限制本次读取的长度
+        let limit = cmp::min(buf.remaining(), limiter.chunk_size());
+        let mut limited = ReadBuf::new(buf.initialize_unfilled_to(limit));
+        ready!(Pin::new(&mut this.inner).poll_read(cx, &mut limited))?;
+        let n = limited.filled().len();
+        buf.advance(n);
+        this.bytes_read += n as u64;
+        this.read_delay = schedule_delay(limiter, n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T> AsyncWrite for ThrottledStream<T>
+where
+    T: AsyncWrite + Unpin,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = &mut *self;
+        ready!(poll_delay(&mut this.write_delay, cx));
+
+        let Some(limiter) = this.write_limiter.as_mut() else {
+            let n = ready!(Pin::new(&mut this.inner).poll_write(cx, buf))?;
+            this.bytes_written += n as u64;
+            return Poll::Ready(Ok(n));
+        };
+
+        let limit = cmp::min(buf.len(), limiter.chunk_size());
+        let n = ready!(Pin::new(&mut this.inner).poll_write(cx, &buf[..limit]))?;
+        this.bytes_written += n as u64;
+        this.write_delay = schedule_delay(limiter, n);
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    const LEN: usize = 10_000;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_rate_limiter() {
+        // 80kbps 即 10000 B/s，每块 1000 字节约 100ms
+        let mut limiter = RateLimiter::new(80).unwrap();
+        assert_eq!(limiter.chunk_size(), 1000);
+        assert_eq!(limiter.consume(1000), Duration::from_millis(100));
+        assert_eq!(limiter.consume(500), Duration::from_millis(150));
+        assert!(RateLimiter::new(0).is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_throttled_write() {
+        let (client, mut server) = tokio::io::duplex(64 * 1024);
+        let mut stream = ThrottledStream::new(client, None, RateLimiter::new(80));
+        let reader = tokio::spawn(async move {
+            let mut buf = vec![0; LEN];
+            server.read_exact(&mut buf).await.unwrap();
+        });
+
+        let start = Instant::now();
+        stream.write_all(&[1; LEN]).await.unwrap();
+        reader.await.unwrap();
+        // 约 LEN * 8 / 80 ms，最后一块发送后不再等待
+        let elapsed = start.elapsed();
+        assert!(
+            elapsed >= Duration::from_millis(900) && elapsed <= Duration::from_millis(1000),
+            "{elapsed:?}"
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_throttled_read() {
+        let (client, mut server) = tokio::io::duplex(64 * 1024);
+        let data: Vec<u8> = (0..LEN).map(|i| i as u8).collect();
+        server.write_all(&data).await.unwrap();
+        let mut stream = ThrottledStream::new(client, RateLimiter::new(80), None);
+
+        let start = Instant::now();
+        let mut received = Vec::new();
+        let mut buf = vec![0; 8192];
+        while received.len() < LEN {
+            let n = stream.read(&mut buf).await.unwrap();
+            assert!(n > 0 && n <= 1000, "read {n} bytes");
+            received.extend_from_slice(&buf[..n]);
+        }
+        assert_eq!(received, data);
+        let elapsed = start.elapsed();
+        assert!(
+            elapsed >= Duration::from_millis(900) && elapsed <= Duration::from_millis(1000),
+            "{elapsed:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_byte_counts() {
+        let (client, mut server) = tokio::io::duplex(1024);
+        let mut stream = ThrottledStream::new(client, None, None);
+        server.write_all(b"hello").await.unwrap();
+        let mut buf = [0; 16];
+        let n = stream.read(&mut buf).await.unwrap();
+        stream.write_all(b"hi").await.unwrap();
+        assert_eq!(n, 5);
+        assert_eq!(stream.bytes_read(), 5);
+        assert_eq!(stream.bytes_written(), 2);
+    }
+}
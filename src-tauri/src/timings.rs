@@ -0,0 +1,183 @@
+use std::{
+    future::Future,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use hyper::Uri;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+use tower_service::Service;
+
+/// HAR 时间分解，单位毫秒，未测量的阶段为 -1
+///
+/// 与 HAR 规范一致，`connect` 包含 `ssl` 的时间
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Timings {
+    pub blocked: f64,
+    pub dns: f64,
+    pub connect: f64,
+    pub ssl: f64,
+    pub send: f64,
+    pub wait: f64,
+    pub receive: f64,
+}
+
+impl Default for Timings {
+    fn default() -> Self {
+        Self {
+            blocked: -1.0,
+            dns: -1.0,
+            connect: -1.0,
+            ssl: -1.0,
+            send: -1.0,
+            wait: -1.0,
+            receive: -1.0,
+        }
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    (duration.as_secs_f64() * 1000.0 * 1000.0).round() / 1000.0
+}
+
+/// 连接建立过程中记录的阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ConnectStage {
+    /// DNS 解析
+    Dns,
+    /// DNS 解析 + TCP 连接
+    Tcp,
+    /// 完整的连接建立，包括 TLS 握手
+    Connect,
+}
+
+#[derive(Debug, Default)]
+struct Marks {
+    dns: Option<(Instant, Instant)>,
+    tcp: Option<(Instant, Instant)>,
+    connect: Option<(Instant, Instant)>,
+    sent: Option<Instant>,
+    remote_addr: Option<SocketAddr>,
+}
+
+/// 单个请求的计时器，由上游连接器、解析器和请求 body 共享
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TimingRecorder {
+    marks: Arc<Mutex<Marks>>,
+    connected: Arc<Notify>,
+}
+
+impl TimingRecorder {
+    pub(crate) fn record(&self, stage: ConnectStage, start: Instant, end: Instant) {
+        let mut marks = self.marks.lock().unwrap();
+        let slot = match stage {
+            ConnectStage::Dns => &mut marks.dns,
+            ConnectStage::Tcp => &mut marks.tcp,
+            ConnectStage::Connect => &mut marks.connect,
+        };
+        *slot = Some((start, end));
+    }
+
+    /// 请求 body 发送完毕
+    pub(crate) fn mark_sent(&self) {
+        self.marks.lock().unwrap().sent = Some(Instant::now());
+    }
+
+    /// 上游 TCP 连接建立，记录对端地址（经过代理时为代理的地址）
+    pub(crate) fn mark_connected(&self, remote_addr: SocketAddr) {
+        self.marks.lock().unwrap().remote_addr = Some(remote_addr);
+        self.connected.notify_one();
+    }
+
+    /// 等待上游 TCP 连接建立，返回对端地址
+    pub(crate) async fn connected(&self) -> Option<SocketAddr> {
+        self.connected.notified().await;
+        self.marks.lock().unwrap().remote_addr
+    }
+
+    /// 根据请求开始时间和收到响应头的时间计算各阶段耗时，`receive` 在 body 接收完成后填充
+    pub(crate) fn timings(&self, request_start: Instant, response_at: Instant) -> Timings {
+        let marks = self.marks.lock().unwrap();
+        let mut timings = Timings::default();
+
+        let dns = marks.dns.map(|(start, end)| end - start);
+        if let Some(dns) = dns {
+            timings.dns = millis(dns);
+        }
+        let mut connected_at = request_start;
+        if let Some((start, end)) = marks.connect {
+            timings.blocked = millis(start - request_start);
+            timings.connect = millis((end - start).saturating_sub(dns.unwrap_or_default()));
+            if let Some((tcp_start, tcp_end)) = marks.tcp {
+                let ssl = (end - start).saturating_sub(tcp_end - tcp_start);
+                if !ssl.is_zero() {
+                    timings.ssl = millis(ssl);
+                }
+            }
+            connected_at = end;
+        }
+        let sent_at = marks
+            .sent
+            .filter(|sent| *sent >= connected_at && *sent <= response_at)
+            .unwrap_or(connected_at);
+        timings.send = millis(sent_at - connected_at);
+        timings.wait = millis(response_at - sent_at);
+        timings
+    }
+}
+
+impl Timings {
+    /// 记录响应 body 的接收耗时
+    pub fn set_receive(&mut self, duration: Duration) {
+        self.receive = millis(duration);
+    }
+}
+
+/// 记录内部连接器耗时的包装
+#[derive(Debug, Clone)]
+pub(crate) struct TimedConnector<C> {
+    inner: C,
+    recorder: TimingRecorder,
+    stage: ConnectStage,
+}
+
+impl<C> TimedConnector<C> {
+    pub(crate) fn new(inner: C, recorder: TimingRecorder, stage: ConnectStage) -> Self {
+        Self {
+            inner,
+            recorder,
+            stage,
+        }
+    }
+}
+
+impl<C> Service<Uri> for TimedConnector<C>
+where
+    C: Service<Uri>,
+    C::Future: Send + 'static,
+{
+    type Response = C::Response;
+    type Error = C::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let recorder = self.recorder.clone();
+        let stage = self.stage;
+        let start = Instant::now();
+        let fut = self.inner.call(uri);
+        Box::pin(async move {
+            let res = fut.await;
+            recorder.record(stage, start, Instant::now());
+            res
+        })
+    }
+}
@@ -0,0 +1,279 @@
+use std::{
+    future::Future,
+    io,
+    net::IpAddr,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use hyper::{
+    rt::{Read, ReadBufCursor, Write},
+    Uri,
+};
+use hyper_rustls::MaybeHttpsStream;
+use hyper_util::client::legacy::connect::{Connected, Connection};
+use serde::{Deserialize, Serialize};
+use time::format_description::well_known::Rfc3339;
+use tokio_rustls::rustls::{
+    pki_types::CertificateDer, server::ClientHello, CommonState, ProtocolVersion,
+};
+use tower_service::Service;
+use x509_parser::{certificate::X509Certificate, extensions::GeneralName, prelude::FromDer};
+
+use crate::upstream_tls::CertErrorSlot;
+
+/// 证书摘要
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CertificateInfo {
+    pub subject: String,
+    pub issuer: String,
+    pub sans: Vec<String>,
+    pub serial: String,
+    pub not_before: String,
+    pub not_after: String,
+}
+
+/// 单侧连接的 TLS 握手信息
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TlsSession {
+    pub sni: Option<String>,
+    /// 客户端在 ClientHello 中提供的 ALPN 协议
+    pub alpn_offered: Vec<String>,
+    /// 协商的 ALPN 协议
+    pub alpn: Option<String>,
+    pub version: Option<String>,
+    pub cipher: Option<String>,
+    /// 服务端证书链，从叶子证书开始
+    pub certificates: Vec<CertificateInfo>,
+}
+
+impl TlsSession {
+    /// 读取 ClientHello 中的 SNI 和 ALPN
+    pub(crate) fn from_client_hello(hello: &ClientHello<'_>) -> Self {
+        Self {
+            sni: hello.server_name().map(str::to_string),
+            alpn_offered: hello
+                .alpn()
+                .map(|protocols| protocols.map(protocol_name).collect())
+                .unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+
+    /// 记录握手完成后协商的参数
+    pub(crate) fn set_negotiated(&mut self, state: &CommonState) {
+        self.alpn = state.alpn_protocol().map(protocol_name);
+        self.version = state.protocol_version().map(version_name);
+        self.cipher = state
+            .negotiated_cipher_suite()
+            .map(|suite| format!("{:?}", suite.suite()));
+    }
+
+    /// 上游连接的握手信息，`sni` 与 hyper_rustls 一致取自 URI 中的域名
+    fn upstream(uri: &Uri, state: &CommonState) -> Self {
+        let mut session = Self {
+            sni: uri
+                .host()
+                .filter(|host| host.trim_matches(['[', ']']).parse::<IpAddr>().is_err())
+                .map(str::to_string),
+            certificates: state
+                .peer_certificates()
+                .unwrap_or_default()
+                .iter()
+                .filter_map(parse_certificate)
+                .collect(),
+            ..Default::default()
+        };
+        session.set_negotiated(state);
+        session
+    }
+}
+
+fn protocol_name(protocol: &[u8]) -> String {
+    String::from_utf8_lossy(protocol).into_owned()
+}
+
+fn version_name(version: ProtocolVersion) -> String {
+    match version {
+        ProtocolVersion::TLSv1_0 => "TLSv1.0".to_string(),
+        ProtocolVersion::TLSv1_1 => "TLSv1.1".to_string(),
+        ProtocolVersion::TLSv1_2 => "TLSv1.2".to_string(),
+        ProtocolVersion::TLSv1_3 => "TLSv1.3".to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+fn parse_certificate(der: &CertificateDer<'_>) -> Option<CertificateInfo> {
+    let (_, cert) = X509Certificate::from_der(der.as_ref()).ok()?;
+    let sans = cert
+        .subject_alternative_name()
+        .ok()
+        .flatten()
+        .map(|ext| {
+            ext.value
+                .general_names
+                .iter()
+                .filter_map(|name| match name {
+                    GeneralName::DNSName(dns) => Some(dns.to_string()),
+                    GeneralName::IPAddress(ip) => match ip.len() {
+                        4 => Some(IpAddr::from(<[u8; 4]>::try_from(*ip).ok()?).to_string()),
+                        16 => Some(IpAddr::from(<[u8; 16]>::try_from(*ip).ok()?).to_string()),
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let validity = cert.validity();
+
+    Some(CertificateInfo {
+        subject: cert.subject().to_string(),
+        issuer: cert.issuer().to_string(),
+        sans,
+        serial: cert.raw_serial_as_string(),
+        not_before: validity
+            .not_before
+            .to_datetime()
+            .format(&Rfc3339)
+            .unwrap_or_default(),
+        not_after: validity
+            .not_after
+            .to_datetime()
+            .format(&Rfc3339)
+            .unwrap_or_default(),
+    })
+}
+
+/// 记录上游 TLS 握手信息的连接器包装，握手信息通过 `Connected::extra` 写入响应扩展
+#[derive(Debug, Clone)]
+pub(crate) struct TlsInspector<C> {
+    inner: C,
+    cert_error: Option<CertErrorSlot>,
+}
+
+impl<C> TlsInspector<C> {
+    pub(crate) fn new(inner: C) -> Self {
+        Self {
+            inner,
+            cert_error: None,
+        }
+    }
+
+    /// 握手完成后取出验证器记录的证书错误，一并写入响应扩展
+    pub(crate) fn with_cert_error(mut self, cert_error: Option<CertErrorSlot>) -> Self {
+        self.cert_error = cert_error;
+        self
+    }
+}
+
+/// 允许无效证书时记录的上游证书校验错误
+#[derive(Debug, Clone)]
+pub(crate) struct UpstreamCertError(pub(crate) String);
+
+impl<C, T> Service<Uri> for TlsInspector<C>
+where
+    C: Service<Uri, Response = MaybeHttpsStream<T>>,
+    C::Future: Send + 'static,
+    T: Send + 'static,
+{
+    type Response = InspectedStream<T>;
+    type Error = C::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let fut = self.inner.call(uri.clone());
+        let cert_error = self.cert_error.clone();
+        Box::pin(async move {
+            let stream = fut.await?;
+            let cert_error = cert_error
+                .and_then(|slot| slot.lock().unwrap().take())
+                .map(UpstreamCertError);
+            let session = match &stream {
+                MaybeHttpsStream::Https(tls) => {
+                    Some(TlsSession::upstream(&uri, tls.inner().get_ref().1))
+                }
+                MaybeHttpsStream::Http(_) => None,
+            };
+            Ok(InspectedStream {
+                inner: stream,
+                session,
+                cert_error,
+            })
+        })
+    }
+}
+
+pub(crate) struct InspectedStream<T> {
+    inner: MaybeHttpsStream<T>,
+    session: Option<TlsSession>,
+    cert_error: Option<UpstreamCertError>,
+}
+
+impl<T> Connection for InspectedStream<T>
+where
+    MaybeHttpsStream<T>: Connection,
+{
+    fn connected(&self) -> Connected {
+        let mut connected = self.inner.connected();
+        if let Some(session) = &self.session {
+            connected = connected.extra(session.clone());
+        }
+        if let Some(cert_error) = &self.cert_error {
+            connected = connected.extra(cert_error.clone());
+        }
+        connected
+    }
+}
+
+impl<T> Read for InspectedStream<T>
+where
+    T: Read + Write + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: ReadBufCursor<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<T> Write for InspectedStream<T>
+where
+    T: Read + Write + Unpin,
+{
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.inner.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
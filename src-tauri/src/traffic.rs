@@ -1,3 +1,5 @@
+use crate::timings::Timings;
+use crate::tls_info::TlsSession;
 use crate::utils::*;
 
 use anyhow::{bail, Result};
@@ -9,6 +11,7 @@ use serde_json::{json, Value};
 use std::{
     path::Path,
     sync::atomic::{self, AtomicU64},
+    time::Instant,
 };
 use time::{format_description::well_known::Rfc3339, OffsetDateTime};
 
@@ -23,6 +26,7 @@ pub enum TransactionState {
     Completed,    // 完整完成
     Failed,       // 失败
     Aborted,      // 中止
+    Blocked,      // 被 BlockList/AllowList 拦截
 }
 
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
@@ -146,9 +150,15 @@ pub struct Traffic {
     pub gid: u64,
     pub session_id: String,
     pub uri: String,
+    /// MapRemote 改写后实际请求的 URL
+    #[serde(default)]
+    pub mapped_uri: Option<String>,
     pub method: String,
     pub transaction_state: TransactionState,
     pub req_headers: Option<Headers>,
+    /// MapRemote / Rewrite 改写后实际发往上游的请求头，未改写时为空，`req_headers` 保留客户端原始请求头
+    #[serde(default)]
+    pub forwarded_req_headers: Option<Headers>,
     pub req_body_file: Option<String>,
     pub req_body_hex: Option<Vec<BodyHex>>,
     pub status: Option<u16>,
@@ -169,6 +179,51 @@ pub struct Traffic {
     )]
     pub end_time: Option<OffsetDateTime>,
     pub error: Option<String>,
+    /// 命中的 Rewrite 规则
+    #[serde(default)]
+    pub rewrites: Vec<String>,
+    /// 生效的限速配置名称
+    #[serde(default)]
+    pub throttle: Option<String>,
+    /// 触发的故障注入
+    #[serde(default)]
+    pub faults: Vec<String>,
+    /// 客户端地址
+    #[serde(default)]
+    pub client_addr: Option<String>,
+    /// 客户端连接 id，同一连接上的请求共享
+    #[serde(default)]
+    pub connection_id: Option<u64>,
+    /// 实际连接的上游地址
+    #[serde(default)]
+    pub server_addr: Option<String>,
+    /// HAR 时间分解
+    #[serde(default)]
+    pub timings: Option<Timings>,
+    /// 客户端与代理之间的 TLS 握手信息
+    #[serde(default)]
+    pub client_tls: Option<TlsSession>,
+    /// 代理与上游之间的 TLS 握手信息
+    #[serde(default)]
+    pub server_tls: Option<TlsSession>,
+    /// 连接上游时出示的客户端证书身份
+    #[serde(default)]
+    pub client_cert: Option<String>,
+    /// 允许无效证书时记录的上游证书校验错误
+    #[serde(default)]
+    pub upstream_cert_error: Option<String>,
+    /// 外部代理配置选择的上游路由，如 `PROXY host:port` 或 `DIRECT`
+    #[serde(default)]
+    pub proxy_route: Option<String>,
+    /// 透传隧道中客户端发往上游的字节数
+    #[serde(default)]
+    pub bytes_sent: Option<u64>,
+    /// 透传隧道中上游发往客户端的字节数
+    #[serde(default)]
+    pub bytes_received: Option<u64>,
+    /// 收到响应头的时间，用于计算 body 接收耗时
+    #[serde(skip)]
+    pub(crate) response_at: Option<Instant>,
     #[serde(skip)]
     pub(crate) valid: bool,
 }
@@ -179,9 +234,11 @@ impl Traffic {
             gid: GLOBAL_ID.fetch_add(1, atomic::Ordering::Relaxed),
             session_id: session_id.to_string(),
             uri: uri.to_string(),
+            mapped_uri: None,
             method: method.to_string(),
             transaction_state: TransactionState::Pending,
             req_headers: None,
+            forwarded_req_headers: None,
             req_body_file: None,
             req_body_hex: None,
             status: None,
@@ -194,6 +251,21 @@ impl Traffic {
             end_time: None,
             websocket_id: None,
             error: None,
+            rewrites: Vec::new(),
+            throttle: None,
+            faults: Vec::new(),
+            client_addr: None,
+            connection_id: None,
+            server_addr: None,
+            timings: None,
+            client_tls: None,
+            server_tls: None,
+            client_cert: None,
+            upstream_cert_error: None,
+            proxy_route: None,
+            bytes_sent: None,
+            bytes_received: None,
+            response_at: None,
             valid: true,
         }
     }
@@ -205,6 +277,12 @@ impl Traffic {
         None
     }
 
+    pub fn forwarded_req_head_json(&self) -> Option<String> {
+        self.forwarded_req_headers
+            .as_ref()
+            .map(|headers| headers.to_json())
+    }
+
     pub fn res_head_json(&self) -> Option<String> {
         if let Some(headers) = &self.res_headers {
             return Some(headers.to_json());
@@ -212,6 +290,13 @@ impl Traffic {
         None
     }
 
+    /// 记录错误并以失败状态结束
+    pub(crate) fn fail(&mut self, error: String) {
+        self.add_error(error);
+        self.set_transaction_state(TransactionState::Failed);
+        self.end_time = Some(OffsetDateTime::now_utc());
+    }
+
     pub fn add_error(&mut self, error: String) {
         match self.error.as_mut() {
             Some(current_error) => {
@@ -239,10 +324,18 @@ impl Traffic {
 
         lines.push(format!("\n# {}", self.oneline()));
 
+        if let Some(connection) = self.render_connection() {
+            lines.push(connection);
+        }
+
         if let Some(headers) = &self.req_headers {
             lines.push(render_header("REQUEST HEADERS", headers));
         }
 
+        if let Some(headers) = &self.forwarded_req_headers {
+            lines.push(render_header("FORWARDED REQUEST HEADERS", headers));
+        }
+
         if let Some(body) = req_body {
             lines.push(render_body("REQUEST BODY", &body, &self.req_headers));
         }
@@ -262,6 +355,52 @@ impl Traffic {
         lines.join("\n\n")
     }
 
+    fn render_connection(&self) -> Option<String> {
+        let mut items: Vec<String> = vec![];
+        if let Some(client_addr) = &self.client_addr {
+            items.push(format!("client: {client_addr}"));
+        }
+        if let Some(connection_id) = self.connection_id {
+            items.push(format!("connection: {connection_id}"));
+        }
+        if let Some(server_addr) = &self.server_addr {
+            items.push(format!("server: {server_addr}"));
+        }
+        if let Some(client_cert) = &self.client_cert {
+            items.push(format!("client certificate: {client_cert}"));
+        }
+        if let Some(err) = &self.upstream_cert_error {
+            items.push(format!("upstream certificate error: {err}"));
+        }
+        if let Some(route) = &self.proxy_route {
+            items.push(format!("proxy route: {route}"));
+        }
+        if let (Some(sent), Some(received)) = (self.bytes_sent, self.bytes_received) {
+            items.push(format!(
+                "tunnel: {sent} bytes sent, {received} bytes received"
+            ));
+        }
+        if items.is_empty() {
+            return None;
+        }
+        Some(format!(
+            r#"CONNECTION
+```
+{}
+```"#,
+            items.join("\n")
+        ))
+    }
+
+    /// 上游 IP，不含端口
+    pub fn server_ip(&self) -> Option<String> {
+        let server_addr = self.server_addr.as_ref()?;
+        match server_addr.parse::<std::net::SocketAddr>() {
+            Ok(addr) => Some(addr.ip().to_string()),
+            Err(_) => Some(server_addr.clone()),
+        }
+    }
+
     pub async fn har(&self) -> Value {
         let entries = match self.har_entry().await {
             Some(v) => vec![v],
@@ -295,20 +434,21 @@ impl Traffic {
             "headersSize": har_size(self.res_headers.as_ref().map(|v| v.size), -1),
             "bodySize": har_size(self.res_body_size, -1),
         });
-        Some(json!({
+        let mut entry = json!({
             "startedDateTime": self.start_time.as_ref().and_then(|v| v.format(&Rfc3339).ok()),
             "time": self.time().map(|v| v as isize).unwrap_or(-1),
             "request": request,
             "response": response,
             "cache": {},
-            "timings": {
-                "connect": -1,
-                "ssl": -1,
-                "send": -1,
-                "receive": -1,
-                "wait": -1
-            }
-        }))
+            "timings": self.timings.clone().unwrap_or_default(),
+        });
+        if let Some(server_ip) = self.server_ip() {
+            entry["serverIPAddress"] = json!(server_ip);
+        }
+        if let Some(connection_id) = self.connection_id {
+            entry["connection"] = json!(connection_id.to_string());
+        }
+        Some(entry)
     }
 
     pub async fn curl(&self) -> String {
@@ -392,6 +532,9 @@ impl Traffic {
             start_time: self.start_time,
             websocket_id: self.websocket_id,
             session_id,
+            client_addr: self.client_addr.clone(),
+            connection_id: self.connection_id,
+            server_addr: self.server_addr.clone(),
         }
     }
 
@@ -409,6 +552,29 @@ impl Traffic {
         self.req_headers = Some(Headers::new(headers));
         self
     }
+    /// 实际发往上游的请求头，未改写时即客户端原始请求头
+    pub(crate) fn upstream_req_headers(&self) -> Option<&Headers> {
+        self.forwarded_req_headers
+            .as_ref()
+            .or(self.req_headers.as_ref())
+    }
+
+    /// 修改转发给上游的请求头，首次修改时从原始请求头复制，不影响记录的原始请求头
+    pub(crate) fn set_forwarded_req_header(&mut self, name: &str, value: &str) -> &mut Self {
+        let original = &self.req_headers;
+        self.forwarded_req_headers
+            .get_or_insert_with(|| original.clone().unwrap_or_default())
+            .set(name, value);
+        self
+    }
+
+    pub(crate) fn set_res_header(&mut self, name: &str, value: &str) -> &mut Self {
+        self.res_headers
+            .get_or_insert_with(Headers::default)
+            .set(name, value);
+        self
+    }
+
     pub(crate) fn set_req_body_file(&mut self, path: &Path) -> &mut Self {
         self.req_body_file = Some(path.display().to_string());
         self
@@ -481,6 +647,9 @@ impl Traffic {
             self.end_time = Some(OffsetDateTime::now_utc());
             self.res_body_size = Some(raw_size);
         }
+        if let (Some(timings), Some(response_at)) = (self.timings.as_mut(), self.response_at) {
+            timings.set_receive(response_at.elapsed());
+        }
     }
 
     pub(crate) async fn bodies(&self, binary_in_base64: bool) -> (Option<Body>, Option<Body>) {
@@ -518,6 +687,12 @@ pub struct TrafficHead {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub websocket_id: Option<usize>,
     pub session_id: String,
+    #[serde(default)]
+    pub client_addr: Option<String>,
+    #[serde(default)]
+    pub connection_id: Option<u64>,
+    #[serde(default)]
+    pub server_addr: Option<String>,
 }
 
 impl TrafficHead {
@@ -536,7 +711,7 @@ impl TrafficHead {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Headers {
     pub items: Vec<Header>,
     pub size: u64,
@@ -550,6 +725,35 @@ impl Headers {
         }
     }
 
+    /// 设置头部，已存在时（不区分大小写）替换其值
+    pub fn set(&mut self, name: &str, value: &str) {
+        match self
+            .items
+            .iter_mut()
+            .find(|h| h.name.eq_ignore_ascii_case(name))
+        {
+            Some(header) => header.value = value.to_string(),
+            None => self.items.push(Header::new(name, value)),
+        }
+        self.update_size();
+    }
+
+    /// 删除头部（不区分大小写）
+    pub fn remove(&mut self, name: &str) {
+        self.items.retain(|h| !h.name.eq_ignore_ascii_case(name));
+        self.update_size();
+    }
+
+    /// 修改头部后重新计算大小，与 `cal_headers_size` 一致
+    pub fn update_size(&mut self) {
+        self.size = self
+            .items
+            .iter()
+            .map(|h| h.name.len() as u64 + h.value.len() as u64 + 12)
+            .sum::<u64>()
+            + 7;
+    }
+
     pub fn to_json(&self) -> String {
         let mut json_str = String::from("{\n");
         let mut cookies = Vec::new();
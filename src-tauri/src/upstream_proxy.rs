@@ -0,0 +1,853 @@
+use std::{
+    collections::hash_map::DefaultHasher,
+    future::Future,
+    hash::{Hash, Hasher},
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use http::HeaderValue;
+use hyper::Uri;
+use hyper_util::{
+    client::legacy::connect::{Connected, Connection, HttpConnector},
+    rt::TokioIo,
+};
+use log::error;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf},
+    net::TcpStream,
+    time::timeout,
+};
+use tokio_rustls::{
+    client::TlsStream,
+    rustls::{pki_types::ServerName, ClientConfig},
+    TlsConnector,
+};
+use tokio_socks::tcp::Socks5Stream;
+use tower_service::Service;
+
+use crate::{
+    models::{
+        crypto::CRYPTO_SERVICE,
+        dns_override::OverrideResolver,
+        external_proxy::{
+            check_proxy_config, is_direct_upstream, ExternalProxy,
+            MutableExternalProxyConfiguration, NamedUpstream,
+        },
+        upstream_trust::UpstreamTrust,
+    },
+    pac::{find_proxy_for_url, PacProxy},
+    proxy_health,
+    timings::TimingRecorder,
+    upstream_tls::root_store,
+};
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// CONNECT 响应头的最大长度
+const MAX_CONNECT_RESPONSE: usize = 16 * 1024;
+
+/// 连接单个代理（含 CONNECT 握手）的超时，超时后尝试下一项
+const PROXY_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+fn decrypt_auth(
+    config: &MutableExternalProxyConfiguration,
+) -> Result<Option<(String, String)>, String> {
+    if !config.requires_authentication {
+        return Ok(None);
+    }
+    let password = CRYPTO_SERVICE
+        .decrypt(&config.encrypted_password)
+        .map_err(|e| format!("密码解密失败: {}", e))?;
+    Ok(Some((config.username.clone(), password)))
+}
+
+/// 命中 DNS 覆盖时使用覆盖的 IP，否则把主机名交给代理解析
+fn proxy_target(host: &str, resolver: &OverrideResolver) -> String {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    match resolver.lookup(host).and_then(|ips| ips.first()) {
+        Some(ip) => ip.to_string(),
+        None => host.to_string(),
+    }
+}
+
+/// 上游 TCP 连接，到 HTTPS 代理时为 TLS 连接
+pub(crate) struct UpstreamStream {
+    io: StreamKind,
+    /// 连接的是 HTTP 代理本身，请求需要使用绝对形式的 URI
+    proxied: bool,
+    /// 经过外部代理配置选择的路由
+    route: Option<String>,
+}
+
+enum StreamKind {
+    Tcp(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl UpstreamStream {
+    fn tcp(&self) -> &TcpStream {
+        match &self.io {
+            StreamKind::Tcp(stream) => stream,
+            StreamKind::Tls(stream) => stream.get_ref().0,
+        }
+    }
+
+    pub(crate) fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.tcp().peer_addr()
+    }
+
+    pub(crate) fn route(&self) -> Option<&str> {
+        self.route.as_deref()
+    }
+}
+
+impl From<TcpStream> for UpstreamStream {
+    fn from(stream: TcpStream) -> Self {
+        Self {
+            io: StreamKind::Tcp(stream),
+            proxied: false,
+            route: None,
+        }
+    }
+}
+
+impl Connection for UpstreamStream {
+    fn connected(&self) -> Connected {
+        let connected = self.tcp().connected().proxy(self.proxied);
+        match &self.route {
+            Some(route) => connected.extra(ProxyRoute(route.clone())),
+            None => connected,
+        }
+    }
+}
+
+impl AsyncRead for UpstreamStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match &mut self.get_mut().io {
+            StreamKind::Tcp(stream) => Pin::new(stream).poll_read(cx, buf),
+            StreamKind::Tls(stream) => Pin::new(stream.as_mut()).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for UpstreamStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match &mut self.get_mut().io {
+            StreamKind::Tcp(stream) => Pin::new(stream).poll_write(cx, buf),
+            StreamKind::Tls(stream) => Pin::new(stream.as_mut()).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match &mut self.get_mut().io {
+            StreamKind::Tcp(stream) => Pin::new(stream).poll_flush(cx),
+            StreamKind::Tls(stream) => Pin::new(stream.as_mut()).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match &mut self.get_mut().io {
+            StreamKind::Tcp(stream) => Pin::new(stream).poll_shutdown(cx),
+            StreamKind::Tls(stream) => Pin::new(stream.as_mut()).poll_shutdown(cx),
+        }
+    }
+}
+
+/// SOCKS5 上游代理
+#[derive(Debug, Clone)]
+pub(crate) struct Socks5Proxy {
+    addr: String,
+    auth: Option<(String, String)>,
+}
+
+impl Socks5Proxy {
+    pub(crate) fn from_config(config: &MutableExternalProxyConfiguration) -> Result<Self, String> {
+        Ok(Self {
+            addr: format!("{}:{}", config.host, config.port),
+            auth: decrypt_auth(config)?,
+        })
+    }
+
+    /// 通过代理连接目标，主机名交给代理解析；命中 DNS 覆盖时改为连接覆盖的 IP
+    pub(crate) async fn connect(
+        &self,
+        host: &str,
+        port: u16,
+        resolver: &OverrideResolver,
+    ) -> io::Result<TcpStream> {
+        let target = proxy_target(host, resolver);
+        let socket = TcpStream::connect(self.addr.as_str()).await?;
+        let stream = match &self.auth {
+            Some((username, password)) => {
+                Socks5Stream::connect_with_password_and_socket(
+                    socket,
+                    (target.as_str(), port),
+                    username,
+                    password,
+                )
+                .await
+            }
+            None => Socks5Stream::connect_with_socket(socket, (target.as_str(), port)).await,
+        }
+        .map_err(|err| {
+            // 保留底层 IO 错误的类型，供熔断判断代理是否不可达
+            let kind = match &err {
+                tokio_socks::Error::Io(err) => err.kind(),
+                _ => io::ErrorKind::Other,
+            };
+            io::Error::new(kind, format!("SOCKS5 proxy {}: {}", self.addr, err))
+        })?;
+        Ok(stream.into_inner())
+    }
+}
+
+/// HTTP 上游代理，`tls` 为空时以明文连接代理
+#[derive(Clone)]
+pub(crate) struct HttpProxy {
+    host: String,
+    port: u16,
+    tls: Option<Arc<ClientConfig>>,
+    authorization: Option<HeaderValue>,
+}
+
+impl HttpProxy {
+    /// HTTPS 代理的证书按上游证书校验配置中的根证书校验
+    pub(crate) fn from_config(
+        config: &MutableExternalProxyConfiguration,
+        trust: &UpstreamTrust,
+    ) -> Result<Self, String> {
+        let authorization = match decrypt_auth(config)? {
+            Some((username, password)) => {
+                let credentials = STANDARD.encode(format!("{}:{}", username, password));
+                let value = HeaderValue::from_str(&format!("Basic {}", credentials))
+                    .map_err(|e| format!("代理认证信息无效: {}", e))?;
+                Some(value)
+            }
+            None => None,
+        };
+        let tls = config.use_tls.then(|| {
+            let roots = root_store(trust.native_roots, &trust.ca_files);
+            Arc::new(
+                ClientConfig::builder()
+                    .with_root_certificates(roots)
+                    .with_no_client_auth(),
+            )
+        });
+        Ok(Self {
+            host: config.host.clone(),
+            port: config.port,
+            tls,
+            authorization,
+        })
+    }
+
+    fn addr(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    fn label(&self) -> String {
+        match self.tls {
+            Some(_) => format!("HTTPS {}", self.addr()),
+            None => format!("PROXY {}", self.addr()),
+        }
+    }
+
+    /// 明文 HTTP 请求直接发给代理时附带的 Proxy-Authorization
+    pub(crate) fn authorization(&self) -> Option<&HeaderValue> {
+        self.authorization.as_ref()
+    }
+
+    /// 连接代理本身
+    pub(crate) async fn connect_proxy(&self) -> io::Result<UpstreamStream> {
+        let tcp = TcpStream::connect(self.addr()).await?;
+        let io = match &self.tls {
+            Some(config) => {
+                let host = self.host.trim_start_matches('[').trim_end_matches(']');
+                let server_name = ServerName::try_from(host.to_string())
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+                let tls = TlsConnector::from(Arc::clone(config))
+                    .connect(server_name, tcp)
+                    .await
+                    .map_err(|err| {
+                        // 保留错误类型，握手时连接被重置等仍计为代理不可达
+                        io::Error::new(err.kind(), format!("HTTPS proxy {}: {}", self.addr(), err))
+                    })?;
+                StreamKind::Tls(Box::new(tls))
+            }
+            None => StreamKind::Tcp(tcp),
+        };
+        Ok(UpstreamStream {
+            io,
+            proxied: true,
+            route: None,
+        })
+    }
+
+    /// 通过 CONNECT 建立到目标的隧道；命中 DNS 覆盖时改为连接覆盖的 IP
+    pub(crate) async fn connect(
+        &self,
+        host: &str,
+        port: u16,
+        resolver: &OverrideResolver,
+    ) -> io::Result<UpstreamStream> {
+        let target = proxy_target(host, resolver);
+        let authority = if target.contains(':') {
+            format!("[{}]:{}", target, port)
+        } else {
+            format!("{}:{}", target, port)
+        };
+        let mut stream = self.connect_proxy().await?;
+        let mut request = format!("CONNECT {authority} HTTP/1.1\r\nHost: {authority}\r\n");
+        if let Some(authorization) = &self.authorization {
+            if let Ok(value) = authorization.to_str() {
+                request.push_str(&format!("Proxy-Authorization: {}\r\n", value));
+            }
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes()).await?;
+
+        let status_line = read_connect_response(&mut stream).await?;
+        let status = status_line.split_whitespace().nth(1).unwrap_or_default();
+        if status != "200" {
+            return Err(io::Error::other(format!(
+                "HTTP proxy {} refused CONNECT {}: {}",
+                self.addr(),
+                authority,
+                status_line
+            )));
+        }
+        stream.proxied = false;
+        Ok(stream)
+    }
+}
+
+/// 读取 CONNECT 响应头并返回状态行，逐字节读取避免吞掉隧道中的数据
+async fn read_connect_response(stream: &mut UpstreamStream) -> io::Result<String> {
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() >= MAX_CONNECT_RESPONSE {
+            return Err(io::Error::other("CONNECT response header too large"));
+        }
+        let byte = stream.read_u8().await.map_err(|err| match err.kind() {
+            io::ErrorKind::UnexpectedEof => {
+                io::Error::new(err.kind(), "Proxy closed connection during CONNECT")
+            }
+            _ => err,
+        })?;
+        head.push(byte);
+    }
+    let head = String::from_utf8_lossy(&head);
+    Ok(head.lines().next().unwrap_or_default().to_string())
+}
+
+/// 外部代理，HTTP(S) 或 SOCKS5
+#[derive(Clone)]
+pub(crate) enum UpstreamProxy {
+    Http(HttpProxy),
+    Socks5(Socks5Proxy),
+}
+
+impl UpstreamProxy {
+    /// 按代理类型（http / socks）创建上游代理，HTTPS 代理为开启 `use_tls` 的 http 类型
+    pub(crate) fn from_entry(
+        proxy_type: &str,
+        config: &MutableExternalProxyConfiguration,
+        trust: &UpstreamTrust,
+    ) -> Result<Self, String> {
+        if !config.is_configured() {
+            return Err("代理配置不完整".to_string());
+        }
+        if proxy_type == "socks" {
+            Ok(Self::Socks5(Socks5Proxy::from_config(config)?))
+        } else {
+            Ok(Self::Http(HttpProxy::from_config(config, trust)?))
+        }
+    }
+
+    fn from_named(upstream: &NamedUpstream, trust: &UpstreamTrust) -> Result<Self, String> {
+        Self::from_entry(
+            &upstream.proxy_type,
+            &upstream.mutable_external_proxy_configuration,
+            trust,
+        )
+        .map_err(|e| format!("上游代理 {}: {}", upstream.name, e))
+    }
+
+    /// 外部代理配置中填写完整的代理：当前模式下的代理条目与全部具名上游，PAC 模式下只有具名上游
+    pub(crate) fn configured(proxy_config: &ExternalProxy, trust: &UpstreamTrust) -> Vec<Self> {
+        if !proxy_config.enabled {
+            return Vec::new();
+        }
+        let entries: &[&str] = if proxy_config.is_pac() {
+            &[]
+        } else if proxy_config.is_socks() {
+            &["socks"]
+        } else {
+            &["http", "https"]
+        };
+        let proxy_type = if proxy_config.is_socks() {
+            "socks"
+        } else {
+            "http"
+        };
+        entries
+            .iter()
+            .filter_map(|name| proxy_config.entry(name))
+            .filter_map(|config| Self::from_entry(proxy_type, config, trust).ok())
+            .chain(
+                proxy_config
+                    .upstreams
+                    .values()
+                    .filter_map(|upstream| Self::from_named(upstream, trust).ok()),
+            )
+            .collect()
+    }
+
+    /// 只连接代理本身，用于健康检查
+    pub(crate) async fn probe(&self) -> io::Result<()> {
+        match self {
+            Self::Http(proxy) => proxy.connect_proxy().await.map(drop),
+            Self::Socks5(proxy) => TcpStream::connect(proxy.addr.as_str()).await.map(drop),
+        }
+    }
+
+    /// 通过代理建立到目标的隧道
+    pub(crate) async fn connect(
+        &self,
+        host: &str,
+        port: u16,
+        resolver: &OverrideResolver,
+    ) -> io::Result<UpstreamStream> {
+        match self {
+            Self::Http(proxy) => proxy.connect(host, port, resolver).await,
+            Self::Socks5(proxy) => proxy.connect(host, port, resolver).await.map(Into::into),
+        }
+    }
+
+    pub(crate) fn label(&self) -> String {
+        match self {
+            Self::Http(proxy) => proxy.label(),
+            Self::Socks5(proxy) => format!("SOCKS5 {}", proxy.addr),
+        }
+    }
+
+    /// 熔断状态的键，地址相同但认证信息不同的代理分别统计，认证信息只参与哈希
+    pub(crate) fn health_key(&self) -> String {
+        let mut hasher = DefaultHasher::new();
+        match self {
+            Self::Http(proxy) => proxy
+                .authorization
+                .as_ref()
+                .map(HeaderValue::as_bytes)
+                .hash(&mut hasher),
+            Self::Socks5(proxy) => proxy.auth.hash(&mut hasher),
+        }
+        format!("{} {:016x}", self.label(), hasher.finish())
+    }
+}
+
+/// 上游路由中的一项
+#[derive(Clone)]
+pub(crate) enum RouteTarget {
+    Direct,
+    Proxy(UpstreamProxy),
+}
+
+impl RouteTarget {
+    /// PAC 只给出代理地址，认证信息沿用 http / socks 条目的配置
+    fn from_pac(
+        pac: &PacProxy,
+        proxy_config: &ExternalProxy,
+        trust: &UpstreamTrust,
+    ) -> Result<Self, String> {
+        let (entry, host, port, use_tls) = match pac {
+            PacProxy::Direct => return Ok(Self::Direct),
+            PacProxy::Http(host, port) => ("http", host, *port, false),
+            PacProxy::Https(host, port) => ("http", host, *port, true),
+            PacProxy::Socks(host, port) => ("socks", host, *port, false),
+        };
+        let mut config = proxy_config.entry(entry).cloned().unwrap_or_default();
+        config.host = host.clone();
+        config.port = port;
+        config.use_tls = use_tls;
+        let proxy = match pac {
+            PacProxy::Socks(..) => UpstreamProxy::Socks5(Socks5Proxy::from_config(&config)?),
+            _ => UpstreamProxy::Http(HttpProxy::from_config(&config, trust)?),
+        };
+        Ok(Self::Proxy(proxy))
+    }
+
+    /// 代理规则或备用上游引用的名称，`DIRECT` 或具名上游
+    fn from_name(
+        name: &str,
+        proxy_config: &ExternalProxy,
+        trust: &UpstreamTrust,
+    ) -> Result<Self, String> {
+        if is_direct_upstream(name) {
+            return Ok(Self::Direct);
+        }
+        let upstream = proxy_config
+            .upstream(name)
+            .ok_or_else(|| format!("上游代理 {} 不存在", name))?;
+        Ok(Self::Proxy(UpstreamProxy::from_named(upstream, trust)?))
+    }
+
+    fn label(&self) -> String {
+        match self {
+            Self::Direct => "DIRECT".to_string(),
+            Self::Proxy(proxy) => proxy.label(),
+        }
+    }
+}
+
+/// 连接上游时实际使用的路由，通过连接的 extra 记录到流量中
+#[derive(Debug, Clone)]
+pub(crate) struct ProxyRoute(pub(crate) String);
+
+/// 外部代理的选择结果，按顺序尝试，连接失败时使用下一项
+#[derive(Clone)]
+pub(crate) struct UpstreamRoute {
+    targets: Vec<RouteTarget>,
+}
+
+impl UpstreamRoute {
+    /// 请求对应的上游路由，未启用、命中绕过列表或未填写代理时返回 None
+    /// PAC 脚本加载或执行失败时与浏览器一样直连
+    pub(crate) async fn resolve(
+        proxy_config: &ExternalProxy,
+        url: &str,
+        trust: &UpstreamTrust,
+    ) -> Result<Option<Self>, String> {
+        if !proxy_config.enabled || proxy_config.is_bypassed(url) {
+            return Ok(None);
+        }
+        // 代理规则优先于 PAC 与默认的代理条目
+        if let Some(rule) = proxy_config.match_rule(url) {
+            let targets = rule
+                .upstreams()
+                .map(|name| RouteTarget::from_name(name, proxy_config, trust))
+                .collect::<Result<_, _>>()?;
+            return Ok(Some(Self { targets }));
+        }
+        if !check_proxy_config(proxy_config, url.to_string()) {
+            return Ok(None);
+        }
+        let mut targets: Vec<RouteTarget> = if proxy_config.is_pac() {
+            match find_proxy_for_url(&proxy_config.pac_url, url, trust).await {
+                Ok(proxies) => proxies
+                    .iter()
+                    .map(|pac| RouteTarget::from_pac(pac, proxy_config, trust))
+                    .collect::<Result<_, _>>()?,
+                Err(err) => {
+                    error!("PAC evaluation failed for {}: {}", url, err);
+                    vec![RouteTarget::Direct]
+                }
+            }
+        } else {
+            let Some(config) = proxy_config.configuration_for(url) else {
+                return Ok(None);
+            };
+            let proxy = if proxy_config.is_socks() {
+                UpstreamProxy::Socks5(Socks5Proxy::from_config(config)?)
+            } else {
+                UpstreamProxy::Http(HttpProxy::from_config(config, trust)?)
+            };
+            vec![RouteTarget::Proxy(proxy)]
+        };
+        for name in &proxy_config.fallback_upstreams {
+            targets.push(RouteTarget::from_name(name, proxy_config, trust)?);
+        }
+        Ok(Some(Self { targets }))
+    }
+
+    /// 明文 HTTP 请求直接发给代理时附带的 Proxy-Authorization
+    /// 仅当路由中全部是认证信息相同的 HTTP 代理时返回，避免回退直连时把认证信息发给目标服务器
+    pub(crate) fn authorization(&self) -> Option<&HeaderValue> {
+        let mut authorization = None;
+        for target in &self.targets {
+            let RouteTarget::Proxy(UpstreamProxy::Http(proxy)) = target else {
+                return None;
+            };
+            let current = proxy.authorization()?;
+            if authorization.is_some_and(|value| value != current) {
+                return None;
+            }
+            authorization = Some(current);
+        }
+        authorization
+    }
+
+    /// 依次尝试路由中的各项，返回第一个成功的连接并记录所用路由
+    ///
+    /// 已熔断的代理排到最后，其余各项均失败时才会尝试；连接代理超时视为代理不可用
+    async fn connect_with<F, Fut>(&self, mut connect: F) -> io::Result<UpstreamStream>
+    where
+        F: FnMut(RouteTarget) -> Fut,
+        Fut: Future<Output = io::Result<UpstreamStream>>,
+    {
+        let (available, tripped): (Vec<&RouteTarget>, Vec<&RouteTarget>) =
+            self.targets.iter().partition(|target| match target {
+                RouteTarget::Direct => true,
+                RouteTarget::Proxy(proxy) => proxy_health::is_available(proxy),
+            });
+        let mut failures: Vec<(String, io::Error)> = Vec::new();
+        for (index, target) in available.iter().chain(&tripped).enumerate() {
+            let result = match target {
+                RouteTarget::Direct => connect((*target).clone()).await,
+                RouteTarget::Proxy(proxy) => {
+                    let result = timeout(PROXY_CONNECT_TIMEOUT, connect((*target).clone()))
+                        .await
+                        .unwrap_or_else(|_| {
+                            Err(io::Error::new(
+                                io::ErrorKind::TimedOut,
+                                "Proxy connection timed out",
+                            ))
+                        });
+                    match &result {
+                        Ok(_) => proxy_health::record_success(proxy),
+                        Err(err) => proxy_health::record_failure(proxy, err),
+                    }
+                    result
+                }
+            };
+            match result {
+                Ok(mut stream) => {
+                    let mut route = target.label();
+                    let skipped: Vec<String> = tripped
+                        .iter()
+                        .skip((index + 1).saturating_sub(available.len()))
+                        .map(|target| target.label())
+                        .collect();
+                    if !skipped.is_empty() {
+                        route.push_str(&format!(" (skipped {})", skipped.join(", ")));
+                    }
+                    if !failures.is_empty() {
+                        let failed: Vec<&str> =
+                            failures.iter().map(|(label, _)| label.as_str()).collect();
+                        route.push_str(&format!(" (after {} failed)", failed.join(", ")));
+                    }
+                    stream.route = Some(route);
+                    return Ok(stream);
+                }
+                Err(err) => failures.push((target.label(), err)),
+            }
+        }
+        let errors: Vec<String> = failures
+            .iter()
+            .map(|(label, err)| format!("{label}: {err}"))
+            .collect();
+        Err(io::Error::other(format!(
+            "All upstream routes failed: {}",
+            errors.join("; ")
+        )))
+    }
+
+    /// 建立到目标的隧道，用于 CONNECT 透传
+    pub(crate) async fn connect(
+        &self,
+        host: &str,
+        port: u16,
+        resolver: &OverrideResolver,
+    ) -> io::Result<UpstreamStream> {
+        self.connect_with(|target| async move {
+            match target {
+                RouteTarget::Direct => connect_direct(host, port, resolver).await,
+                RouteTarget::Proxy(proxy) => proxy.connect(host, port, resolver).await,
+            }
+        })
+        .await
+    }
+}
+
+/// 直连目标，DNS 覆盖表同样生效
+pub(crate) async fn connect_direct(
+    host: &str,
+    port: u16,
+    resolver: &OverrideResolver,
+) -> io::Result<UpstreamStream> {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    let stream = match resolver.lookup(host) {
+        Some(ips) => {
+            let addrs: Vec<SocketAddr> = ips.iter().map(|ip| SocketAddr::new(*ip, port)).collect();
+            TcpStream::connect(addrs.as_slice()).await?
+        }
+        None => TcpStream::connect((host, port)).await?,
+    };
+    Ok(stream.into())
+}
+
+/// 上游 TCP 连接器，未使用外部代理时直接使用 HttpConnector
+/// HTTP 代理下明文请求直接发给代理，HTTPS 请求先通过 CONNECT 建立隧道
+#[derive(Clone)]
+pub(crate) struct TcpConnector {
+    http: HttpConnector<OverrideResolver>,
+    route: Option<UpstreamRoute>,
+    resolver: OverrideResolver,
+    recorder: Option<TimingRecorder>,
+}
+
+impl TcpConnector {
+    pub(crate) fn new(
+        http: HttpConnector<OverrideResolver>,
+        route: Option<UpstreamRoute>,
+        resolver: OverrideResolver,
+    ) -> Self {
+        Self {
+            http,
+            route,
+            resolver,
+            recorder: None,
+        }
+    }
+
+    /// 连接建立后把对端地址记录到请求的计时器中
+    pub(crate) fn with_recorder(mut self, recorder: TimingRecorder) -> Self {
+        self.recorder = Some(recorder);
+        self
+    }
+}
+
+impl Service<Uri> for TcpConnector {
+    type Response = TokioIo<UpstreamStream>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.http.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let recorder = self.recorder.clone();
+        let mark_connected = move |stream: &UpstreamStream| {
+            if let (Some(recorder), Ok(addr)) = (&recorder, stream.peer_addr()) {
+                recorder.mark_connected(addr);
+            }
+        };
+        let Some(route) = self.route.clone() else {
+            let fut = self.http.call(uri);
+            return Box::pin(async move {
+                let stream: UpstreamStream = fut.await?.into_inner().into();
+                mark_connected(&stream);
+                Ok(TokioIo::new(stream))
+            });
+        };
+        let http = self.http.clone();
+        let resolver = self.resolver.clone();
+        Box::pin(async move {
+            let host = uri.host().ok_or("URI has no host")?.to_string();
+            let port = uri.port_u16().unwrap_or(match uri.scheme_str() {
+                Some("https") => 443,
+                _ => 80,
+            });
+            // 请求中带有代理认证时才能直接把明文请求发给需要认证的代理，否则改用 CONNECT
+            let absolute_form = uri.scheme_str() == Some("http");
+            let shared_authorization = route.authorization().is_some();
+            let stream = route
+                .connect_with(|target| {
+                    let mut http = http.clone();
+                    let uri = uri.clone();
+                    let host = host.clone();
+                    let resolver = resolver.clone();
+                    async move {
+                        match target {
+                            RouteTarget::Direct => http
+                                .call(uri)
+                                .await
+                                .map(|stream| stream.into_inner().into())
+                                .map_err(io::Error::other),
+                            RouteTarget::Proxy(UpstreamProxy::Http(proxy))
+                                if absolute_form
+                                    && (shared_authorization
+                                        || proxy.authorization().is_none()) =>
+                            {
+                                proxy.connect_proxy().await
+                            }
+                            RouteTarget::Proxy(proxy) => {
+                                proxy.connect(&host, port, &resolver).await
+                            }
+                        }
+                    }
+                })
+                .await?;
+            mark_connected(&stream);
+            Ok(TokioIo::new(stream))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    fn socks_proxy(port: u16) -> UpstreamProxy {
+        let config: MutableExternalProxyConfiguration = serde_json::from_value(serde_json::json!({
+            "domain": "", "encryptedPassword": "", "host": "127.0.0.1", "port": port,
+            "requiresAuthentication": false, "username": ""
+        }))
+        .unwrap();
+        UpstreamProxy::from_entry("socks", &config, &UpstreamTrust::default()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_connect_with_tripped_last() {
+        let tripped = socks_proxy(3);
+        let healthy = socks_proxy(4);
+        for _ in 0..3 {
+            proxy_health::record_failure(&tripped, &io::ErrorKind::ConnectionRefused.into());
+        }
+        let route = UpstreamRoute {
+            targets: vec![
+                RouteTarget::Proxy(tripped.clone()),
+                RouteTarget::Proxy(healthy.clone()),
+            ],
+        };
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let attempts = Mutex::new(Vec::new());
+
+        let stream = route
+            .connect_with(|target| {
+                attempts.lock().unwrap().push(target.label());
+                async move { Ok(TcpStream::connect(addr).await?.into()) }
+            })
+            .await
+            .unwrap();
+        assert_eq!(*attempts.lock().unwrap(), vec![healthy.label()]);
+        assert_eq!(
+            stream.route(),
+            Some(format!("{} (skipped {})", healthy.label(), tripped.label()).as_str())
+        );
+
+        // 其余各项均失败时才尝试已熔断的代理
+        attempts.lock().unwrap().clear();
+        let result = route
+            .connect_with(|target| {
+                attempts.lock().unwrap().push(target.label());
+                async { Err(io::ErrorKind::PermissionDenied.into()) }
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(
+            *attempts.lock().unwrap(),
+            vec![healthy.label(), tripped.label()]
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use std::{
+    fs,
+    sync::{Arc, LazyLock, Mutex},
+};
+
+use log::error;
+use tokio_rustls::rustls::{
+    client::{
+        danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        VerifierBuilderError, WebPkiServerVerifier,
+    },
+    crypto::{self, CryptoProvider},
+    pki_types::{CertificateDer, ServerName, UnixTime},
+    DigitallySignedStruct, Error, RootCertStore, SignatureScheme,
+};
+
+/// 校验失败时记录的上游证书错误，每个连接器独享一个
+pub(crate) type CertErrorSlot = Arc<Mutex<Option<String>>>;
+
+type RootStoreKey = (bool, Vec<String>);
+
+/// 根证书按配置缓存，避免每个请求都读取系统证书库
+static ROOT_STORE: LazyLock<Mutex<Option<(RootStoreKey, Arc<RootCertStore>)>>> =
+    LazyLock::new(Default::default);
+
+/// 上游证书校验使用的根证书：内置的 webpki 根证书，可选系统证书库和额外的 CA 文件
+pub(crate) fn root_store(native_roots: bool, ca_files: &[String]) -> Arc<RootCertStore> {
+    let key = (native_roots, ca_files.to_vec());
+    let mut cached = ROOT_STORE.lock().unwrap();
+    if let Some((cached_key, roots)) = cached.as_ref() {
+        if *cached_key == key {
+            return Arc::clone(roots);
+        }
+    }
+
+    let mut roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    if native_roots {
+        let native = rustls_native_certs::load_native_certs();
+        for err in &native.errors {
+            error!("Failed to load native root certificate: {}", err);
+        }
+        roots.add_parsable_certificates(native.certs);
+    }
+    for path in ca_files.iter().filter(|path| !path.trim().is_empty()) {
+        let certs = match fs::read(path.trim()) {
+            Ok(data) => rustls_pemfile::certs(&mut data.as_slice())
+                .filter_map(|cert| cert.ok())
+                .collect::<Vec<_>>(),
+            Err(err) => {
+                error!("Failed to read CA file {}: {}", path, err);
+                continue;
+            }
+        };
+        let (added, ignored) = roots.add_parsable_certificates(certs);
+        if added == 0 || ignored > 0 {
+            error!("CA file {}: {} added, {} ignored", path, added, ignored);
+        }
+    }
+
+    let roots = Arc::new(roots);
+    *cached = Some((key, Arc::clone(&roots)));
+    roots
+}
+
+/// 允许无效证书的主机使用的验证器，证书校验失败时只记录错误，握手签名仍然校验
+#[derive(Debug)]
+pub(crate) struct RecordingVerifier {
+    inner: Arc<WebPkiServerVerifier>,
+    error: CertErrorSlot,
+}
+
+impl RecordingVerifier {
+    pub(crate) fn new(
+        roots: Arc<RootCertStore>,
+        error: CertErrorSlot,
+    ) -> Result<Self, VerifierBuilderError> {
+        Ok(Self {
+            inner: WebPkiServerVerifier::builder(roots).build()?,
+            error,
+        })
+    }
+}
+
+impl ServerCertVerifier for RecordingVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, Error> {
+        match self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        ) {
+            Ok(verified) => Ok(verified),
+            Err(err) => {
+                *self.error.lock().unwrap() = Some(err.to_string());
+                Ok(ServerCertVerified::assertion())
+            }
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+/// 不校验上游证书的验证器，只检查握手签名
+#[derive(Debug)]
+pub(crate) struct AcceptAnyCert(Arc<CryptoProvider>);
+
+impl AcceptAnyCert {
+    pub(crate) fn new() -> Self {
+        Self(
+            CryptoProvider::get_default()
+                .cloned()
+                .unwrap_or_else(|| Arc::new(crypto::ring::default_provider())),
+        )
+    }
+}
+
+impl ServerCertVerifier for AcceptAnyCert {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, Error> {
+        crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
@@ -179,80 +179,100 @@ fn uncompress_decoder(
 }
 
 // see https://developer.mozilla.org/en-US/docs/Web/HTTP/Basics_of_HTTP/MIME_types/Common_types
+const MIME_EXTS: [(&str, &str); 78] = [
+    ("audio/aac", ".aac"),
+    ("application/x-abiword", ".abw"),
+    ("image/apng", ".apng"),
+    ("application/x-freearc", ".arc"),
+    ("image/avif", ".avif"),
+    ("video/x-msvideo", ".avi"),
+    ("application/vnd.amazon.ebook", ".azw"),
+    ("application/octet-stream", ".bin"),
+    ("image/bmp", ".bmp"),
+    ("application/x-bzip", ".bz"),
+    ("application/x-bzip2", ".bz2"),
+    ("application/x-cdf", ".cda"),
+    ("application/x-csh", ".csh"),
+    ("text/css", ".css"),
+    ("text/csv", ".csv"),
+    ("application/msword", ".doc"),
+    (
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        ".docx",
+    ),
+    ("application/vnd.ms-fontobject", ".eot"),
+    ("application/epub+zip", ".epub"),
+    ("application/gzip", ".gz"),
+    ("application/x-gzip", ".gz"),
+    ("image/gif", ".gif"),
+    ("text/html", ".html"),
+    ("text/htm", ".html"),
+    ("image/vnd.microsoft.icon", ".ico"),
+    ("text/calendar", ".ics"),
+    ("application/java-archive", ".jar"),
+    ("image/jpeg", ".jpeg"),
+    ("text/javascript", ".js"),
+    ("application/json", ".json"),
+    ("application/ld+json", ".jsonld"),
+    ("audio/midi", ".mid"),
+    ("audio/x-midi", ".mid"),
+    ("audio/mpeg", ".mp3"),
+    ("video/mp4", ".mp4"),
+    ("video/mpeg", ".mpeg"),
+    ("application/vnd.apple.installer+xml", ".mpkg"),
+    ("application/vnd.oasis.opendocument.presentation", ".odp"),
+    ("application/vnd.oasis.opendocument.spreadsheet", ".ods"),
+    ("application/vnd.oasis.opendocument.text", ".odt"),
+    ("audio/ogg", ".oga"),
+    ("video/ogg", ".ogv"),
+    ("application/ogg", ".ogx"),
+    ("font/otf", ".otf"),
+    ("image/png", ".png"),
+    ("application/pdf", ".pdf"),
+    ("application/x-httpd-php", ".php"),
+    ("application/vnd.ms-powerpoint", ".ppt"),
+    (
+        "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        ".pptx",
+    ),
+    ("application/vnd.rar", ".rar"),
+    ("application/rtf", ".rtf"),
+    ("application/x-sh", ".sh"),
+    ("image/svg+xml", ".svg"),
+    ("application/x-tar", ".tar"),
+    ("image/tiff", ".tif"),
+    ("video/mp2t", ".ts"),
+    ("font/ttf", ".ttf"),
+    ("text/plain", ".txt"),
+    ("application/vnd.visio", ".vsd"),
+    ("audio/wav", ".wav"),
+    ("audio/webm", ".weba"),
+    ("video/webm", ".webm"),
+    ("image/webp", ".webp"),
+    ("font/woff", ".woff"),
+    ("font/woff2", ".woff2"),
+    ("application/xhtml+xml", ".xhtml"),
+    ("application/vnd.ms-excel", ".xls"),
+    (
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        ".xlsx",
+    ),
+    ("application/xml", ".xml"),
+    ("text/xml", ".xml"),
+    ("application/vnd.mozilla.xul+xml", ".xul"),
+    ("application/zip", ".zip"),
+    ("x-zip-compressed", ".zip"),
+    ("video/3gpp", ".3gp"),
+    ("audio/3gpp", ".3gp"),
+    ("video/3gpp2", ".3g2"),
+    ("audio/3gpp2", ".3g2"),
+    ("application/x-7z-compressed", ".7z"),
+];
+
 pub fn to_ext_name(mime: &str) -> &str {
-    match mime {
-        "audio/aac" => ".aac",
-        "application/x-abiword" => ".abw",
-        "image/apng" => ".apng",
-        "application/x-freearc" => ".arc",
-        "image/avif" => ".avif",
-        "video/x-msvideo" => ".avi",
-        "application/vnd.amazon.ebook" => ".azw",
-        "application/octet-stream" => ".bin",
-        "image/bmp" => ".bmp",
-        "application/x-bzip" => ".bz",
-        "application/x-bzip2" => ".bz2",
-        "application/x-cdf" => ".cda",
-        "application/x-csh" => ".csh",
-        "text/css" => ".css",
-        "text/csv" => ".csv",
-        "application/msword" => ".doc",
-        "application/vnd.openxmlformats-officedocument.wordprocessingml.document" => ".docx",
-        "application/vnd.ms-fontobject" => ".eot",
-        "application/epub+zip" => ".epub",
-        "application/gzip" | "application/x-gzip" => ".gz",
-        "image/gif" => ".gif",
-        "text/html" | "text/htm" => ".html",
-        "image/vnd.microsoft.icon" => ".ico",
-        "text/calendar" => ".ics",
-        "application/java-archive" => ".jar",
-        "image/jpeg" => ".jpeg",
-        "text/javascript" => ".js",
-        "application/json" => ".json",
-        "application/ld+json" => ".jsonld",
-        "audio/midi" | "audio/x-midi" => ".mid",
-        "audio/mpeg" => ".mp3",
-        "video/mp4" => ".mp4",
-        "video/mpeg" => ".mpeg",
-        "application/vnd.apple.installer+xml" => ".mpkg",
-        "application/vnd.oasis.opendocument.presentation" => ".odp",
-        "application/vnd.oasis.opendocument.spreadsheet" => ".ods",
-        "application/vnd.oasis.opendocument.text" => ".odt",
-        "audio/ogg" => ".oga",
-        "video/ogg" => ".ogv",
-        "application/ogg" => ".ogx",
-        "font/otf" => ".otf",
-        "image/png" => ".png",
-        "application/pdf" => ".pdf",
-        "application/x-httpd-php" => ".php",
-        "application/vnd.ms-powerpoint" => ".ppt",
-        "application/vnd.openxmlformats-officedocument.presentationml.presentation" => ".pptx",
-        "application/vnd.rar" => ".rar",
-        "application/rtf" => ".rtf",
-        "application/x-sh" => ".sh",
-        "image/svg+xml" => ".svg",
-        "application/x-tar" => ".tar",
-        "image/tiff" => ".tif",
-        "video/mp2t" => ".ts",
-        "font/ttf" => ".ttf",
-        "text/plain" => ".txt",
-        "application/vnd.visio" => ".vsd",
-        "audio/wav" => ".wav",
-        "audio/webm" => ".weba",
-        "video/webm" => ".webm",
-        "image/webp" => ".webp",
-        "font/woff" => ".woff",
-        "font/woff2" => ".woff2",
-        "application/xhtml+xml" => ".xhtml",
-        "application/vnd.ms-excel" => ".xls",
-        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet" => ".xlsx",
-        "application/xml" | "text/xml" => ".xml",
-        "application/vnd.mozilla.xul+xml" => ".xul",
-        "application/zip" | "x-zip-compressed" => ".zip",
-        "video/3gpp" | "audio/3gpp" => ".3gp",
-        "video/3gpp2" | "audio/3gpp2" => ".3g2",
-        "application/x-7z-compressed" => ".7z",
-        _ => {
+    match MIME_EXTS.iter().find(|(v, _)| *v == mime) {
+        Some((_, ext)) => ext,
+        None => {
             if mime.starts_with("text/") {
                 ".txt"
             } else {
@@ -262,6 +282,22 @@ pub fn to_ext_name(mime: &str) -> &str {
     }
 }
 
+/// 根据扩展名反查 MIME 类型，`to_ext_name` 的逆过程
+pub fn from_ext_name(ext: &str) -> Option<&'static str> {
+    let ext = format!(".{}", ext.trim_start_matches('.').to_lowercase());
+    let ext = match ext.as_str() {
+        ".htm" => ".html",
+        ".jpg" => ".jpeg",
+        ".mjs" => ".js",
+        ".tiff" => ".tif",
+        v => v,
+    };
+    MIME_EXTS
+        .iter()
+        .find(|(_, v)| *v == ext)
+        .map(|(mime, _)| *mime)
+}
+
 pub fn to_md_lang(mime: &str) -> &str {
     if let Some(value) = mime
         .strip_prefix("text/")
@@ -326,6 +362,16 @@ mod tests {
         assert_eq!(to_md_lang("text/x-rust"), "rust");
         assert_eq!(to_md_lang("text/css"), "css");
     }
+
+    #[test]
+    fn test_from_ext_name() {
+        assert_eq!(from_ext_name("json"), Some("application/json"));
+        assert_eq!(from_ext_name(".HTML"), Some("text/html"));
+        assert_eq!(from_ext_name("htm"), Some("text/html"));
+        assert_eq!(from_ext_name("JPG"), Some("image/jpeg"));
+        assert_eq!(from_ext_name("mjs"), Some("text/javascript"));
+        assert_eq!(from_ext_name("unknown-ext"), None);
+    }
 }
 
 #[derive(Error, Debug)]
@@ -31,3 +31,106 @@ export function changeMonitorTraffic(
 export function getCurrentListenSessionID(): Promise<string | "Fail"> {
   return ipc.invoke("get_monitor_session_id");
 }
+
+// 自动识别出的证书固定主机
+export interface PinnedHost {
+  host: string;
+  port: number;
+  reason: string;
+  learnedAt: string;
+}
+
+// 获取已识别的证书固定主机
+export function getPinnedHosts(): Promise<PinnedHost[]> {
+  return ipc.invoke("get_pinned_hosts");
+}
+
+/**
+ * 清除已识别的证书固定主机
+ * @param keys host:port 列表，为空时清除全部
+ * @returns
+ */
+export function clearPinnedHosts(keys: string[] = []): Promise<void> {
+  return ipc.invoke("clear_pinned_hosts", {
+    keys
+  });
+}
+
+// CA 私钥类型
+export type CaKeyType = "rsa2048" | "ecdsaP256";
+
+// CA 导出格式，pkcs12 包含私钥
+export type CaExportFormat = "pem" | "der" | "pkcs12";
+
+/**
+ * 重新生成 CA，正在运行的代理服务会自动重启
+ * @param keyType 私钥类型
+ * @param validityDays 有效天数
+ * @returns
+ */
+export function rotateCa(
+  keyType: CaKeyType,
+  validityDays: number
+): Promise<string> {
+  return ipc.invoke("rotate_ca", {
+    keyType,
+    validityDays
+  });
+}
+
+/**
+ * 导入已有的 CA
+ * @param path 证书文件（PEM/DER），未传 keyPath 时为 PKCS#12 文件
+ * @param keyPath PEM 私钥文件
+ * @param password PKCS#12 密码
+ * @returns
+ */
+export function importCa(
+  path: string,
+  keyPath?: string,
+  password?: string
+): Promise<string> {
+  return ipc.invoke("import_ca", {
+    path,
+    keyPath,
+    password
+  });
+}
+
+/**
+ * 导出 CA
+ * @param format 导出格式
+ * @param path 保存路径
+ * @param password PKCS#12 密码
+ * @returns
+ */
+export function exportCa(
+  format: CaExportFormat,
+  path: string,
+  password?: string
+): Promise<string> {
+  return ipc.invoke("export_ca", {
+    format,
+    path,
+    password
+  });
+}
+
+/**
+ * 测试代理条目的连通性与认证
+ * @param proxyType 代理类型，http / https / socks
+ * @param config 代理配置，密码需已加密
+ * @param target 通过代理连接的目标 host:port，默认 example.com:443
+ * @returns
+ */
+export function testExternalProxy(
+  proxyType: string,
+  config: unknown,
+  target?: string
+): Promise<string> {
+  return ipc.invoke("test_external_proxy", {
+    proxyType,
+    config,
+    target
+  });
+}
@@ -125,6 +125,8 @@ export interface Params {
     urlEditData: urlData;
   };
   header: string;
+  // 改写后实际发往上游的请求头
+  forwardedHeader?: string;
   text: string;
   json: string | undefined;
   cookie: DataItem[];
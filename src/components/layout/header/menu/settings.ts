@@ -56,8 +56,44 @@ export const handleSetCharlesPath = async () => {
   await settingStore.set("charlesPath", path);
 };
 
+const handleSslPinning = async () => {
+  await windowManager.createWindow(
+    {
+      url: "/setting/sslPinning"
+    },
+    {
+      title: "证书固定检测",
+      width: 600,
+      height: 460
+    }
+  );
+};
+
+const handleCaManage = async () => {
+  await windowManager.createWindow(
+    {
+      url: "/setting/ca"
+    },
+    {
+      title: "根证书管理",
+      width: 520,
+      height: 420
+    }
+  );
+};
+
 export const settingsMenuItems = [
   { label: "Set Port", action: "set-port", click: handleSetPort },
+  {
+    label: "SSL Pinning Detection",
+    action: "ssl-pinning",
+    click: handleSslPinning
+  },
+  {
+    label: "Root Certificate",
+    action: "root-certificate",
+    click: handleCaManage
+  },
   {
     label: "Set Charles Path",
     action: "set-charles-path",
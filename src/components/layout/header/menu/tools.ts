@@ -13,6 +13,159 @@ const goToMapLocal = () => {
   );
 };
 
+const goToMapRemote = () => {
+  windowManager.createWindow(
+    {
+      url: "/mapRemote"
+    },
+    {
+      width: 800,
+      height: 600,
+      title: "Map Remote"
+    }
+  );
+};
+
+const goToRewrite = () => {
+  windowManager.createWindow(
+    {
+      url: "/rewrite"
+    },
+    {
+      width: 800,
+      height: 600,
+      title: "Rewrite"
+    }
+  );
+};
+
+const goToBlockList = () => {
+  windowManager.createWindow(
+    {
+      url: "/blockList"
+    },
+    {
+      width: 800,
+      height: 600,
+      title: "Block List"
+    }
+  );
+};
+
+const goToAllowList = () => {
+  windowManager.createWindow(
+    {
+      url: "/blockList",
+      param: { mode: "allow" }
+    },
+    {
+      width: 800,
+      height: 600,
+      title: "Allow List"
+    }
+  );
+};
+
+const goToThrottle = () => {
+  windowManager.createWindow(
+    {
+      url: "/throttle"
+    },
+    {
+      width: 800,
+      height: 520,
+      title: "Throttle"
+    }
+  );
+};
+
+const goToFault = () => {
+  windowManager.createWindow(
+    {
+      url: "/fault"
+    },
+    {
+      width: 800,
+      height: 600,
+      title: "Fault Injection"
+    }
+  );
+};
+
+const goToDnsOverride = () => {
+  windowManager.createWindow(
+    {
+      url: "/dnsOverride"
+    },
+    {
+      width: 800,
+      height: 600,
+      title: "DNS Override"
+    }
+  );
+};
+
+const goToSslProxying = () => {
+  windowManager.createWindow(
+    {
+      url: "/sslProxying"
+    },
+    {
+      width: 700,
+      height: 680,
+      title: "SSL Proxying"
+    }
+  );
+};
+
+const goToClientCert = () => {
+  windowManager.createWindow(
+    {
+      url: "/clientCert"
+    },
+    {
+      width: 800,
+      height: 600,
+      title: "Client Certificates"
+    }
+  );
+};
+
+const goToUpstreamTrust = () => {
+  windowManager.createWindow(
+    {
+      url: "/upstreamTrust"
+    },
+    {
+      width: 700,
+      height: 600,
+      title: "Upstream Trust"
+    }
+  );
+};
+
 export const toolsMenuItems = [
-  { label: "Map Local", action: "map local", click: goToMapLocal }
+  { label: "Map Local", action: "map local", click: goToMapLocal },
+  { label: "Map Remote", action: "map remote", click: goToMapRemote },
+  { label: "Rewrite", action: "rewrite", click: goToRewrite },
+  { label: "Block List", action: "block list", click: goToBlockList },
+  { label: "Allow List", action: "allow list", click: goToAllowList },
+  { label: "Throttle Settings", action: "throttle", click: goToThrottle },
+  { label: "Fault Injection", action: "fault injection", click: goToFault },
+  { label: "DNS Override", action: "dns override", click: goToDnsOverride },
+  {
+    label: "SSL Proxying Settings",
+    action: "ssl proxying",
+    click: goToSslProxying
+  },
+  {
+    label: "Client Certificates",
+    action: "client certificates",
+    click: goToClientCert
+  },
+  {
+    label: "Upstream Trust",
+    action: "upstream trust",
+    click: goToUpstreamTrust
+  }
 ];
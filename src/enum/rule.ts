@@ -0,0 +1,3 @@
+export enum RuleEvent {
+  SUBMIT = "rule-submit"
+}
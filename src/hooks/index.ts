@@ -4,6 +4,8 @@ import useEventBus from "./useEventBus";
 import useUrlParams from "./useUrlParams";
 import useExport from "./useExport";
 import useImport from "./useImport";
+import useRuleConfig from "./useRuleConfig";
+import useRuleEditor from "./useRuleEditor";
 
 export {
   useBreakpointConfig,
@@ -11,5 +13,7 @@ export {
   useEventBus,
   useUrlParams,
   useExport,
-  useImport
+  useImport,
+  useRuleConfig,
+  useRuleEditor
 };
@@ -0,0 +1,82 @@
+import { onBeforeMount, ref, type Ref } from "vue";
+import { message } from "ant-design-vue";
+import { error } from "@tauri-apps/plugin-log";
+import { useSettingStore } from "@/stores/settings";
+import { windowManager } from "@/stores/WindowManager";
+import { RuleEvent } from "@/enum/rule";
+import { deepClone } from "@/utils/tools";
+
+interface EditorOptions {
+  url: string;
+  title: string;
+  width: number;
+  height: number;
+}
+
+/**
+ * 工具配置窗口：读取 settings.json 中的配置，点击完成时整体写回
+ * @param storeKey 配置在 settings.json 中的键
+ * @param defaultValue 未配置时的默认值
+ */
+export default function useRuleConfig<T extends object>(
+  storeKey: string,
+  defaultValue: T
+) {
+  const settingStore = useSettingStore();
+  const config = ref(deepClone(defaultValue)) as Ref<T>;
+
+  onBeforeMount(async () => {
+    const s = await settingStore.store;
+    const value = await s.get<Partial<T>>(storeKey);
+    config.value = { ...deepClone(defaultValue), ...value };
+  });
+
+  const cancel = async () => {
+    await windowManager.requestClose();
+  };
+
+  const save = async () => {
+    try {
+      await settingStore.set(storeKey, config.value);
+      await cancel();
+    } catch (e) {
+      error("保存配置失败" + e);
+      message.error(`保存配置失败：${e}`);
+    }
+  };
+
+  /**
+   * 打开编辑窗口，提交后通过 onSubmit 写回列表
+   * @param item 编辑的条目，为空时新建
+   */
+  const openEditor = async <I>(
+    options: EditorOptions,
+    item: I | undefined,
+    onSubmit: (item: I) => void
+  ) => {
+    // 窗口 label 由 url 截断生成，t 放在最前面保证每次打开的窗口不重复
+    const param: Record<string, string> = { t: Date.now().toString(36) };
+    if (item) param.item = JSON.stringify(item);
+    const [wvw] = await windowManager.createWindow(
+      { url: options.url, param },
+      {
+        title: options.title,
+        width: options.width,
+        height: options.height
+      }
+    );
+    const unListen = await wvw.listen(RuleEvent.SUBMIT, (event) => {
+      onSubmit(event.payload as I);
+      unListen();
+    });
+  };
+
+  return { config, save, cancel, openEditor };
+}
+
+/**
+ * 生成规则 id
+ * @param prefix 工具名前缀
+ */
+export const generateRuleId = (prefix: string) =>
+  `${prefix}_${Date.now()}_${Math.random().toString(36).slice(2, 11)}`;
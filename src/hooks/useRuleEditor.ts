@@ -0,0 +1,27 @@
+import { ref, type Ref } from "vue";
+import { windowInit, windowManager } from "@/stores/WindowManager";
+import { RuleEvent } from "@/enum/rule";
+import { deepClone } from "@/utils/tools";
+
+/**
+ * 规则编辑窗口：从窗口参数中读取编辑的条目，提交后通知列表窗口
+ * @param defaultItem 新建时的默认值
+ */
+export default function useRuleEditor<T extends object>(defaultItem: T) {
+  const prams = windowInit();
+  const item = ref(deepClone(defaultItem)) as Ref<T>;
+  if (typeof prams.item === "string") {
+    item.value = { ...deepClone(defaultItem), ...JSON.parse(prams.item) };
+  }
+
+  const cancel = async () => {
+    await windowManager.requestClose();
+  };
+
+  const submit = async () => {
+    await windowManager.window.emit(RuleEvent.SUBMIT, item.value);
+    await cancel();
+  };
+
+  return { item, isNew: typeof prams.item !== "string", submit, cancel };
+}
@@ -5,7 +5,16 @@ import {
   settingRoute,
   externalProxyRoute,
   searchRoute,
-  mapLocal
+  mapLocal,
+  mapRemote,
+  rewrite,
+  blockList,
+  throttle,
+  fault,
+  dnsOverride,
+  sslProxying,
+  clientCert,
+  upstreamTrust
 } from "./routes";
 
 const routes = [
@@ -14,7 +23,16 @@ const routes = [
   ...settingRoute,
   ...externalProxyRoute,
   ...searchRoute,
-  ...mapLocal
+  ...mapLocal,
+  ...mapRemote,
+  ...rewrite,
+  ...blockList,
+  ...throttle,
+  ...fault,
+  ...dnsOverride,
+  ...sslProxying,
+  ...clientCert,
+  ...upstreamTrust
 ];
 
 const router = createRouter({
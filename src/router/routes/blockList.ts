@@ -0,0 +1,10 @@
+export default [
+  {
+    path: "/blockList",
+    component: () => import("@/window/blockList/index.vue")
+  },
+  {
+    path: "/blockList/edit",
+    component: () => import("@/window/blockList/edit/index.vue")
+  }
+];
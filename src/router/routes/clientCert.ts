@@ -0,0 +1,10 @@
+export default [
+  {
+    path: "/clientCert",
+    component: () => import("@/window/clientCert/index.vue")
+  },
+  {
+    path: "/clientCert/edit",
+    component: () => import("@/window/clientCert/edit/index.vue")
+  }
+];
@@ -0,0 +1,10 @@
+export default [
+  {
+    path: "/dnsOverride",
+    component: () => import("@/window/dnsOverride/index.vue")
+  },
+  {
+    path: "/dnsOverride/edit",
+    component: () => import("@/window/dnsOverride/edit/index.vue")
+  }
+];
@@ -0,0 +1,10 @@
+export default [
+  {
+    path: "/fault",
+    component: () => import("@/window/fault/index.vue")
+  },
+  {
+    path: "/fault/edit",
+    component: () => import("@/window/fault/edit/index.vue")
+  }
+];
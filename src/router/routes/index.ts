@@ -3,11 +3,29 @@ import settingRoute from "./setting";
 import externalProxyRoute from "./externalProxy";
 import searchRoute from "./search";
 import mapLocal from "./mapLocal";
+import mapRemote from "./mapRemote";
+import rewrite from "./rewrite";
+import blockList from "./blockList";
+import throttle from "./throttle";
+import fault from "./fault";
+import dnsOverride from "./dnsOverride";
+import sslProxying from "./sslProxying";
+import clientCert from "./clientCert";
+import upstreamTrust from "./upstreamTrust";
 
 export {
   breakpointRoute,
   settingRoute,
   externalProxyRoute,
   searchRoute,
-  mapLocal
+  mapLocal,
+  mapRemote,
+  rewrite,
+  blockList,
+  throttle,
+  fault,
+  dnsOverride,
+  sslProxying,
+  clientCert,
+  upstreamTrust
 };
@@ -0,0 +1,10 @@
+export default [
+  {
+    path: "/mapRemote",
+    component: () => import("@/window/mapRemote/index.vue")
+  },
+  {
+    path: "/mapRemote/edit",
+    component: () => import("@/window/mapRemote/edit/index.vue")
+  }
+];
@@ -0,0 +1,10 @@
+export default [
+  {
+    path: "/rewrite",
+    component: () => import("@/window/rewrite/index.vue")
+  },
+  {
+    path: "/rewrite/edit",
+    component: () => import("@/window/rewrite/edit/index.vue")
+  }
+];
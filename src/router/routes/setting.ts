@@ -2,5 +2,13 @@ export default [
   {
     path: "/setting/port",
     component: () => import("@/window/settings/port/index.vue")
+  },
+  {
+    path: "/setting/sslPinning",
+    component: () => import("@/window/settings/sslPinning/index.vue")
+  },
+  {
+    path: "/setting/ca",
+    component: () => import("@/window/settings/ca/index.vue")
   }
 ];
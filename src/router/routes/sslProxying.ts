@@ -0,0 +1,10 @@
+export default [
+  {
+    path: "/sslProxying",
+    component: () => import("@/window/sslProxying/index.vue")
+  },
+  {
+    path: "/sslProxying/edit",
+    component: () => import("@/window/sslProxying/edit/index.vue")
+  }
+];
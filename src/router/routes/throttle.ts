@@ -0,0 +1,10 @@
+export default [
+  {
+    path: "/throttle",
+    component: () => import("@/window/throttle/index.vue")
+  },
+  {
+    path: "/throttle/edit",
+    component: () => import("@/window/throttle/edit/index.vue")
+  }
+];
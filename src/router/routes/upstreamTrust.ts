@@ -0,0 +1,10 @@
+export default [
+  {
+    path: "/upstreamTrust",
+    component: () => import("@/window/upstreamTrust/index.vue")
+  },
+  {
+    path: "/upstreamTrust/edit",
+    component: () => import("@/window/upstreamTrust/edit/index.vue")
+  }
+];
@@ -9,6 +9,8 @@ import type { WebviewWindow } from "@tauri-apps/api/webviewWindow";
 import { error } from "@tauri-apps/plugin-log";
 import { useSessionStore } from "./session";
 import { ezSearch } from "@/api/search";
+import type { PinnedHost } from "@/api/server";
+import { ElNotification } from "element-plus";
 
 export enum TransactionState {
   Pending = "Pending", // 初始化/等待发送
@@ -17,7 +19,8 @@ export enum TransactionState {
   ResponseDone = "ResponseDone", // 响应接收完成
   Completed = "Completed", // 完整完成
   Failed = "Failed", // 失败
-  Aborted = "Aborted" // 中止
+  Aborted = "Aborted", // 中止
+  Blocked = "Blocked" // 被拦截
 }
 
 // 保留原有的 TrafficData
@@ -35,6 +38,9 @@ export interface TrafficData
   transaction_state: TransactionState;
   start_time: string | null;
   session_id: string;
+  client_addr: string | null;
+  connection_id: number | null;
+  server_addr: string | null;
 }
 
 // 头部项接口
@@ -105,9 +111,34 @@ interface Overview extends Record<string, any> {
   code?: number | undefined | null;
   protocol?: string | undefined | null;
 }
+// 证书摘要
+interface ICertificateInfo {
+  subject: string;
+  issuer: string;
+  sans: string[];
+  serial: string;
+  not_before: string;
+  not_after: string;
+}
+
+// TLS 握手信息
+interface ITlsSession {
+  sni: string | null;
+  alpn_offered: string[];
+  alpn: string | null;
+  version: string | null;
+  cipher: string | null;
+  certificates: ICertificateInfo[];
+}
 interface ITrafficDataDetail extends Record<string, any> {
   overview: Overview;
+  client_tls: ITlsSession | null;
+  server_tls: ITlsSession | null;
+  client_cert: string | null;
+  upstream_cert_error: string | null;
+  proxy_route: string | null;
   req_head_json: string;
+  forwarded_req_head_json: string | null;
   res_head_json: string;
   req_body_hex: HexBody[];
   res_body_hex: HexBody[];
@@ -140,6 +171,7 @@ type PauseTrafficHandler = (
 type ResendTrafficHandler = (
   payload: Payload<[string, TrafficEditData<IHeaders>]>
 ) => Promise<void>;
+type SslPinningHandler = (payload: Payload<PinnedHost>) => void;
 
 export const useTrafficStore = defineStore("traffic", () => {
   const ipc = useIpc();
@@ -364,6 +396,14 @@ export const useTrafficStore = defineStore("traffic", () => {
         await createTrafficWindow(windowManager, setBreakpointPauseListener);
       };
 
+      const sslPinningHandler: SslPinningHandler = (payload) => {
+        ElNotification({
+          title: "SSL Pinning",
+          message: payload.message,
+          type: "warning"
+        });
+      };
+
       // 分别注册各种处理器
       const unlistenNewTraffic = await ipc.listen<Payload<TrafficData>>(
         "new-traffic",
@@ -380,6 +420,12 @@ export const useTrafficStore = defineStore("traffic", () => {
         Payload<[string, TrafficEditData<IHeaders>]>
       >("resend-traffic", resendTrafficHandler);
       unListenList.value.push(unlistenResendTraffic);
+
+      const unlistenSslPinning = await ipc.listen<Payload<PinnedHost>>(
+        "ssl-pinning-detected",
+        sslPinningHandler
+      );
+      unListenList.value.push(unlistenSslPinning);
     } catch (e) {
       error("Failed to setup traffic monitor:" + e);
       throw new Error(`Traffic monitor setup failed: ${e}`);
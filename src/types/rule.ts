@@ -0,0 +1,49 @@
+// 与后端 RuleMatcher 对应的匹配规则，各工具的规则通过展开字段复用
+export type MatchType = "contains" | "wildcard" | "regex";
+
+export interface RuleMatcher {
+  url: string;
+  matchType: MatchType;
+  scheme: string;
+  host: string;
+  port: string;
+  path: string;
+  query: string;
+  methods: string[];
+}
+
+export const defaultRuleMatcher: RuleMatcher = {
+  url: "",
+  matchType: "contains",
+  scheme: "",
+  host: "",
+  port: "",
+  path: "",
+  query: "",
+  methods: []
+};
+
+// 列表中展示的 Location
+export const describeMatcher = (matcher: RuleMatcher) => {
+  if (matcher.url) return matcher.url;
+  const port = matcher.port ? `:${matcher.port}` : "";
+  const query = matcher.query ? `?${matcher.query}` : "";
+  const scheme = matcher.scheme ? `${matcher.scheme}://` : "";
+  return `${scheme}${matcher.host || "*"}${port}${matcher.path}${query}`;
+};
+
+// 后端规则未配置任何条件时不会命中
+export const isEmptyMatcher = (matcher: RuleMatcher) =>
+  !matcher.url &&
+  !matcher.scheme &&
+  !matcher.host &&
+  !matcher.port &&
+  !matcher.path &&
+  !matcher.query;
+
+// 规则列表的列，value 返回单元格中展示的文本
+export interface RuleColumn<T> {
+  title: string;
+  width?: string;
+  value: (item: T) => string;
+}
@@ -0,0 +1,57 @@
+import { defaultRuleMatcher, type RuleMatcher } from "@/types/rule";
+
+export type BlockMode = "block" | "allow";
+
+// 拦截后的处理方式，dropConnection 直接断开连接
+export type BlockAction = "response" | "dropConnection";
+
+export interface BlockListItem extends RuleMatcher {
+  id: string;
+  enabled: boolean;
+}
+
+type ListKey = "blockLists" | "allowLists";
+
+/**
+ * @description: BlockList 与 AllowList 共用的配置
+ * AllowList 开启后未命中规则的请求都会被拦截
+ * @param key id
+ */
+export type BlockListConfig = {
+  toolEnabled: boolean;
+  action: BlockAction;
+  statusCode: number;
+  body: string;
+} & Partial<Record<ListKey, Record<string, BlockListItem>>>;
+
+export const blockModes: Record<
+  BlockMode,
+  { storeKey: string; listKey: ListKey; label: string; description: string }
+> = {
+  block: {
+    storeKey: "blockList",
+    listKey: "blockLists",
+    label: "黑名单功能",
+    description: "拦截命中规则的请求，优先于白名单检查"
+  },
+  allow: {
+    storeKey: "allowList",
+    listKey: "allowLists",
+    label: "白名单功能",
+    description: "只放行命中规则的请求，其余请求都会被拦截"
+  }
+};
+
+export const defaultBlockListConfig = (mode: BlockMode): BlockListConfig => ({
+  toolEnabled: false,
+  action: "response",
+  statusCode: 403,
+  body: "",
+  [blockModes[mode].listKey]: {}
+});
+
+export const defaultBlockListItem: BlockListItem = {
+  ...defaultRuleMatcher,
+  id: "",
+  enabled: true
+};
@@ -0,0 +1,42 @@
+export type ClientCertFormat = "pem" | "pkcs12";
+
+// host 支持通配符，多条命中时优先使用不含通配符的配置
+export interface ClientCertItem {
+  id: string;
+  enabled: boolean;
+  host: string;
+  // 身份名称，为空时使用证书 subject
+  name: string;
+  format: ClientCertFormat;
+  // PEM 证书链或 PKCS#12 文件
+  certPath: string;
+  // PEM 私钥文件，为空时从证书文件中读取
+  keyPath: string;
+  // PKCS#12 的密码，使用 cryptoService 加密后保存
+  encryptedPassword: string;
+}
+
+/**
+ * @description: 连接上游时出示的客户端证书
+ * @param key id
+ */
+export interface ClientCerts {
+  toolEnabled: boolean;
+  clientCerts: Record<string, ClientCertItem>;
+}
+
+export const defaultClientCerts: ClientCerts = {
+  toolEnabled: false,
+  clientCerts: {}
+};
+
+export const defaultClientCertItem: ClientCertItem = {
+  id: "",
+  enabled: true,
+  host: "",
+  name: "",
+  format: "pem",
+  certPath: "",
+  keyPath: "",
+  encryptedPassword: ""
+};
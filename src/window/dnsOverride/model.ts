@@ -0,0 +1,36 @@
+// host 支持通配符，ips 为覆盖后的 IPv4/IPv6 地址
+export interface DnsOverrideItem {
+  id: string;
+  enabled: boolean;
+  host: string;
+  ips: string[];
+}
+
+/**
+ * @description: 连接上游时使用的 DNS 覆盖表
+ * @param key id
+ */
+export interface DnsOverride {
+  toolEnabled: boolean;
+  dnsOverrides: Record<string, DnsOverrideItem>;
+}
+
+export const defaultDnsOverride: DnsOverride = {
+  toolEnabled: false,
+  dnsOverrides: {}
+};
+
+export const defaultDnsOverrideItem: DnsOverrideItem = {
+  id: "",
+  enabled: true,
+  host: "",
+  ips: []
+};
+
+const IPV4 = /^(\d{1,3})(\.\d{1,3}){3}$/;
+
+// 粗略校验，后端解析失败的地址会被忽略
+export const isIpAddress = (value: string) =>
+  IPV4.test(value)
+    ? value.split(".").every((part) => Number(part) <= 255)
+    : value.includes(":") && /^[0-9a-fA-F:.]+$/.test(value);
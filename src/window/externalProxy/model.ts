@@ -2,7 +2,8 @@
 enum ProxyType {
   SOCKS = "socks",
   HTTP = "http",
-  HTTPS = "https"
+  HTTPS = "https",
+  PAC = "pac"
 }
 
 // 代理配置项接口
@@ -13,6 +14,8 @@ interface MutableExternalProxyConfiguration {
   domain: string;
   username: string;
   encryptedPassword: string;
+  // 与代理之间使用 TLS 连接
+  useTls: boolean;
 }
 
 interface Entry {
@@ -20,6 +23,28 @@ interface Entry {
   mutableExternalProxyConfiguration: MutableExternalProxyConfiguration;
 }
 
+// 具名上游代理，HTTPS 代理使用 http 类型并开启 useTls
+interface NamedUpstream {
+  id: string;
+  name: string;
+  proxyType: ProxyType.HTTP | ProxyType.SOCKS;
+  mutableExternalProxyConfiguration: MutableExternalProxyConfiguration;
+}
+
+// 按顺序匹配的代理规则，host 支持通配符与 CIDR（只匹配 IP 形式的主机），
+// upstream 为上游名称或 DIRECT
+interface ProxyRule {
+  id: string;
+  enabled: boolean;
+  host: string;
+  port: string;
+  upstream: string;
+  // upstream 不可用时按顺序尝试的备用上游
+  fallbacks: string[];
+}
+
+const DIRECT_UPSTREAM = "DIRECT";
+
 // 主接口
 interface ExternalProxy {
   configurations: { entry: Entry[] };
@@ -29,6 +54,12 @@ interface ExternalProxy {
   enabled: boolean;
   proxyType: ProxyType;
   alwaysBypassLocalhost: boolean;
+  // PAC 脚本的本地路径或 URL
+  pacUrl: string;
+  upstreams: Record<string, NamedUpstream>;
+  proxyRules: ProxyRule[];
+  // 协议条目或 PAC 给出的代理不可用时按顺序尝试的备用上游
+  fallbackUpstreams: string[];
 }
 
 const defaultData: ExternalProxy = {
@@ -42,7 +73,8 @@ const defaultData: ExternalProxy = {
           port: 8080,
           domain: "",
           username: "",
-          encryptedPassword: ""
+          encryptedPassword: "",
+          useTls: false
         }
       },
       {
@@ -53,7 +85,8 @@ const defaultData: ExternalProxy = {
           port: 443,
           domain: "",
           username: "",
-          encryptedPassword: ""
+          encryptedPassword: "",
+          useTls: false
         }
       },
       {
@@ -64,7 +97,8 @@ const defaultData: ExternalProxy = {
           port: 1080,
           domain: "",
           username: "",
-          encryptedPassword: ""
+          encryptedPassword: "",
+          useTls: false
         }
       }
     ]
@@ -74,8 +108,18 @@ const defaultData: ExternalProxy = {
   },
   proxyType: ProxyType.HTTP,
   enabled: false,
-  alwaysBypassLocalhost: true
+  alwaysBypassLocalhost: true,
+  pacUrl: "",
+  upstreams: {},
+  proxyRules: [],
+  fallbackUpstreams: []
 };
 
-export type { MutableExternalProxyConfiguration, Entry, ExternalProxy };
-export { ProxyType, defaultData };
+export type {
+  MutableExternalProxyConfiguration,
+  Entry,
+  ExternalProxy,
+  NamedUpstream,
+  ProxyRule
+};
+export { ProxyType, DIRECT_UPSTREAM, defaultData };
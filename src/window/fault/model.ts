@@ -0,0 +1,63 @@
+import { defaultRuleMatcher, type RuleMatcher } from "@/types/rule";
+
+// errorStatus 直接返回错误状态码，不请求上游
+export type FaultType = "delay" | "reset" | "errorStatus" | "truncate";
+
+// probability 为触发概率（0-100），afterBytes 按解码后的 body 计算
+export interface FaultItem extends RuleMatcher {
+  id: string;
+  enabled: boolean;
+  faultType: FaultType;
+  probability: number;
+  delayMs: number;
+  statusCode: number;
+  body: string;
+  afterBytes: number;
+}
+
+/**
+ * @description: 故障注入，同一请求每种类型只取一条，Reset 优先于 Truncate
+ * @param key id
+ */
+export interface FaultInjection {
+  toolEnabled: boolean;
+  faults: Record<string, FaultItem>;
+}
+
+export const defaultFaultInjection: FaultInjection = {
+  toolEnabled: false,
+  faults: {}
+};
+
+export const defaultFaultItem: FaultItem = {
+  ...defaultRuleMatcher,
+  id: "",
+  enabled: true,
+  faultType: "delay",
+  probability: 100,
+  delayMs: 1000,
+  statusCode: 503,
+  body: "",
+  afterBytes: 0
+};
+
+export const faultTypeOptions: { label: string; value: FaultType }[] = [
+  { label: "延迟响应", value: "delay" },
+  { label: "断开连接", value: "reset" },
+  { label: "错误状态码", value: "errorStatus" },
+  { label: "截断 Body", value: "truncate" }
+];
+
+// 列表中展示的故障，与后端 FaultItem::describe 一致
+export const describeFault = (item: FaultItem) => {
+  switch (item.faultType) {
+    case "delay":
+      return `delay ${item.delayMs}ms`;
+    case "reset":
+      return `reset after ${item.afterBytes} bytes`;
+    case "errorStatus":
+      return `status ${item.statusCode}`;
+    case "truncate":
+      return `truncate after ${item.afterBytes} bytes`;
+  }
+};
@@ -1,9 +1,23 @@
+export type MatchType = "contains" | "wildcard" | "regex";
+
 export interface MapLocalItem {
   id?: string;
   enabled: boolean;
+  priority?: number;
   url: string;
+  matchType?: MatchType;
+  scheme?: string;
+  host?: string;
+  port?: string;
+  path?: string;
+  query?: string;
+  methods?: string[];
   headerLocal: string;
   bodyLocal: string;
+  dirLocal?: string;
+  statusCode?: number;
+  reasonPhrase?: string;
+  template?: boolean;
 }
 
 /**
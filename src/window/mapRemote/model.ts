@@ -0,0 +1,39 @@
+import { defaultRuleMatcher, type RuleMatcher } from "@/types/rule";
+
+export interface MapRemoteItem extends RuleMatcher {
+  id: string;
+  enabled: boolean;
+  priority: number;
+  toScheme: string;
+  toHost: string;
+  toPort: number | null;
+  // 为空时保留原始路径
+  toPath: string;
+  preserveHostHeader: boolean;
+}
+
+/**
+ * @description: 将请求转发到其他地址
+ * @param key id
+ */
+export interface MapRemote {
+  toolEnabled: boolean;
+  mapRemotes: Record<string, MapRemoteItem>;
+}
+
+export const defaultMapRemote: MapRemote = {
+  toolEnabled: false,
+  mapRemotes: {}
+};
+
+export const defaultMapRemoteItem: MapRemoteItem = {
+  ...defaultRuleMatcher,
+  id: "",
+  enabled: true,
+  priority: 0,
+  toScheme: "",
+  toHost: "",
+  toPort: null,
+  toPath: "",
+  preserveHostHeader: false
+};
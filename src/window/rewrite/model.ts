@@ -0,0 +1,100 @@
+import type { RuleMatcher } from "@/types/rule";
+
+export type RewriteRuleType =
+  | "addHeader"
+  | "modifyHeader"
+  | "removeHeader"
+  | "addQueryParam"
+  | "modifyQueryParam"
+  | "removeQueryParam"
+  | "url"
+  | "body"
+  | "status";
+
+// matchName/matchValue 为空时匹配任意值，matchRegex 开启后 replaceValue 可以使用 $1
+export interface RewriteRule {
+  id: string;
+  enabled: boolean;
+  ruleType: RewriteRuleType;
+  request: boolean;
+  response: boolean;
+  matchName: string;
+  matchValue: string;
+  matchRegex: boolean;
+  replaceName: string;
+  replaceValue: string;
+  replaceAll: boolean;
+}
+
+export interface RewriteSet {
+  id: string;
+  name: string;
+  enabled: boolean;
+  priority: number;
+  locations: RuleMatcher[];
+  rules: RewriteRule[];
+}
+
+/**
+ * @description: 按规则集改写请求和响应
+ * @param key id
+ */
+export interface Rewrite {
+  toolEnabled: boolean;
+  rewriteSets: Record<string, RewriteSet>;
+}
+
+export const defaultRewrite: Rewrite = {
+  toolEnabled: false,
+  rewriteSets: {}
+};
+
+export const defaultRewriteSet: RewriteSet = {
+  id: "",
+  name: "",
+  enabled: true,
+  priority: 0,
+  locations: [],
+  rules: []
+};
+
+export const defaultRewriteRule: RewriteRule = {
+  id: "",
+  enabled: true,
+  ruleType: "addHeader",
+  request: true,
+  response: false,
+  matchName: "",
+  matchValue: "",
+  matchRegex: false,
+  replaceName: "",
+  replaceValue: "",
+  replaceAll: false
+};
+
+export const ruleTypeOptions: { label: string; value: RewriteRuleType }[] = [
+  { label: "Add Header", value: "addHeader" },
+  { label: "Modify Header", value: "modifyHeader" },
+  { label: "Remove Header", value: "removeHeader" },
+  { label: "Add Query Param", value: "addQueryParam" },
+  { label: "Modify Query Param", value: "modifyQueryParam" },
+  { label: "Remove Query Param", value: "removeQueryParam" },
+  { label: "URL", value: "url" },
+  { label: "Body", value: "body" },
+  { label: "Response Status", value: "status" }
+];
+
+// 列表中展示的规则
+export const describeRule = (rule: RewriteRule) => {
+  const type =
+    ruleTypeOptions.find((option) => option.value === rule.ruleType)?.label ??
+    rule.ruleType;
+  const phase = [rule.request && "请求", rule.response && "响应"]
+    .filter(Boolean)
+    .join("/");
+  const match = [rule.matchName, rule.matchValue].filter(Boolean).join(": ");
+  const replace = [rule.replaceName, rule.replaceValue]
+    .filter(Boolean)
+    .join(": ");
+  return `${type} [${phase}] ${match} → ${replace}`;
+};
@@ -0,0 +1,21 @@
+import type { PinnedHost } from "@/api/server";
+
+/**
+ * @description: 证书固定检测
+ * @param pinnedHosts key 为 host:port
+ */
+export interface SslPinning {
+  toolEnabled: boolean;
+  // 连续握手失败多少次后判定为证书固定
+  threshold: number;
+  pinnedHosts: Record<string, PinnedHost>;
+}
+
+export const defaultSslPinning: SslPinning = {
+  toolEnabled: false,
+  threshold: 3,
+  pinnedHosts: {}
+};
+
+export const pinnedKey = (host: PinnedHost) =>
+  `${host.host.toLowerCase()}:${host.port}`;
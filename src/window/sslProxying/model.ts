@@ -0,0 +1,31 @@
+// host 与 port 均支持通配符，port 为空时匹配任意端口
+export interface SslProxyingLocation {
+  id: string;
+  enabled: boolean;
+  host: string;
+  port: string;
+}
+
+/**
+ * @description: 需要解密的 HTTPS 主机
+ * includes 为空时包含全部主机，命中 excludes 的主机直接透传
+ * @param key id
+ */
+export interface SslProxying {
+  toolEnabled: boolean;
+  includes: Record<string, SslProxyingLocation>;
+  excludes: Record<string, SslProxyingLocation>;
+}
+
+export const defaultSslProxying: SslProxying = {
+  toolEnabled: false,
+  includes: {},
+  excludes: {}
+};
+
+export const defaultSslProxyingLocation: SslProxyingLocation = {
+  id: "",
+  enabled: true,
+  host: "",
+  port: ""
+};
@@ -0,0 +1,34 @@
+// 带宽单位为 kbps，为 0 时不限制；hosts 支持通配符，为空时作用于全部主机
+export interface ThrottleProfile {
+  id: string;
+  name: string;
+  downloadKbps: number;
+  uploadKbps: number;
+  latencyMs: number;
+  hosts: string[];
+}
+
+/**
+ * @description: 网络限速，只有 activeProfile 对应的配置生效
+ * @param key id
+ */
+export interface Throttle {
+  toolEnabled: boolean;
+  activeProfile: string;
+  throttleProfiles: Record<string, ThrottleProfile>;
+}
+
+export const defaultThrottle: Throttle = {
+  toolEnabled: false,
+  activeProfile: "",
+  throttleProfiles: {}
+};
+
+export const defaultThrottleProfile: ThrottleProfile = {
+  id: "",
+  name: "",
+  downloadKbps: 0,
+  uploadKbps: 0,
+  latencyMs: 0,
+  hosts: []
+};
@@ -0,0 +1,30 @@
+// 证书校验失败时仍然继续连接的主机，host 支持通配符
+export interface AllowInvalidCertHost {
+  id: string;
+  enabled: boolean;
+  host: string;
+}
+
+/**
+ * @description: 上游证书校验配置，默认只信任内置的 webpki 根证书
+ * @param key id
+ */
+export interface UpstreamTrust {
+  // 同时信任系统证书库
+  nativeRoots: boolean;
+  // 额外信任的 CA 证书文件（PEM）
+  caFiles: string[];
+  allowInvalidHosts: Record<string, AllowInvalidCertHost>;
+}
+
+export const defaultUpstreamTrust: UpstreamTrust = {
+  nativeRoots: false,
+  caFiles: [],
+  allowInvalidHosts: {}
+};
+
+export const defaultAllowInvalidCertHost: AllowInvalidCertHost = {
+  id: "",
+  enabled: true,
+  host: ""
+};